@@ -1,15 +1,32 @@
 use matrix::Matrix;
 use species::Species;
 use camera::Camera;
+use representation::Representation;
 
 // ============================================================
 // Atom
 // ============================================================
 /// The atom, the fundamental unit of a molecular viewer.
+#[derive(Clone)]
 pub struct Atom<'a> {
-    _species      : &'a Species<'a>,
-    _position     : [f32;3],
-    _model_matrix : Matrix,
+    _species          : &'a Species<'a>,
+    _position         : [f32;3],
+    _model_matrix     : Matrix,
+    /// A custom label to draw next to the atom.
+    _label            : Option<String>,
+    /// Overrides the molecule's representation for this atom.
+    _representation   : Option<Representation>,
+    /// Overrides the colour scheme for this atom.
+    _colour           : Option<[f32;3]>,
+    /// Numbers from the structure file, e.g. "charge" or "spin".
+    _properties       : Vec<(String, f32)>,
+    /// Groups from the structure file, e.g. "residue" = "HOH".
+    _categories       : Vec<(String, String)>,
+    /// False if the atom has been hidden.
+    _visible          : bool,
+    /// The coordinates a .cell file gave, and whether they were fractional (or else Å
+    /// from the cell's corner), to be written back as they were while the atom stays put.
+    _cell_coordinates : Option<([f64;3], bool)>,
 }
 
 impl<'a> Atom<'a> {
@@ -18,27 +35,84 @@ impl<'a> Atom<'a> {
         in_position : &[f32;3],
     ) -> Atom<'a> {
         Atom {
-            _species      : in_species,
-            _position     : in_position.to_owned(),
-            _model_matrix : Matrix::new([
+            _species          : in_species,
+            _position         : in_position.to_owned(),
+            _model_matrix     : Matrix::new([
                 [*in_species.size(), 0.0               , 0.0               , in_position[0]],
                 [0.0               , *in_species.size(), 0.0               , in_position[1]],
                 [0.0               , 0.0               , *in_species.size(), in_position[2]],
                 [0.0               , 0.0               , 0.0               , 1.0           ]
             ]),
+            _label            : None,
+            _representation   : None,
+            _colour           : None,
+            _properties       : Vec::new(),
+            _categories       : Vec::new(),
+            _visible          : true,
+            _cell_coordinates : None,
         }
     }
 
     pub fn species(&self) -> &Species<'a> {&self._species}
+    pub fn position(&self) -> &[f32;3] {&self._position}
+    /// Moves the atom. The model matrix catches up when it is next turned to the camera.
+    pub fn set_position(&mut self, in_position : &[f32;3]) {self._position = *in_position}
     pub fn model_matrix(&self) -> &Matrix {&self._model_matrix}
+    pub fn label(&self) -> Option<&str> {self._label.as_ref().map(|label| label.as_str())}
+    pub fn set_label(&mut self, in_label : &str) {self._label = Some(in_label.to_string())}
+    pub fn properties(&self) -> &Vec<(String, f32)> {&self._properties}
+    pub fn categories(&self) -> &Vec<(String, String)> {&self._categories}
 
-    pub fn rotate_against_camera(&mut self, in_camera : &Camera) {
+    pub fn property(&self, in_name : &str) -> Option<f32> {
+        self._properties.iter().find(|property| property.0 == in_name).map(|property| property.1)
+    }
+
+    pub fn set_property(&mut self, in_name : &str, in_value : &f32) {
+        match self._properties.iter_mut().find(|property| property.0 == in_name) {
+            Some(property) => property.1 = *in_value,
+            None => self._properties.push((in_name.to_string(), *in_value)),
+        }
+    }
+
+    pub fn remove_property(&mut self, in_name : &str) {
+        self._properties.retain(|property| property.0 != in_name);
+    }
+
+    pub fn category(&self, in_name : &str) -> Option<&str> {
+        self._categories.iter().find(|category| category.0 == in_name).map(|category| category.1.as_str())
+    }
+
+    pub fn set_category(&mut self, in_name : &str, in_value : &str) {
+        match self._categories.iter_mut().find(|category| category.0 == in_name) {
+            Some(category) => category.1 = in_value.to_string(),
+            None => self._categories.push((in_name.to_string(), in_value.to_string())),
+        }
+    }
+
+    pub fn visible(&self) -> bool {self._visible}
+    pub fn set_visible(&mut self, in_visible : &bool) {self._visible = *in_visible}
+
+    pub fn colour(&self) -> Option<[f32;3]> {self._colour}
+    pub fn set_colour(&mut self, in_colour : &Option<[f32;3]>) {self._colour = *in_colour}
+
+    pub fn cell_coordinates(&self) -> Option<([f64;3], bool)> {self._cell_coordinates}
+    pub fn set_cell_coordinates(&mut self, in_coordinates : &[f64;3], in_fractional : &bool) {
+        self._cell_coordinates = Some((*in_coordinates, *in_fractional))
+    }
+
+    pub fn representation(&self) -> Option<Representation> {self._representation}
+    pub fn set_representation(&mut self, in_representation : &Option<Representation>) {
+        self._representation = *in_representation
+    }
+
+    /// Turns the atom to face the camera, scaled to in_radius.
+    pub fn rotate_against_camera(&mut self, in_camera : &Camera, in_radius : &f32) {
 
         let translation_and_scaling_matrix = Matrix::new ([
-            [*self._species.size(), 0.0, 0.0, self._position[0]],
-            [0.0, *self._species.size(), 0.0, self._position[1]],
-            [0.0, 0.0, *self._species.size(), self._position[2]],
-            [0.0, 0.0, 0.0                  , 1.0              ]
+            [*in_radius, 0.0, 0.0, self._position[0]],
+            [0.0, *in_radius, 0.0, self._position[1]],
+            [0.0, 0.0, *in_radius, self._position[2]],
+            [0.0, 0.0, 0.0       , 1.0              ]
         ]);
         
         let mut quaternion = in_camera.quaternion().to_owned();
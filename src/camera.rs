@@ -1,9 +1,74 @@
 extern crate glium;
 
 use std::f32; // pi
+use std::time::Instant;
 
 use matrix::Matrix;
 use quaternion::Quaternion;
+use vector::{sub, dot, cross, scale, normalised};
+
+// ============================================================
+// Easing
+// ============================================================
+/// How an animated camera move speeds up and slows down.
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    SmoothStep,
+    CubicInOut,
+}
+
+impl Easing {
+    /// Maps the fraction of time elapsed (0 to 1) to the fraction of the move made.
+    pub fn apply(&self, in_t : &f32) -> f32 {
+        let t = in_t.max(0.0).min(1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::SmoothStep => t*t*(3.0-2.0*t),
+            Easing::CubicInOut => if t < 0.5 {
+                4.0*t*t*t
+            } else {
+                1.0-(2.0-2.0*t).powi(3)/2.0
+            },
+        }
+    }
+}
+
+// ============================================================
+// Animation
+// ============================================================
+/// Where the camera is and which way it faces.
+#[derive(Copy, Clone)]
+struct Pose {
+    focus      : [f32;3],
+    r          : f32,
+    quaternion : Quaternion,
+    view_scale : f32,
+}
+
+/// A camera move in progress.
+struct Animation {
+    _from  : Pose,
+    _to    : Pose,
+    _start : Instant,
+}
+
+// ============================================================
+// Camera state
+// ============================================================
+/// Everything needed to reproduce a view exactly, e.g. for a saved bookmark.
+#[derive(Copy, Clone, Debug)]
+pub struct CameraState {
+    pub focus                 : [f32;3],
+    pub r                     : f32,
+    pub quaternion            : Quaternion,
+    pub field_of_view_degrees : f32,
+    pub orthographic          : bool,
+    pub view_scale            : f32,
+    pub near_plane            : f32,
+    pub far_plane             : f32,
+}
 
 // ============================================================
 // Camera
@@ -18,7 +83,20 @@ pub struct Camera {
     _field_of_view      : f32,
     _near_plane         : f32,
     _far_plane          : f32,
+    /// Clipping planes restored from a saved view, rather than fitted to the structure,
+    /// as how far they are in front of and behind its centre, so they move with it.
+    _fixed_clipping     : Option<[f32;2]>,
+    /// The bounding sphere of the structure being viewed, used to place the clipping planes.
+    _scene_centre       : [f32;3],
+    _scene_radius       : f32,
+    _orthographic       : bool,
+    /// Half the height of the view in orthographic mode.
+    _view_scale         : f32,
     _screen_size        : [u32;2],
+    /// Moves such as resetting or changing view direction are animated.
+    _animation          : Option<Animation>,
+    _transition_seconds : f32,
+    _easing             : Easing,
     _view_matrix        : Matrix,
     _perspective_matrix : Matrix,
     _vp_matrix          : Matrix,
@@ -31,10 +109,8 @@ impl Camera {
         in_theta_degrees         : &f32,
         in_phi_degrees           : &f32,
         in_psi_degrees           : &f32,
-        in_r                     : &f32,
+        in_scene_radius          : &f32,
         in_field_of_view_degrees : &f32,
-        in_near_plane            : &f32,
-        in_far_plane             : &f32
     ) -> Camera {
 
         let (w, h) = (*in_display).get_framebuffer_dimensions();
@@ -44,38 +120,49 @@ impl Camera {
 
         let mut camera = Camera {
             _focus              : in_focus.to_owned(),
-            _r                  : in_r.to_owned(),
+            _r                  : 1.0,                        // set by fit_to_sphere
             _quaternion         : Quaternion::new(&1.0,&0.0,&0.0,&0.0),
             _cos_half_step      : half_step_radians.cos(),
             _sin_half_step      : half_step_radians.sin(),
             _r_step             : 0.1,
             _field_of_view      : in_field_of_view_degrees*f32::consts::PI/180.0,
-            _near_plane         : in_near_plane.to_owned(),
-            _far_plane          : in_far_plane.to_owned(),
+            _near_plane         : 0.1,                        // set by update
+            _far_plane          : 10.0,                       // set by update
+            _fixed_clipping     : None,
+            _scene_centre       : in_focus.to_owned(),
+            _scene_radius       : 1.0,                        // set by fit_to_sphere
+            _orthographic       : false,
+            _view_scale         : 1.0,                        // set by fit_to_sphere
             _screen_size        : [w, h],
+            _animation          : None,
+            _transition_seconds : 0.0,                        // start in place, then see set_transition
+            _easing             : Easing::SmoothStep,
             _view_matrix        : Matrix::new([[0.0;4];4]),   // dummy value
             _perspective_matrix : Matrix::new([[0.0;4];4]),   // dummy value
             _vp_matrix          : Matrix::new([[0.0;4];4]),   // dummy value
         };
-        camera.set_angles(in_theta_degrees, in_phi_degrees, in_psi_degrees, in_r);
+        camera.set_angles(in_theta_degrees, in_phi_degrees, in_psi_degrees);
+        camera.fit_to_sphere(in_focus, in_scene_radius);
         camera
     }
     
     pub fn view_matrix(&self) -> &Matrix {&self._view_matrix}
     pub fn vp_matrix(&self) -> &Matrix {&self._vp_matrix}
     pub fn quaternion(&self) -> &Quaternion {&self._quaternion}
+    pub fn orthographic(&self) -> bool {self._orthographic}
+    pub fn screen_size(&self) -> &[u32;2] {&self._screen_size}
 
     pub fn set_angles(
         &mut self,
         in_theta_degrees : &f32,
         in_phi_degrees   : &f32,
         in_psi_degrees   : &f32,
-        in_r             : &f32
     ) {
         let half_theta_radians = in_theta_degrees*f32::consts::PI/360.0;
         let half_phi_radians = in_phi_degrees*f32::consts::PI/360.0;
         let half_psi_radians = in_psi_degrees*f32::consts::PI/360.0;
-        self._quaternion = Quaternion::new(
+        let mut target = self.target_pose();
+        target.quaternion = Quaternion::new(
             &half_psi_radians.cos(),
             &0.0,
             &0.0,
@@ -91,14 +178,187 @@ impl Camera {
             &half_phi_radians.sin(),
             &0.0,
         );
-        
-        self._r = in_r.to_owned();
+        self.animate_to(&target);
+    }
+
+    /// Points the camera at in_centre from far enough away that a sphere of
+    /// in_radius fills the view, keeping the current orientation.
+    /// The clipping planes then follow the camera distance so the sphere is never cut.
+    pub fn fit_to_sphere(&mut self, in_centre : &[f32;3], in_radius : &f32) {
+        let radius = if *in_radius > 0.0 {*in_radius} else {1.0};
+        let margin = 1.05;
+        self._fixed_clipping = None;
+        self._scene_centre = in_centre.to_owned();
+        self._scene_radius = radius;
+        self._r_step = radius/20.0;
+        let mut target = self.target_pose();
+        target.focus = in_centre.to_owned();
+        target.r = margin*radius/(self._field_of_view/2.0).sin();
+        target.view_scale = margin*radius;
+        self.animate_to(&target);
+    }
+
+    /// Keeps the clipping planes clear of a scene that has grown or shrunk to in_radius,
+    /// e.g. when atoms are drawn bigger, without moving the camera.
+    pub fn set_scene_radius(&mut self, in_radius : &f32) {
+        if *in_radius > 0.0 {
+            self._scene_radius = *in_radius;
+        }
+    }
+
+    /// Sets how long animated moves take. Zero makes them instant.
+    pub fn set_transition(&mut self, in_seconds : &f32, in_easing : &Easing) {
+        self._transition_seconds = in_seconds.max(0.0);
+        self._easing = *in_easing;
+    }
+
+    pub fn state(&self) -> CameraState {
+        let pose = self.target_pose();
+        CameraState {
+            focus                 : pose.focus,
+            r                     : pose.r,
+            quaternion            : pose.quaternion,
+            field_of_view_degrees : self._field_of_view*180.0/f32::consts::PI,
+            orthographic          : self._orthographic,
+            view_scale            : pose.view_scale,
+            near_plane            : self._near_plane,
+            far_plane             : self._far_plane,
+        }
+    }
+
+    /// Moves to a saved state. The clipping planes stay where they were saved relative
+    /// to the structure, following it when zooming or panning, until it is next fitted to the view.
+    pub fn set_state(&mut self, in_state : &CameraState) {
+        self._field_of_view = in_state.field_of_view_degrees*f32::consts::PI/180.0;
+        self._orthographic = in_state.orthographic;
+        // how far the centre of the structure was from the camera when the view was saved
+        let mut quaternion = in_state.quaternion;
+        quaternion.normalise();
+        let relative = sub(&self._scene_centre, &in_state.focus);
+        let centre = quaternion.rotation_matrix()*[relative[0], relative[1], relative[2], 0.0];
+        let distance = (centre[0]*centre[0]+centre[1]*centre[1]+(centre[2]+in_state.r).powi(2)).sqrt();
+        self._fixed_clipping = Some([distance-in_state.near_plane, in_state.far_plane-distance]);
+        self.animate_to(&Pose {
+            focus      : in_state.focus,
+            r          : in_state.r,
+            quaternion : in_state.quaternion,
+            view_scale : in_state.view_scale,
+        });
         self.update();
     }
 
-    pub fn zoom_in (&mut self) {if self._r > self._r_step {self._r -= self._r_step} self.update();}
-    pub fn zoom_out (&mut self) {self._r += self._r_step; self.update();}
+    fn pose(&self) -> Pose {
+        Pose {
+            focus      : self._focus,
+            r          : self._r,
+            quaternion : self._quaternion,
+            view_scale : self._view_scale,
+        }
+    }
+
+    fn set_pose(&mut self, in_pose : &Pose) {
+        self._focus = in_pose.focus;
+        self._r = in_pose.r;
+        self._quaternion = in_pose.quaternion;
+        self._view_scale = in_pose.view_scale;
+    }
+
+    /// Where the camera will end up once any animation has finished,
+    /// so that moves made in quick succession add together.
+    fn target_pose(&self) -> Pose {
+        match self._animation {
+            Some(ref animation) => animation._to,
+            None => self.pose(),
+        }
+    }
+
+    fn animate_to(&mut self, in_target : &Pose) {
+        if self._transition_seconds > 0.0 {
+            self._animation = Some(Animation {
+                _from  : self.pose(),
+                _to    : *in_target,
+                _start : Instant::now(),
+            });
+        } else {
+            self._animation = None;
+            self.set_pose(in_target);
+            self.update();
+        }
+    }
+
+    /// Stops any animation where it is, so that the user can take over.
+    fn stop_animation(&mut self) {self._animation = None}
+
+    pub fn is_animating(&self) -> bool {self._animation.is_some()}
+
+    /// Moves the camera along any animation in progress. Call once a frame.
+    pub fn tick(&mut self) {
+        let (from, to, t) = match self._animation {
+            Some(ref animation) => (
+                animation._from,
+                animation._to,
+                animation._start.elapsed().as_secs_f32()/self._transition_seconds,
+            ),
+            None => return,
+        };
+        if t >= 1.0 {
+            self._animation = None;
+            self.set_pose(&to);
+        } else {
+            let s = self._easing.apply(&t);
+            let mut focus = [0.0;3];
+            for l in 0..3 {
+                focus[l] = from.focus[l]+s*(to.focus[l]-from.focus[l]);
+            }
+            self.set_pose(&Pose {
+                focus      : focus,
+                r          : from.r+s*(to.r-from.r),
+                quaternion : Quaternion::slerp(&from.quaternion, &to.quaternion, &s),
+                view_scale : from.view_scale+s*(to.view_scale-from.view_scale),
+            });
+        }
+        self.update();
+    }
+
+    fn tan_half_fov(&self) -> f32 {(self._field_of_view/2.0).tan()}
+
+    /// Switches between perspective and orthographic projection.
+    /// The orthographic view is scaled to match what was visible at the focus,
+    /// so the structure stays the same size on screen.
+    pub fn toggle_projection (&mut self) {
+        self.stop_animation();
+        if self._orthographic {
+            self._r = self._view_scale/self.tan_half_fov();
+        } else {
+            self._view_scale = self._r*self.tan_half_fov();
+        }
+        self._orthographic = !self._orthographic;
+        self.update();
+    }
+
+    // In orthographic mode the distance makes no difference to the picture,
+    // so zooming changes the view scale by the equivalent amount instead.
+    pub fn zoom_in (&mut self) {
+        self.stop_animation();
+        if self._orthographic {
+            let scale_step = self._r_step*self.tan_half_fov();
+            if self._view_scale > scale_step {self._view_scale -= scale_step}
+        } else if self._r > self._r_step {
+            self._r -= self._r_step;
+        }
+        self.update();
+    }
+    pub fn zoom_out (&mut self) {
+        self.stop_animation();
+        if self._orthographic {
+            self._view_scale += self._r_step*self.tan_half_fov();
+        } else {
+            self._r += self._r_step;
+        }
+        self.update();
+    }
     pub fn spin_clockwise (&mut self) {
+        self.stop_animation();
         self._quaternion.left_multiply(&Quaternion::new(
             &self._cos_half_step,
             &0.0,
@@ -108,6 +368,7 @@ impl Camera {
 	    self.update();
     }
     pub fn spin_anticlockwise (&mut self) {
+        self.stop_animation();
         self._quaternion.left_multiply(&Quaternion::new(
             &self._cos_half_step,
             &0.0,
@@ -117,6 +378,7 @@ impl Camera {
 	self.update();
     }
     pub fn azimuth_up (&mut self) {
+        self.stop_animation();
         self._quaternion.left_multiply(&Quaternion::new(
             &self._cos_half_step,
             &self._sin_half_step,
@@ -126,6 +388,7 @@ impl Camera {
         self.update();
     }
     pub fn azimuth_down (&mut self) {
+        self.stop_animation();
         self._quaternion.left_multiply(&Quaternion::new(
             &self._cos_half_step,
             &-self._sin_half_step,
@@ -135,6 +398,7 @@ impl Camera {
         self.update();
     }
     pub fn orbit_right (&mut self) {
+        self.stop_animation();
         self._quaternion.left_multiply(&Quaternion::new(
             &self._cos_half_step,
             &0.0,
@@ -144,6 +408,7 @@ impl Camera {
         self.update();
     }
     pub fn orbit_left (&mut self) {
+        self.stop_animation();
         self._quaternion.left_multiply(&Quaternion::new(
             &self._cos_half_step,
             &0.0,
@@ -153,33 +418,99 @@ impl Camera {
         self.update();
     }
     
+    /// Turns the camera to look along in_direction, with in_up as near to
+    /// straight up the screen as possible. in_up must not be parallel to in_direction.
+    pub fn look_along (&mut self, in_direction : &[f32;3], in_up : &[f32;3]) {
+        // the rows of the rotation are the world directions of the screen axes
+        let forward = normalised(in_direction);
+        let up = normalised(&sub(in_up, &scale(&forward, &dot(in_up, &forward))));
+        let right = cross(&up, &forward);
+        let mut target = self.target_pose();
+        target.quaternion = Quaternion::from_rotation_matrix(&[right, up, forward]);
+        self.animate_to(&target);
+    }
+
+    /// Applies a rotation in view space (x right, y up, z into the screen).
+    pub fn rotate (&mut self, in_rotation : &Quaternion) {
+        self.stop_animation();
+        self._quaternion.left_multiply(in_rotation);
+        self.update();
+    }
+
+    /// Moves the focus so the structure follows a drag of in_dx, in_dy pixels
+    /// (y down, as reported by the window).
+    pub fn pan (&mut self, in_dx : &f32, in_dy : &f32) {
+        self.stop_animation();
+        // the world distance across the shorter side of the screen at the focus
+        let visible = if self._orthographic {
+            2.0*self._view_scale
+        } else {
+            2.0*self._r*self.tan_half_fov()
+        };
+        let pixels = self._screen_size[0].min(self._screen_size[1]).max(1) as f32;
+        let units_per_pixel = visible/pixels;
+
+        let mut inverse = self._quaternion;
+        inverse.invert();
+        let shift = inverse.rotate_vector(&[-in_dx*units_per_pixel, in_dy*units_per_pixel, 0.0]);
+        for l in 0..3 {
+            self._focus[l] += shift[l];
+        }
+        self.update();
+    }
+
+    /// Scales the camera distance (or the view scale, if orthographic) by in_factor.
+    pub fn zoom_by (&mut self, in_factor : &f32) {
+        self.stop_animation();
+        if self._orthographic {
+            self._view_scale *= *in_factor;
+        } else {
+            self._r *= *in_factor;
+        }
+        self.update();
+    }
+
     pub fn set_screen_size(&mut self, in_x : &u32, in_y : &u32) {
         self._screen_size = [*in_x, *in_y];
         self.update();
     }
+
+    /// Where the camera is, in world space, or None if the view can't be undone.
+    pub fn position(&self) -> Option<[f32;3]> {
+        let position = self._view_matrix.inverse()?*[0.0, 0.0, 0.0, 1.0];
+        Some([position[0], position[1], position[2]])
+    }
+
+    /// The pixel (from the top-left of the window) and depth (in normalised
+    /// device coordinates) of a point, or None if it is behind the camera.
+    pub fn project(&self, in_point : &[f32;3]) -> Option<([f32;2], f32)> {
+        let clip = self._vp_matrix*[in_point[0], in_point[1], in_point[2], 1.0];
+        if clip[3] <= 0.0 {return None;}
+        let w = self._screen_size[0] as f32;
+        let h = self._screen_size[1] as f32;
+        Some((
+            [(clip[0]/clip[3]+1.0)*w/2.0, (1.0-clip[1]/clip[3])*h/2.0],
+            clip[2]/clip[3],
+        ))
+    }
+
+    /// The ray under a pixel, as a world-space origin and unit direction,
+    /// found by un-projecting the pixel at the near and far planes.
+    /// None if the projection can't be undone, e.g. for a window with no size.
+    pub fn ray(&self, in_pixel : &[i32;2]) -> Option<([f32;3], [f32;3])> {
+        let x = 2.0*(in_pixel[0] as f32)/(self._screen_size[0].max(1) as f32)-1.0;
+        let y = 1.0-2.0*(in_pixel[1] as f32)/(self._screen_size[1].max(1) as f32);
+        let inverse = self._vp_matrix.inverse()?;
+        let unproject = |z : f32| -> [f32;3] {
+            let point = inverse*[x, y, z, 1.0];
+            [point[0]/point[3], point[1]/point[3], point[2]/point[3]]
+        };
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+        Some((near, normalised(&sub(&far, &near))))
+    }
     
     pub fn update(&mut self) {
-        // Update perspective matrix
-        let mut w = self._screen_size[0] as f32;
-        let mut h = self._screen_size[1] as f32;
-        if w > h {
-            w = w/h;
-            h = 1.0;
-        } else {
-            h = h/w;
-            w = 1.0;
-        }
-        
-        let s = 1.0/(self._field_of_view/2.0).tan();
-        let n = self._near_plane.to_owned();
-        let f = self._far_plane.to_owned();
-        self._perspective_matrix = Matrix::new([
-            [s/w, 0.0, 0.0        , 0.0          ],
-            [0.0, s/h, 0.0        , 0.0          ],
-            [0.0, 0.0, (f+n)/(f-n), 2.0*f*n/(n-f)],
-            [0.0, 0.0, 1.0        , 0.0          ]
-        ]);
-        
         // Translate so that the focus is centred.
         let focus_translation_matrix = Matrix::new([
             [1.0, 0.0, 0.0, -self._focus[0]],
@@ -207,7 +538,57 @@ impl Camera {
         self._view_matrix = zoom_matrix
                           * rotation_matrix
                           * focus_translation_matrix;
+
+        // Clip just outside the structure, but never behind the camera.
+        // The camera sits at the origin of view space.
+        let centre = self._view_matrix*[
+            self._scene_centre[0],
+            self._scene_centre[1],
+            self._scene_centre[2],
+            1.0
+        ];
+        let distance = (centre[0]*centre[0]+centre[1]*centre[1]+centre[2]*centre[2]).sqrt();
+        match self._fixed_clipping {
+            Some(planes) => {
+                self._near_plane = (distance-planes[0]).max(0.01*self._scene_radius);
+                self._far_plane = (distance+planes[1]).max(self._near_plane+0.01*self._scene_radius);
+            },
+            None => {
+                self._near_plane = (distance-self._scene_radius).max(0.01*self._scene_radius);
+                self._far_plane = distance+self._scene_radius;
+            },
+        }
+
+        // Update perspective matrix
+        let mut w = self._screen_size[0] as f32;
+        let mut h = self._screen_size[1] as f32;
+        if w > h {
+            w = w/h;
+            h = 1.0;
+        } else {
+            h = h/w;
+            w = 1.0;
+        }
+        
+        let n = self._near_plane.to_owned();
+        let f = self._far_plane.to_owned();
+        if self._orthographic {
+            let s = 1.0/self._view_scale;
+            self._perspective_matrix = Matrix::new([
+                [s/w, 0.0, 0.0        , 0.0          ],
+                [0.0, s/h, 0.0        , 0.0          ],
+                [0.0, 0.0, 2.0/(f-n)  , (f+n)/(n-f)  ],
+                [0.0, 0.0, 0.0        , 1.0          ]
+            ]);
+        } else {
+            let s = 1.0/self.tan_half_fov();
+            self._perspective_matrix = Matrix::new([
+                [s/w, 0.0, 0.0        , 0.0          ],
+                [0.0, s/h, 0.0        , 0.0          ],
+                [0.0, 0.0, (f+n)/(f-n), 2.0*f*n/(n-f)],
+                [0.0, 0.0, 1.0        , 0.0          ]
+            ]);
+        }
         self._vp_matrix = self._perspective_matrix*self._view_matrix;
     }
 }
-
@@ -0,0 +1,231 @@
+use molecule::Molecule;
+
+// ============================================================
+// Colour maps
+// ============================================================
+/// Viridis, from matplotlib: perceptually uniform, for values from low to high.
+const VIRIDIS : &'static [u32] = &[
+    0x440154, 0x472D7B, 0x3B528B, 0x2C728E, 0x21918C, 0x28AE80, 0x5EC962, 0xADDC30, 0xFDE725,
+];
+
+/// Moreland's cool-warm: diverging, for values either side of zero.
+const COOLWARM : &'static [u32] = &[
+    0x3B4CC0, 0x6282EA, 0x8DB0FE, 0xB8D0F9, 0xDDDDDD, 0xF5C4AD, 0xF49A7B, 0xDE604D, 0xB40426,
+];
+
+/// Colours for categories, from ColorBrewer's Set1 and Dark2.
+const PALETTE : &'static [u32] = &[
+    0xE41A1C, 0x377EB8, 0x4DAF4A, 0x984EA3, 0xFF7F00, 0xA65628, 0xF781BF,
+    0x1B9E77, 0xD95F02, 0x7570B3, 0xE7298A, 0x66A61E, 0xE6AB02, 0x666666,
+];
+
+/// For atoms without a value.
+const MISSING : [f32;3] = [0.5, 0.5, 0.5];
+
+fn from_hex(in_colour : &u32) -> [f32;3] {
+    [
+        ((in_colour >> 16) & 0xFF) as f32/255.0,
+        ((in_colour >> 8) & 0xFF) as f32/255.0,
+        (in_colour & 0xFF) as f32/255.0,
+    ]
+}
+
+/// A colour written "#rrggbb", e.g. "#ff0d0d".
+pub fn parse_hex(in_text : &str) -> Option<[f32;3]> {
+    if in_text.len() != 7 || !in_text.starts_with('#') || !in_text[1..].chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(&in_text[1..], 16).ok().map(|colour| from_hex(&colour))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ColourMap {
+    Viridis,
+    Coolwarm,
+}
+
+impl ColourMap {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ColourMap::Viridis => "viridis",
+            ColourMap::Coolwarm => "coolwarm",
+        }
+    }
+
+    pub fn from_name(in_name : &str) -> Option<ColourMap> {
+        [ColourMap::Viridis, ColourMap::Coolwarm].iter().cloned().find(|map| map.name() == in_name)
+    }
+
+    /// The colour at in_t, from 0 to 1, interpolating between the stops.
+    pub fn colour(&self, in_t : &f32) -> [f32;3] {
+        let stops = match *self {
+            ColourMap::Viridis => VIRIDIS,
+            ColourMap::Coolwarm => COOLWARM,
+        };
+        let x = in_t.max(0.0).min(1.0)*(stops.len()-1) as f32;
+        let i = (x.floor() as usize).min(stops.len()-2);
+        let f = x-i as f32;
+        let a = from_hex(&stops[i]);
+        let b = from_hex(&stops[i+1]);
+        [a[0]+f*(b[0]-a[0]), a[1]+f*(b[1]-a[1]), a[2]+f*(b[2]-a[2])]
+    }
+}
+
+// ============================================================
+// Legend
+// ============================================================
+/// What the colours mean, for drawing alongside the structure.
+pub enum Legend {
+    /// Colouring by element needs no legend.
+    None,
+    /// A colour bar running from min to max.
+    Bar {
+        title : String,
+        map   : ColourMap,
+        min   : f32,
+        max   : f32,
+    },
+    /// Each category with its colour.
+    Categories {
+        title   : String,
+        colours : Vec<(String, [f32;3])>,
+    },
+}
+
+// ============================================================
+// Colour scheme
+// ============================================================
+/// How atoms are coloured.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColourScheme {
+    /// By species, the default.
+    Element,
+    /// By a number, e.g. "charge" or "coordination", through a colour map.
+    /// Without a map, one is chosen to suit the values; without a range, it covers them.
+    Property {
+        name  : String,
+        map   : Option<ColourMap>,
+        range : Option<(f32, f32)>,
+    },
+    /// By a label, e.g. "residue" or "fragment", one colour per label.
+    Category(String),
+}
+
+impl ColourScheme {
+    /// The scheme for a property or category called in_name, checking the molecule has it.
+    pub fn new(
+        in_molecule : &Molecule,
+        in_name     : &str,
+        in_map      : &Option<ColourMap>,
+        in_range    : &Option<(f32, f32)>,
+    ) -> Result<ColourScheme, String> {
+        if in_name == "element" {
+            Ok(ColourScheme::Element)
+        } else if in_molecule.category_values(in_name).is_some() {
+            Ok(ColourScheme::Category(in_name.to_string()))
+        } else if in_molecule.property_values(in_name).is_some() {
+            Ok(ColourScheme::Property {
+                name  : in_name.to_string(),
+                map   : *in_map,
+                range : *in_range,
+            })
+        } else {
+            Err(format!(
+                "There is nothing called {} to colour by (try {})",
+                in_name,
+                ColourScheme::all(in_molecule).iter().map(|scheme| scheme.name()).collect::<Vec<&str>>().join(", "),
+            ))
+        }
+    }
+
+    /// Every scheme that makes sense for the molecule, in the order they are cycled through.
+    pub fn all(in_molecule : &Molecule) -> Vec<ColourScheme> {
+        let mut schemes = vec![ColourScheme::Element];
+        for name in in_molecule.category_names() {
+            if name != "element" {
+                schemes.push(ColourScheme::Category(name));
+            }
+        }
+        for name in in_molecule.property_names() {
+            schemes.push(ColourScheme::Property {name : name, map : None, range : None});
+        }
+        schemes
+    }
+
+    pub fn name(&self) -> &str {
+        match *self {
+            ColourScheme::Element => "element",
+            ColourScheme::Property {ref name, ..} => name,
+            ColourScheme::Category(ref name) => name,
+        }
+    }
+
+    /// The scheme after this one in all().
+    pub fn next(&self, in_molecule : &Molecule) -> ColourScheme {
+        let all = ColourScheme::all(in_molecule);
+        match all.iter().position(|scheme| scheme.name() == self.name()) {
+            Some(position) => all[(position+1)%all.len()].clone(),
+            None => ColourScheme::Element,
+        }
+    }
+
+    /// The colour of each atom, and the legend explaining them.
+    /// Atoms given a colour of their own keep it.
+    pub fn apply(&self, in_molecule : &Molecule) -> (Vec<[f32;3]>, Legend) {
+        let (mut colours, legend) = match *self {
+            ColourScheme::Element => (
+                in_molecule.atoms().iter().map(|atom| *atom.species().colour()).collect(),
+                Legend::None,
+            ),
+            ColourScheme::Property {ref name, map, range} => {
+                let values = in_molecule.property_values(name).unwrap_or_default();
+                let known : Vec<f32> = values.iter().filter_map(|&value| value).collect();
+                let min = known.iter().cloned().fold(f32::INFINITY, f32::min);
+                let max = known.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+                // values either side of zero get a diverging map, centred on zero
+                let diverging = min < 0.0 && max > 0.0;
+                let map = map.unwrap_or(if diverging {ColourMap::Coolwarm} else {ColourMap::Viridis});
+                let (min, max) = match range {
+                    Some(range) => range,
+                    None if known.is_empty() => (0.0, 1.0),
+                    None if diverging && map == ColourMap::Coolwarm => {
+                        let extent = max.max(-min);
+                        (-extent, extent)
+                    },
+                    None => (min, max),
+                };
+                let colours = values.iter().map(|value| match *value {
+                    Some(value) if max > min => map.colour(&((value-min)/(max-min))),
+                    Some(_) => map.colour(&0.5),
+                    None => MISSING,
+                }).collect();
+                (colours, Legend::Bar {title : name.clone(), map : map, min : min, max : max})
+            },
+            ColourScheme::Category(ref name) => {
+                let values = in_molecule.category_values(name).unwrap_or_default();
+                let mut legend : Vec<(String, [f32;3])> = Vec::new();
+                let mut colours = Vec::new();
+                for value in &values {
+                    colours.push(match *value {
+                        Some(ref value) => match legend.iter().find(|entry| entry.0 == *value) {
+                            Some(entry) => entry.1,
+                            None => {
+                                let colour = from_hex(&PALETTE[legend.len()%PALETTE.len()]);
+                                legend.push((value.clone(), colour));
+                                colour
+                            },
+                        },
+                        None => MISSING,
+                    });
+                }
+                (colours, Legend::Categories {title : name.clone(), colours : legend})
+            },
+        };
+        for (colour, atom) in colours.iter_mut().zip(in_molecule.atoms()) {
+            if let Some(own) = atom.colour() {
+                *colour = own;
+            }
+        }
+        (colours, legend)
+    }
+}
@@ -0,0 +1,303 @@
+use colour_scheme::{self, ColourMap};
+use representation::Representation;
+use selection::{self, Selection};
+
+// ============================================================
+// Commands
+// ============================================================
+/// Things that can be typed at the command prompt (opened with Return).
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Look down the lattice direction [uvw].
+    ViewDirection([i32;3]),
+    /// Look along the normal to the lattice plane (hkl).
+    ViewPlane([i32;3]),
+    /// Bookmark the current view under a name.
+    SaveView(String),
+    /// Go to a bookmarked view.
+    RestoreView(String),
+    /// Label the selected atoms.
+    Label(String),
+    /// Draw the selected atoms differently from the rest, or as the rest if None.
+    Style(Option<Representation>),
+    /// Colour atoms by a property or group, optionally with a colour map and range.
+    Colour {
+        name  : String,
+        map   : Option<ColourMap>,
+        range : Option<(f32, f32)>,
+    },
+    /// Give the atoms matching a query a colour of their own, or back to the colour scheme if None.
+    ColourAtoms(Selection, Option<[f32;3]>),
+    /// Select the atoms matching a query.
+    Select(Selection),
+    /// Delete the atoms matching a query.
+    Delete(Selection),
+    /// Export just the atoms matching a query.
+    ExportSelection(Selection),
+    /// Hide the atoms matching a query.
+    Hide(Selection),
+    /// Show the atoms matching a query, if nothing else hides them.
+    Show(Selection),
+    /// Show everything, undoing hiding, clipping and focus.
+    ShowAll,
+    /// Hide atoms beyond a plane, or stop clipping if None.
+    Clip(Option<PlaneSpec>),
+    /// Show only atoms within a distance of the selected atoms, or everything if None.
+    Focus(Option<f32>),
+    /// Slice through the structure along a plane, or stop slicing if None.
+    Slice(Option<PlaneSpec>),
+    /// Go to a trajectory frame, counting from 1.
+    Frame(usize),
+    /// Write the structure to a .cell file, with fractional positions unless told otherwise.
+    Write {
+        path       : String,
+        fractional : bool,
+    },
+}
+
+/// A plane as typed at the prompt, before it is placed in the structure.
+#[derive(Debug, PartialEq)]
+pub enum PlaneSpec {
+    /// The lattice plane (hkl), offset in multiples of its spacing.
+    Miller([i32;3], f32),
+    /// A Cartesian normal, offset in Å from the origin.
+    Normal([f32;3], f32),
+}
+
+/// Parses a line typed at the command prompt.
+///
+/// Examples:
+/// [001], [1 -1 0], [1-10], (111), (1,1,0)
+/// save figure3, view figure3
+/// label Fe(III)
+/// style licorice, style default
+/// colour charge, colour z viridis 0 10, colour residue, colour element
+/// colour element O and z > 5.0 #ff0000, colour element O default
+/// select element O and z > 5.0, delete element H, export within 3.0 of index 12
+/// hide element H, show all, clip (001) 0.5, clip 0 0 1 5.0, clip off, focus 5.0, focus off
+/// slice (110) 0.5, slice 1 0 0 2.0, slice off
+/// frame 25
+/// write salt_edited.cell, write salt_edited.cell abs
+pub fn parse(in_line : &str) -> Result<Command, String> {
+    let line = in_line.trim();
+    let mut words = line.splitn(2, char::is_whitespace);
+    let first = words.next().unwrap_or("");
+    let rest = words.next().unwrap_or("").trim();
+    if first == "save" || first == "view" {
+        if rest.is_empty() {
+            return Err(format!("{} needs the name of a view", first));
+        }
+        return Ok(if first == "save" {
+            Command::SaveView(rest.to_string())
+        } else {
+            Command::RestoreView(rest.to_string())
+        });
+    }
+    if first == "label" {
+        if rest.is_empty() {
+            return Err("label needs some text".to_string());
+        }
+        return Ok(Command::Label(rest.to_string()));
+    }
+    if first == "style" {
+        if rest == "default" {
+            return Ok(Command::Style(None));
+        }
+        return match Representation::from_name(rest) {
+            Some(representation) => Ok(Command::Style(Some(representation))),
+            None => Err(format!(
+                "style needs one of {}, or default",
+                Representation::all().iter().map(|r| r.name()).collect::<Vec<&str>>().join(", "),
+            )),
+        };
+    }
+    if first == "show" && rest == "all" {
+        return Ok(Command::ShowAll);
+    }
+    if first == "select" || first == "delete" || first == "export" || first == "hide" || first == "show" {
+        let selection = selection::parse(rest)?;
+        return Ok(match first {
+            "select" => Command::Select(selection),
+            "delete" => Command::Delete(selection),
+            "export" => Command::ExportSelection(selection),
+            "hide" => Command::Hide(selection),
+            _ => Command::Show(selection),
+        });
+    }
+    if first == "clip" || first == "slice" {
+        let plane = if rest == "off" {None} else {Some(parse_plane(rest)?)};
+        return Ok(if first == "clip" {Command::Clip(plane)} else {Command::Slice(plane)});
+    }
+    if first == "focus" {
+        if rest == "off" {
+            return Ok(Command::Focus(None));
+        }
+        return match rest.parse::<f32>() {
+            Ok(radius) if radius > 0.0 => Ok(Command::Focus(Some(radius))),
+            _ => Err("focus needs a distance in Å, or off".to_string()),
+        };
+    }
+    if first == "frame" {
+        return match rest.parse::<usize>() {
+            Ok(frame) if frame > 0 => Ok(Command::Frame(frame)),
+            _ => Err("frame needs a frame number, counting from 1".to_string()),
+        };
+    }
+    if first == "write" {
+        let words : Vec<&str> = rest.split_whitespace().collect();
+        return match words.as_slice() {
+            [path] if path.to_lowercase().ends_with(".cell") => Ok(Command::Write {path : path.to_string(), fractional : true}),
+            [path, "frac"] if path.to_lowercase().ends_with(".cell") => Ok(Command::Write {path : path.to_string(), fractional : true}),
+            [path, "abs"] if path.to_lowercase().ends_with(".cell") => Ok(Command::Write {path : path.to_string(), fractional : false}),
+            _ => Err("write needs a .cell file name, then optionally frac or abs".to_string()),
+        };
+    }
+    if first == "colour" || first == "color" {
+        return parse_colour(rest);
+    }
+    if line.starts_with('[') && line.ends_with(']') {
+        Ok(Command::ViewDirection(parse_indices(&line[1..line.len()-1])?))
+    } else if line.starts_with('(') && line.ends_with(')') {
+        Ok(Command::ViewPlane(parse_indices(&line[1..line.len()-1])?))
+    } else {
+        Err(format!("Unknown command \"{}\"", line))
+    }
+}
+
+/// Reads "(hkl) [OFFSET]" or "X Y Z [OFFSET]", the offset being zero if not given.
+fn parse_plane(in_text : &str) -> Result<PlaneSpec, String> {
+    let error = "Expected a plane, e.g. (001) 0.5 or 0 0 1 5.0".to_string();
+    let text = in_text.trim();
+    if text.starts_with('(') {
+        let end = match text.find(')') {
+            Some(end) => end,
+            None => return Err(error),
+        };
+        let hkl = parse_indices(&text[1..end])?;
+        let offset = match text[end+1..].trim() {
+            "" => 0.0,
+            offset => offset.parse::<f32>().map_err(|_| error)?,
+        };
+        return Ok(PlaneSpec::Miller(hkl, offset));
+    }
+    let numbers : Result<Vec<f32>, _> = text.split_whitespace().map(|word| word.parse::<f32>()).collect();
+    match numbers {
+        Ok(ref numbers) if numbers.len() == 3 || numbers.len() == 4 => {
+            let normal = [numbers[0], numbers[1], numbers[2]];
+            if normal.iter().all(|&x| x == 0.0) {
+                return Err("The normal cannot be zero".to_string());
+            }
+            Ok(PlaneSpec::Normal(normal, *numbers.get(3).unwrap_or(&0.0)))
+        },
+        _ => Err(error),
+    }
+}
+
+/// Reads "NAME [MAP] [MIN MAX]", or "QUERY #rrggbb" or "QUERY default" for the atoms matching a query.
+fn parse_colour(in_text : &str) -> Result<Command, String> {
+    let text = in_text.trim();
+    if let Some(space) = text.rfind(char::is_whitespace) {
+        let last = text[space..].trim();
+        if last == "default" || last.starts_with('#') {
+            let colour = if last == "default" {
+                None
+            } else {
+                Some(colour_scheme::parse_hex(last).ok_or(format!("\"{}\" is not a colour, e.g. #ff0000", last))?)
+            };
+            return Ok(Command::ColourAtoms(selection::parse(&text[..space])?, colour));
+        }
+    }
+    let mut words : Vec<&str> = in_text.split_whitespace().collect();
+    if words.is_empty() {
+        return Err("colour needs something to colour by, e.g. element or charge".to_string());
+    }
+    let name = words.remove(0).to_string();
+    let map = match words.first().and_then(|word| ColourMap::from_name(word)) {
+        Some(map) => {
+            words.remove(0);
+            Some(map)
+        },
+        None => None,
+    };
+    let range = match words.len() {
+        0 => None,
+        2 => match (words[0].parse::<f32>(), words[1].parse::<f32>()) {
+            (Ok(min), Ok(max)) if min < max => Some((min, max)),
+            _ => return Err(format!("\"{} {}\" is not a range from low to high", words[0], words[1])),
+        },
+        _ => return Err("colour takes a name, then viridis or coolwarm, then a minimum and maximum".to_string()),
+    };
+    Ok(Command::Colour {name : name, map : map, range : range})
+}
+
+/// Reads three integer indices, either separated ("1 -1 0", "1,-1,0")
+/// or run together as single digits with optional minus signs ("1-10").
+fn parse_indices(in_text : &str) -> Result<[i32;3], String> {
+    let text = in_text.trim();
+    let words : Vec<&str> = text
+        .split(|c : char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    let mut indices : Vec<i32> = Vec::new();
+    if words.len() == 3 {
+        for word in words {
+            match word.parse::<i32>() {
+                Ok(index) => indices.push(index),
+                Err(_) => return Err(format!("\"{}\" is not an integer", word)),
+            }
+        }
+    } else {
+        let mut sign = 1;
+        for c in text.chars().filter(|c| !c.is_whitespace()) {
+            match c {
+                '-' => sign = -1,
+                '0'..='9' => {
+                    indices.push(sign*(c as i32-'0' as i32));
+                    sign = 1;
+                },
+                _ => return Err(format!("Unexpected \"{}\" in indices", c)),
+            }
+        }
+    }
+
+    if indices.len() != 3 {
+        return Err(format!("Expected three indices, found \"{}\"", text));
+    }
+    if indices.iter().all(|&index| index == 0) {
+        return Err("The indices cannot all be zero".to_string());
+    }
+    Ok([indices[0], indices[1], indices[2]])
+}
+
+// ============================================================
+// Command line
+// ============================================================
+/// The text typed so far at the command prompt, if it is open.
+/// While it is open, keys go to the prompt rather than moving the camera.
+pub struct CommandLine {
+    _text : Option<String>,
+}
+
+impl CommandLine {
+    pub fn new() -> CommandLine {CommandLine{_text : None}}
+
+    pub fn is_open(&self) -> bool {self._text.is_some()}
+    pub fn text(&self) -> Option<&str> {self._text.as_ref().map(|text| text.as_str())}
+
+    pub fn open(&mut self) {self._text = Some(String::new())}
+    pub fn cancel(&mut self) {self._text = None}
+
+    /// Closes the prompt, returning what was typed.
+    pub fn take(&mut self) -> Option<String> {self._text.take()}
+
+    pub fn received_character(&mut self, in_character : &char) {
+        if let Some(ref mut text) = self._text {
+            match *in_character {
+                '\u{8}' | '\u{7f}' => {text.pop();},
+                c if c.is_control() => {},
+                c => text.push(c),
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+extern crate bzip2;
+extern crate flate2;
+extern crate xz2;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+
+// ============================================================
+// Compression
+// ============================================================
+/// How a file is compressed, if at all.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+}
+
+/// Tells how a file is compressed from its first few bytes, which say so whatever it is called,
+/// or failing that from its extension.
+pub fn detect(in_path : &str) -> io::Result<Compression> {
+    let mut magic = [0u8;6];
+    let mut file = File::open(in_path)?;
+    let length = file.read(&mut magic)?;
+    let magic = &magic[..length];
+    if magic.starts_with(&[0x1F, 0x8B]) {
+        Ok(Compression::Gzip)
+    } else if magic.starts_with(b"BZh") {
+        Ok(Compression::Bzip2)
+    } else if magic.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+        Ok(Compression::Xz)
+    } else {
+        Ok(from_extension(in_path))
+    }
+}
+
+fn from_extension(in_name : &str) -> Compression {
+    let name = in_name.to_lowercase();
+    if name.ends_with(".gz") {
+        Compression::Gzip
+    } else if name.ends_with(".bz2") {
+        Compression::Bzip2
+    } else if name.ends_with(".xz") {
+        Compression::Xz
+    } else {
+        Compression::None
+    }
+}
+
+/// The name without any compression extension, e.g. "run.xyz" for "run.xyz.gz",
+/// so the format can be told from what is left.
+pub fn uncompressed_name(in_name : &str) -> &str {
+    match from_extension(in_name) {
+        Compression::None => in_name,
+        _ => &in_name[..in_name.rfind('.').unwrap()],
+    }
+}
+
+/// Opens a file for reading, decompressing it on the fly if it is compressed.
+pub fn open(in_path : &str) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(in_path)?;
+    Ok(match detect(in_path)? {
+        Compression::None => Box::new(BufReader::new(file)),
+        // files may be several compressed streams one after another, e.g. from pigz or pbzip2
+        Compression::Gzip => Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file))),
+        Compression::Bzip2 => Box::new(BufReader::new(bzip2::read::MultiBzDecoder::new(file))),
+        Compression::Xz => Box::new(BufReader::new(xz2::read::XzDecoder::new_multi_decoder(file))),
+    })
+}
@@ -0,0 +1,291 @@
+//! User settings, read from a small subset of TOML: comments, [tables],
+//! and key = value with strings, numbers, booleans and arrays.
+//!
+//! ```text
+//! background = "#eee8d5"
+//! fxaa = true
+//! projection = "perspective"
+//! representation = "ball_and_stick"
+//!
+//! [camera]
+//! field_of_view = 90.0
+//! theta = 0.0
+//! phi = 0.0
+//! psi = 0.0
+//! transition_seconds = 0.5
+//!
+//! [light]
+//! position = [2.0, 0.0, 0.0]
+//!
+//! [elements.O]
+//! radius = 0.3
+//! colour = [1.0, 0.0, 0.0]
+//! visible = true
+//! ```
+//!
+//! Settings in later files override earlier ones, so a project's oxide.toml
+//! can override the user's ~/.config/oxide/config.toml.
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+use elements;
+use colour_scheme::parse_hex;
+use representation::Representation;
+
+// ============================================================
+// Values
+// ============================================================
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Text(String),
+    Number(f32),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+fn parse_value(in_text : &str) -> Result<Value, String> {
+    let text = in_text.trim();
+    if text.starts_with('"') {
+        if text.len() < 2 || !text.ends_with('"') {
+            return Err("unterminated string".to_string());
+        }
+        Ok(Value::Text(text[1..text.len()-1].to_string()))
+    } else if text.starts_with('[') {
+        if !text.ends_with(']') {
+            return Err("unterminated array".to_string());
+        }
+        let inside = text[1..text.len()-1].trim();
+        if inside.is_empty() {return Ok(Value::Array(Vec::new()));}
+        // arrays of arrays aren't needed, so splitting on commas is enough
+        let items : Result<Vec<Value>, String> = inside
+            .trim_end_matches(',')
+            .split(',')
+            .map(parse_value)
+            .collect();
+        Ok(Value::Array(items?))
+    } else if text == "true" || text == "false" {
+        Ok(Value::Boolean(text == "true"))
+    } else {
+        text.parse::<f32>()
+            .map(Value::Number)
+            .map_err(|_| format!("\"{}\" is not a string, number, boolean or array", text))
+    }
+}
+
+/// Strips a comment, leaving any # inside a string alone.
+fn strip_comment(in_line : &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in in_line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &in_line[..i],
+            _ => {},
+        }
+    }
+    in_line
+}
+
+fn as_number(in_value : &Value) -> Result<f32, String> {
+    match *in_value {
+        Value::Number(x) => Ok(x),
+        _ => Err("expected a number".to_string()),
+    }
+}
+
+fn as_positive(in_value : &Value) -> Result<f32, String> {
+    let x = as_number(in_value)?;
+    if x > 0.0 {Ok(x)} else {Err("expected a number greater than zero".to_string())}
+}
+
+fn as_boolean(in_value : &Value) -> Result<bool, String> {
+    match *in_value {
+        Value::Boolean(x) => Ok(x),
+        _ => Err("expected true or false".to_string()),
+    }
+}
+
+fn as_vector(in_value : &Value) -> Result<[f32;3], String> {
+    if let Value::Array(ref items) = *in_value {
+        if items.len() == 3 {
+            return Ok([as_number(&items[0])?, as_number(&items[1])?, as_number(&items[2])?]);
+        }
+    }
+    Err("expected three numbers, e.g. [2.0, 0.0, 0.0]".to_string())
+}
+
+/// A colour is "#rrggbb", or [r, g, b] with each between 0 and 1.
+fn as_colour(in_value : &Value) -> Result<[f32;3], String> {
+    let error = "expected a colour, e.g. \"#eee8d5\" or [0.93, 0.91, 0.84]".to_string();
+    match *in_value {
+        Value::Text(ref text) => parse_hex(text).ok_or(error),
+        Value::Array(_) => {
+            let colour = as_vector(in_value).map_err(|_| error.clone())?;
+            if colour.iter().all(|&x| x >= 0.0 && x <= 1.0) {Ok(colour)} else {Err(error)}
+        },
+        _ => Err(error),
+    }
+}
+
+// ============================================================
+// Config
+// ============================================================
+/// Overrides for how one element is drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElementStyle {
+    pub symbol  : String,
+    pub radius  : Option<f32>,
+    pub colour  : Option<[f32;3]>,
+    pub visible : Option<bool>,
+}
+
+pub struct Config {
+    pub background            : [f32;3],
+    /// In world space.
+    pub light_position        : [f32;3],
+    pub fxaa                  : bool,
+    pub orthographic          : bool,
+    pub representation        : Representation,
+    pub field_of_view_degrees : f32,
+    pub theta_degrees         : f32,
+    pub phi_degrees           : f32,
+    pub psi_degrees           : f32,
+    pub transition_seconds    : f32,
+    pub elements              : Vec<ElementStyle>,
+}
+
+impl Config {
+    /// The built-in defaults.
+    pub fn new() -> Config {
+        Config {
+            background            : [0.93, 0.91, 0.835],
+            light_position        : [2.0, 0.0, 0.0],
+            fxaa                  : true,
+            orthographic          : false,
+            representation        : Representation::BallAndStick,
+            field_of_view_degrees : 90.0,
+            theta_degrees         : 0.0,
+            phi_degrees           : 0.0,
+            psi_degrees           : 0.0,
+            transition_seconds    : 0.5,
+            elements              : Vec::new(),
+        }
+    }
+
+    /// Applies the settings in in_path on top of these ones.
+    /// Returns false if there is no such file.
+    pub fn load(&mut self, in_path : &Path) -> Result<bool, String> {
+        let mut text = String::new();
+        match File::open(in_path) {
+            Ok(mut file) => {
+                if let Err(e) = file.read_to_string(&mut text) {
+                    return Err(format!("{}: {}", in_path.display(), e));
+                }
+            },
+            Err(_) => return Ok(false),
+        }
+
+        let mut table = String::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {continue;}
+            let at = |key : &str| format!("{}:{}: {}", in_path.display(), i+1, key);
+
+            if line.starts_with('[') {
+                if !line.ends_with(']') {
+                    return Err(format!("{}: expected [table]", at(line)));
+                }
+                table = line[1..line.len()-1].trim().to_string();
+                match table.as_str() {
+                    "camera" | "light" => {},
+                    _ if table.starts_with("elements.") => {
+                        let symbol = &table["elements.".len()..];
+                        if elements::find(symbol).is_none() {
+                            return Err(format!("{}: unknown element \"{}\"", at(&table), symbol));
+                        }
+                    },
+                    _ => return Err(format!("{}: unknown table", at(&table))),
+                }
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap().trim();
+            let full_key = if table.is_empty() {key.to_string()} else {format!("{}.{}", table, key)};
+            let value = match parts.next() {
+                Some(value) => parse_value(value).map_err(|e| format!("{}: {}", at(&full_key), e))?,
+                None => return Err(format!("{}: expected key = value", at(key))),
+            };
+            self.set(&table, key, &value).map_err(|e| format!("{}: {}", at(&full_key), e))?;
+        }
+        Ok(true)
+    }
+
+    fn set(&mut self, in_table : &str, in_key : &str, in_value : &Value) -> Result<(), String> {
+        match (in_table, in_key) {
+            ("", "background") => self.background = as_colour(in_value)?,
+            ("", "fxaa") => self.fxaa = as_boolean(in_value)?,
+            ("", "projection") => self.orthographic = match *in_value {
+                Value::Text(ref text) if text == "perspective" => false,
+                Value::Text(ref text) if text == "orthographic" => true,
+                _ => return Err("expected \"perspective\" or \"orthographic\"".to_string()),
+            },
+            ("", "representation") => self.representation = match *in_value {
+                Value::Text(ref text) => match Representation::from_name(text) {
+                    Some(representation) => representation,
+                    None => return Err(format!(
+                        "expected one of {}",
+                        Representation::all().iter()
+                            .map(|representation| format!("\"{}\"", representation.name()))
+                            .collect::<Vec<String>>()
+                            .join(", "),
+                    )),
+                },
+                _ => return Err("expected a string, e.g. \"ball_and_stick\"".to_string()),
+            },
+            ("camera", "field_of_view") => {
+                let degrees = as_positive(in_value)?;
+                if degrees >= 180.0 {
+                    return Err("expected an angle less than 180 degrees".to_string());
+                }
+                self.field_of_view_degrees = degrees;
+            },
+            ("camera", "theta") => self.theta_degrees = as_number(in_value)?,
+            ("camera", "phi") => self.phi_degrees = as_number(in_value)?,
+            ("camera", "psi") => self.psi_degrees = as_number(in_value)?,
+            ("camera", "transition_seconds") => {
+                let seconds = as_number(in_value)?;
+                if seconds < 0.0 {
+                    return Err("expected a time of zero or more".to_string());
+                }
+                self.transition_seconds = seconds;
+            },
+            ("light", "position") => self.light_position = as_vector(in_value)?,
+            (table, key) if table.starts_with("elements.") => {
+                let symbol = &table["elements.".len()..];
+                let position = match self.elements.iter().position(|style| style.symbol == symbol) {
+                    Some(position) => position,
+                    None => {
+                        self.elements.push(ElementStyle {
+                            symbol  : symbol.to_string(),
+                            radius  : None,
+                            colour  : None,
+                            visible : None,
+                        });
+                        self.elements.len()-1
+                    },
+                };
+                let style = &mut self.elements[position];
+                match key {
+                    "radius" => style.radius = Some(as_positive(in_value)?),
+                    "colour" | "color" => style.colour = Some(as_colour(in_value)?),
+                    "visible" => style.visible = Some(as_boolean(in_value)?),
+                    _ => return Err("unknown setting (expected radius, colour or visible)".to_string()),
+                }
+            },
+            _ => return Err("unknown setting".to_string()),
+        }
+        Ok(())
+    }
+}
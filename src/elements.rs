@@ -0,0 +1,114 @@
+// ============================================================
+// Elements
+// ============================================================
+/// Per-element data: symbol, covalent radius (Å, Cordero et al. 2008),
+/// van der Waals radius (Å, Bondi 1964 where available, otherwise 2.0)
+/// and the Jmol colour.
+const ELEMENTS : &'static [(&'static str, f32, f32, u32)] = &[
+    ("H" , 0.31, 1.20, 0xFFFFFF), ("He", 0.28, 1.40, 0xD9FFFF), ("Li", 1.28, 1.82, 0xCC80FF),
+    ("Be", 0.96, 1.53, 0xC2FF00), ("B" , 0.84, 1.92, 0xFFB5B5), ("C" , 0.76, 1.70, 0x909090),
+    ("N" , 0.71, 1.55, 0x3050F8), ("O" , 0.66, 1.52, 0xFF0D0D), ("F" , 0.57, 1.47, 0x90E050),
+    ("Ne", 0.58, 1.54, 0xB3E3F5), ("Na", 1.66, 2.27, 0xAB5CF2), ("Mg", 1.41, 1.73, 0x8AFF00),
+    ("Al", 1.21, 1.84, 0xBFA6A6), ("Si", 1.11, 2.10, 0xF0C8A0), ("P" , 1.07, 1.80, 0xFF8000),
+    ("S" , 1.05, 1.80, 0xFFFF30), ("Cl", 1.02, 1.75, 0x1FF01F), ("Ar", 1.06, 1.88, 0x80D1E3),
+    ("K" , 2.03, 2.75, 0x8F40D4), ("Ca", 1.76, 2.31, 0x3DFF00), ("Sc", 1.70, 2.11, 0xE6E6E6),
+    ("Ti", 1.60, 2.00, 0xBFC2C7), ("V" , 1.53, 2.00, 0xA6A6AB), ("Cr", 1.39, 2.00, 0x8A99C7),
+    ("Mn", 1.39, 2.00, 0x9C7AC7), ("Fe", 1.32, 2.00, 0xE06633), ("Co", 1.26, 2.00, 0xF090A0),
+    ("Ni", 1.24, 1.63, 0x50D050), ("Cu", 1.32, 1.40, 0xC88033), ("Zn", 1.22, 1.39, 0x7D80B0),
+    ("Ga", 1.22, 1.87, 0xC28F8F), ("Ge", 1.20, 2.11, 0x668F8F), ("As", 1.19, 1.85, 0xBD80E3),
+    ("Se", 1.20, 1.90, 0xFFA100), ("Br", 1.20, 1.85, 0xA62929), ("Kr", 1.16, 2.02, 0x5CB8D1),
+    ("Rb", 2.20, 3.03, 0x702EB0), ("Sr", 1.95, 2.49, 0x00FF00), ("Y" , 1.90, 2.00, 0x94FFFF),
+    ("Zr", 1.75, 2.00, 0x94E0E0), ("Nb", 1.64, 2.00, 0x73C2C9), ("Mo", 1.54, 2.00, 0x54B5B5),
+    ("Tc", 1.47, 2.00, 0x3B9E9E), ("Ru", 1.46, 2.00, 0x248F8F), ("Rh", 1.42, 2.00, 0x0A7D8C),
+    ("Pd", 1.39, 1.63, 0x006985), ("Ag", 1.45, 1.72, 0xC0C0C0), ("Cd", 1.44, 1.58, 0xFFD98F),
+    ("In", 1.42, 1.93, 0xA67573), ("Sn", 1.39, 2.17, 0x668080), ("Sb", 1.39, 2.06, 0x9E63B5),
+    ("Te", 1.38, 2.06, 0xD47A00), ("I" , 1.39, 1.98, 0x940094), ("Xe", 1.40, 2.16, 0x429EB0),
+    ("Cs", 2.44, 3.43, 0x57178F), ("Ba", 2.15, 2.68, 0x00C900), ("La", 2.07, 2.00, 0x70D4FF),
+    ("Ce", 2.04, 2.00, 0xFFFFC7), ("Pr", 2.03, 2.00, 0xD9FFC7), ("Nd", 2.01, 2.00, 0xC7FFC7),
+    ("Pm", 1.99, 2.00, 0xA3FFC7), ("Sm", 1.98, 2.00, 0x8FFFC7), ("Eu", 1.98, 2.00, 0x61FFC7),
+    ("Gd", 1.96, 2.00, 0x45FFC7), ("Tb", 1.94, 2.00, 0x30FFC7), ("Dy", 1.92, 2.00, 0x1FFFC7),
+    ("Ho", 1.92, 2.00, 0x00FF9C), ("Er", 1.89, 2.00, 0x00E675), ("Tm", 1.90, 2.00, 0x00D452),
+    ("Yb", 1.87, 2.00, 0x00BF38), ("Lu", 1.87, 2.00, 0x00AB24), ("Hf", 1.75, 2.00, 0x4DC2FF),
+    ("Ta", 1.70, 2.00, 0x4DA6FF), ("W" , 1.62, 2.00, 0x2194D6), ("Re", 1.51, 2.00, 0x267DAB),
+    ("Os", 1.44, 2.00, 0x266696), ("Ir", 1.41, 2.00, 0x175487), ("Pt", 1.36, 1.75, 0xD0D0E0),
+    ("Au", 1.36, 1.66, 0xFFD123), ("Hg", 1.32, 1.55, 0xB8B8D0), ("Tl", 1.45, 1.96, 0xA6544D),
+    ("Pb", 1.46, 2.02, 0x575961), ("Bi", 1.48, 2.07, 0x9E4FB5), ("Po", 1.40, 1.97, 0xAB5C00),
+    ("At", 1.50, 2.02, 0x754F45), ("Rn", 1.50, 2.20, 0x428296), ("Th", 2.06, 2.00, 0x00BAFF),
+    ("U" , 1.96, 1.86, 0x008FFF), ("Pu", 1.87, 2.00, 0x006BFF),
+];
+
+/// Standard atomic weights (u), for telling elements apart by mass
+/// in files that only give masses, e.g. LAMMPS data files.
+const MASSES : &'static [(&'static str, f32)] = &[
+    ("H" ,   1.008), ("He",   4.003), ("Li",   6.940), ("Be",   9.012), ("B" ,  10.810), ("C" ,  12.011),
+    ("N" ,  14.007), ("O" ,  15.999), ("F" ,  18.998), ("Ne",  20.180), ("Na",  22.990), ("Mg",  24.305),
+    ("Al",  26.982), ("Si",  28.085), ("P" ,  30.974), ("S" ,  32.060), ("Cl",  35.450), ("Ar",  39.948),
+    ("K" ,  39.098), ("Ca",  40.078), ("Sc",  44.956), ("Ti",  47.867), ("V" ,  50.942), ("Cr",  51.996),
+    ("Mn",  54.938), ("Fe",  55.845), ("Co",  58.933), ("Ni",  58.693), ("Cu",  63.546), ("Zn",  65.380),
+    ("Ga",  69.723), ("Ge",  72.630), ("As",  74.922), ("Se",  78.971), ("Br",  79.904), ("Kr",  83.798),
+    ("Rb",  85.468), ("Sr",  87.620), ("Y" ,  88.906), ("Zr",  91.224), ("Nb",  92.906), ("Mo",  95.950),
+    ("Tc",  98.000), ("Ru", 101.070), ("Rh", 102.910), ("Pd", 106.420), ("Ag", 107.870), ("Cd", 112.410),
+    ("In", 114.820), ("Sn", 118.710), ("Sb", 121.760), ("Te", 127.600), ("I" , 126.900), ("Xe", 131.290),
+    ("Cs", 132.910), ("Ba", 137.330), ("La", 138.910), ("Ce", 140.120), ("Pr", 140.910), ("Nd", 144.240),
+    ("Pm", 145.000), ("Sm", 150.360), ("Eu", 151.960), ("Gd", 157.250), ("Tb", 158.930), ("Dy", 162.500),
+    ("Ho", 164.930), ("Er", 167.260), ("Tm", 168.930), ("Yb", 173.050), ("Lu", 174.970), ("Hf", 178.490),
+    ("Ta", 180.950), ("W" , 183.840), ("Re", 186.210), ("Os", 190.230), ("Ir", 192.220), ("Pt", 195.080),
+    ("Au", 196.970), ("Hg", 200.590), ("Tl", 204.380), ("Pb", 207.200), ("Bi", 208.980), ("Po", 209.000),
+    ("At", 210.000), ("Rn", 222.000), ("Th", 232.040), ("U" , 238.030), ("Pu", 244.000),
+];
+
+pub struct Element {
+    pub symbol          : &'static str,
+    pub covalent_radius : f32,
+    pub vdw_radius      : f32,
+    pub colour          : [f32;3],
+}
+
+fn element(in_entry : &(&'static str, f32, f32, u32)) -> Element {
+    let &(symbol, covalent_radius, vdw_radius, colour) = in_entry;
+    Element {
+        symbol          : symbol,
+        covalent_radius : covalent_radius,
+        vdw_radius      : vdw_radius,
+        colour          : [
+            ((colour >> 16) & 0xFF) as f32/255.0,
+            ((colour >> 8) & 0xFF) as f32/255.0,
+            (colour & 0xFF) as f32/255.0,
+        ],
+    }
+}
+
+/// Every element in the table, lightest first.
+pub fn all() -> Vec<Element> {ELEMENTS.iter().map(element).collect()}
+
+pub fn find(in_symbol : &str) -> Option<Element> {
+    ELEMENTS.iter().find(|entry| entry.0 == in_symbol).map(element)
+}
+
+/// The symbol of the element whose atomic weight is nearest in_mass, if one is within 0.5 u.
+pub fn symbol_from_mass(in_mass : &f32) -> Option<&'static str> {
+    MASSES.iter()
+        .map(|&(symbol, mass)| (symbol, (mass-in_mass).abs()))
+        .filter(|&(_, difference)| difference < 0.5)
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(symbol, _)| symbol)
+}
+
+/// Turns a species label from a structure file into an element symbol,
+/// e.g. "Cl", "CL", "cl1", "O:a" and "Fe2+" all give a capitalised symbol.
+pub fn symbol_from_label(in_label : &str) -> String {
+    let letters : String = in_label.chars().take_while(|c| c.is_alphabetic()).collect();
+    let mut symbol = String::new();
+    for (i, c) in letters.chars().take(2).enumerate() {
+        if i == 0 {
+            symbol.extend(c.to_uppercase());
+        } else {
+            symbol.extend(c.to_lowercase());
+        }
+    }
+    // labels like "Oa" are a one-letter symbol followed by something else
+    if symbol.len() == 2 && find(&symbol).is_none() {
+        symbol.truncate(1);
+    }
+    symbol
+}
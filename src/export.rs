@@ -0,0 +1,356 @@
+/// Writes the molecule out as a 3D model, for Blender or a 3D printer.
+/// Atoms become icospheres, bonds become cylinders (each half coloured by
+/// its atom) and polyhedra become convex hulls of the bonded neighbours.
+/// For printing they are all merged into one closed surface.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use mesh;
+use mesh::{Mesh, Solid};
+use vector;
+use molecule::Molecule;
+
+// ============================================================
+// Export options
+// ============================================================
+pub struct ExportOptions {
+    pub sphere_subdivisions : u32,
+    pub cylinder_segments   : u32,
+    /// Atoms closer than this are bonded.
+    pub bond_cutoff         : f32,
+    pub bond_radius         : f32,
+    /// Whether to add coordination polyhedra.
+    pub polyhedra           : bool,
+    /// How finely the merged solid for printing is sampled, in Å.
+    pub print_spacing       : f32,
+}
+
+impl ExportOptions {
+    /// Sensible defaults for the given molecule.
+    pub fn new(in_molecule : &Molecule) -> ExportOptions {
+        ExportOptions {
+            sphere_subdivisions : 2,
+            cylinder_segments   : 16,
+            bond_cutoff         : in_molecule.default_bond_cutoff(),
+            bond_radius         : *in_molecule.stick_radius(),
+            polyhedra           : false,
+            print_spacing       : 0.5*in_molecule.stick_radius(),
+        }
+    }
+}
+
+// ============================================================
+// Export scene
+// ============================================================
+/// A mesh with the material it should be drawn with.
+pub struct Part {
+    pub name   : String,
+    pub colour : [f32;3],
+    pub mesh   : Mesh,
+}
+
+/// Tessellates the molecule, giving one part per species plus one for the polyhedra.
+pub fn tessellate(in_molecule : &Molecule, in_options : &ExportOptions) -> Vec<Part> {
+    let mut parts : Vec<Part> = Vec::new();
+    let mut part_of_species : HashMap<String, usize> = HashMap::new();
+    let unit_sphere = mesh::icosphere(&in_options.sphere_subdivisions);
+
+    // hidden atoms are left out
+    let molecule = in_molecule.subset(in_molecule.visible_mask());
+    let atoms = molecule.atoms();
+    let mut part_indices = Vec::new();
+    for atom in atoms {
+        let name = atom.species().name().to_string();
+        let index = match part_of_species.get(&name) {
+            Some(&index) => index,
+            None => {
+                parts.push(Part {
+                    name   : name.clone(),
+                    colour : *atom.species().colour(),
+                    mesh   : Mesh::new(),
+                });
+                parts.len()-1
+            },
+        };
+        part_of_species.insert(name, index);
+        part_indices.push(index);
+        parts[index].mesh.add_sphere(&unit_sphere, atom.position(), atom.species().size());
+    }
+
+    let bonds = molecule.bonds(&in_options.bond_cutoff);
+    for &(i, j) in &bonds {
+        let a = atoms[i].position();
+        let b = atoms[j].position();
+        let midpoint = [(a[0]+b[0])/2.0, (a[1]+b[1])/2.0, (a[2]+b[2])/2.0];
+        // the halves are open at the midpoint, so they make one tube
+        parts[part_indices[i]].mesh.add_cylinder(a, &midpoint, &in_options.bond_radius, &in_options.cylinder_segments, &false);
+        parts[part_indices[j]].mesh.add_cylinder(&midpoint, b, &in_options.bond_radius, &in_options.cylinder_segments, &false);
+    }
+
+    if in_options.polyhedra {
+        let mut polyhedra = Mesh::new();
+        for corners in polyhedron_corners(&molecule, &bonds) {
+            polyhedra.add_convex_hull(&corners);
+        }
+        if !polyhedra.is_empty() {
+            parts.push(Part {
+                name   : "polyhedra".to_string(),
+                colour : [0.6, 0.6, 0.8],
+                mesh   : polyhedra,
+            });
+        }
+    }
+
+    parts.retain(|part| !part.mesh.is_empty());
+    parts
+}
+
+/// The corners of the coordination polyhedra. A polyhedron is drawn around any atom
+/// bonded to at least four atoms, none of which are its own species (e.g. NaCl6 octahedra).
+fn polyhedron_corners(in_molecule : &Molecule, in_bonds : &Vec<(usize, usize)>) -> Vec<Vec<[f32;3]>> {
+    let atoms = in_molecule.atoms();
+    let mut neighbours : Vec<Vec<usize>> = vec![Vec::new(); atoms.len()];
+    for &(i, j) in in_bonds {
+        neighbours[i].push(j);
+        neighbours[j].push(i);
+    }
+    let mut polyhedra = Vec::new();
+    for (i, atom) in atoms.iter().enumerate() {
+        if neighbours[i].len() < 4 {continue;}
+        if neighbours[i].iter().any(|&j| atoms[j].species().name() == atom.species().name()) {continue;}
+        polyhedra.push(neighbours[i].iter().map(|&j| *atoms[j].position()).collect());
+    }
+    polyhedra
+}
+
+/// The whole model as one closed surface with nothing inside it, ready to print:
+/// the union of the atoms, whole bonds and any polyhedra.
+pub fn printable(in_molecule : &Molecule, in_options : &ExportOptions) -> Mesh {
+    // hidden atoms are left out
+    let molecule = in_molecule.subset(in_molecule.visible_mask());
+    let atoms = molecule.atoms();
+    let mut solids : Vec<Solid> = atoms.iter().map(|atom| Solid::Sphere {
+        centre : *atom.position(),
+        radius : *atom.species().size(),
+    }).collect();
+    let bonds = molecule.bonds(&in_options.bond_cutoff);
+    for &(i, j) in &bonds {
+        solids.push(Solid::Cylinder {
+            start  : *atoms[i].position(),
+            end    : *atoms[j].position(),
+            radius : in_options.bond_radius,
+        });
+    }
+    if in_options.polyhedra {
+        solids.extend(polyhedron_corners(&molecule, &bonds).iter().filter_map(Solid::polyhedron));
+    }
+    mesh::union_surface(&solids, &in_options.print_spacing)
+}
+
+// ============================================================
+// glTF 2.0
+// ============================================================
+/// Writes a single .gltf file with the vertex data embedded as base64,
+/// one mesh and one material per part.
+pub fn write_gltf(in_path : &Path, in_parts : &Vec<Part>) -> io::Result<()> {
+    let mut buffer : Vec<u8> = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut meshes = Vec::new();
+    let mut materials = Vec::new();
+    let mut nodes = Vec::new();
+
+    for (p, part) in in_parts.iter().enumerate() {
+        let positions = part.mesh.positions();
+        let mut min = [f32::INFINITY;3];
+        let mut max = [f32::NEG_INFINITY;3];
+        for position in positions {
+            for l in 0..3 {
+                min[l] = min[l].min(position[l]);
+                max[l] = max[l].max(position[l]);
+            }
+        }
+
+        // positions
+        let offset = buffer.len();
+        for position in positions {
+            for x in position {buffer.extend_from_slice(&x.to_le_bytes());}
+        }
+        buffer_views.push(format!(
+            "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":34962}}",
+            offset, buffer.len()-offset
+        ));
+        accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":5126,\"count\":{},\"type\":\"VEC3\",\
+             \"min\":[{},{},{}],\"max\":[{},{},{}]}}",
+            buffer_views.len()-1, positions.len(),
+            min[0], min[1], min[2], max[0], max[1], max[2]
+        ));
+
+        // normals
+        let offset = buffer.len();
+        for normal in part.mesh.normals() {
+            for x in normal {buffer.extend_from_slice(&x.to_le_bytes());}
+        }
+        buffer_views.push(format!(
+            "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":34962}}",
+            offset, buffer.len()-offset
+        ));
+        accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":5126,\"count\":{},\"type\":\"VEC3\"}}",
+            buffer_views.len()-1, positions.len()
+        ));
+
+        // indices
+        let offset = buffer.len();
+        for triangle in part.mesh.triangles() {
+            for i in triangle {buffer.extend_from_slice(&i.to_le_bytes());}
+        }
+        buffer_views.push(format!(
+            "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":34963}}",
+            offset, buffer.len()-offset
+        ));
+        accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":5125,\"count\":{},\"type\":\"SCALAR\"}}",
+            buffer_views.len()-1, 3*part.mesh.triangles().len()
+        ));
+
+        let first_accessor = accessors.len()-3;
+        meshes.push(format!(
+            "{{\"name\":\"{}\",\"primitives\":[{{\"attributes\":{{\"POSITION\":{},\"NORMAL\":{}}},\
+             \"indices\":{},\"material\":{}}}]}}",
+            part.name, first_accessor, first_accessor+1, first_accessor+2, p
+        ));
+        let colour = srgb_to_linear(&part.colour);
+        materials.push(format!(
+            "{{\"name\":\"{}\",\"pbrMetallicRoughness\":{{\"baseColorFactor\":[{},{},{},1.0],\
+             \"metallicFactor\":0.0,\"roughnessFactor\":0.5}}}}",
+            part.name, colour[0], colour[1], colour[2]
+        ));
+        nodes.push(format!("{{\"name\":\"{}\",\"mesh\":{}}}", part.name, p));
+    }
+
+    let node_indices : Vec<String> = (0..nodes.len()).map(|n| n.to_string()).collect();
+    let json = format!(
+        "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"Oxide\"}},\
+         \"scene\":0,\"scenes\":[{{\"nodes\":[{}]}}],\
+         \"nodes\":[{}],\"meshes\":[{}],\"materials\":[{}],\
+         \"accessors\":[{}],\"bufferViews\":[{}],\
+         \"buffers\":[{{\"byteLength\":{},\"uri\":\"data:application/octet-stream;base64,{}\"}}]}}\n",
+        node_indices.join(","),
+        nodes.join(","),
+        meshes.join(","),
+        materials.join(","),
+        accessors.join(","),
+        buffer_views.join(","),
+        buffer.len(),
+        base64(&buffer)
+    );
+
+    let mut file = File::create(in_path)?;
+    file.write_all(json.as_bytes())
+}
+
+/// glTF colours are linear, ours are sRGB.
+fn srgb_to_linear(in_colour : &[f32;3]) -> [f32;3] {
+    let mut colour = [0.0;3];
+    for l in 0..3 {
+        let c = in_colour[l];
+        colour[l] = if c <= 0.04045 {c/12.92} else {((c+0.055)/1.055).powf(2.4)};
+    }
+    colour
+}
+
+fn base64(in_bytes : &[u8]) -> String {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity((in_bytes.len()+2)/3*4);
+    for chunk in in_bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | (b[2] as u32);
+        for c in 0..4 {
+            if c <= chunk.len() {
+                output.push(alphabet[((n >> (18-6*c)) & 63) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+// ============================================================
+// OBJ + MTL
+// ============================================================
+/// Writes in_path (.obj) and a .mtl file alongside it with one material per part.
+pub fn write_obj(in_path : &Path, in_parts : &Vec<Part>) -> io::Result<()> {
+    let mtl_path = in_path.with_extension("mtl");
+    let mut mtl = File::create(&mtl_path)?;
+    for part in in_parts {
+        writeln!(mtl, "newmtl {}", part.name)?;
+        writeln!(mtl, "Ka {} {} {}", part.colour[0]*0.2, part.colour[1]*0.2, part.colour[2]*0.2)?;
+        writeln!(mtl, "Kd {} {} {}", part.colour[0], part.colour[1], part.colour[2])?;
+        writeln!(mtl, "Ks 0.2 0.2 0.2")?;
+        writeln!(mtl, "d 1.0")?;
+        writeln!(mtl, "illum 2")?;
+        writeln!(mtl, "")?;
+    }
+
+    let mut obj = io::BufWriter::new(File::create(in_path)?);
+    writeln!(obj, "# Written by Oxide")?;
+    writeln!(obj, "mtllib {}", mtl_path.file_name().unwrap().to_string_lossy())?;
+    let mut offset = 1; // OBJ indices start at 1
+    for part in in_parts {
+        writeln!(obj, "o {}", part.name)?;
+        writeln!(obj, "usemtl {}", part.name)?;
+        for position in part.mesh.positions() {
+            writeln!(obj, "v {} {} {}", position[0], position[1], position[2])?;
+        }
+        for normal in part.mesh.normals() {
+            writeln!(obj, "vn {} {} {}", normal[0], normal[1], normal[2])?;
+        }
+        for triangle in part.mesh.triangles() {
+            let (a, b, c) = (triangle[0]+offset, triangle[1]+offset, triangle[2]+offset);
+            writeln!(obj, "f {}//{} {}//{} {}//{}", a, a, b, b, c, c)?;
+        }
+        offset += part.mesh.positions().len() as u32;
+    }
+    obj.flush()
+}
+
+// ============================================================
+// Binary STL
+// ============================================================
+/// Writes a mesh, e.g. from printable(), as a binary STL solid.
+pub fn write_stl(in_path : &Path, in_mesh : &Mesh) -> io::Result<()> {
+    let mut stl = io::BufWriter::new(File::create(in_path)?);
+    let mut header = [0u8;80];
+    let title = b"Oxide molecular model";
+    header[..title.len()].copy_from_slice(title);
+    stl.write_all(&header)?;
+
+    stl.write_all(&(in_mesh.triangles().len() as u32).to_le_bytes())?;
+    for corners in in_mesh.triangle_corners() {
+        let normal = vector::cross(
+            &vector::sub(&corners[1], &corners[0]),
+            &vector::sub(&corners[2], &corners[0]),
+        );
+        let normal = if vector::length(&normal) > 0.0 {vector::normalised(&normal)} else {normal};
+        for x in &normal {stl.write_all(&x.to_le_bytes())?;}
+        for corner in &corners {
+            for x in corner {stl.write_all(&x.to_le_bytes())?;}
+        }
+        stl.write_all(&[0u8;2])?;
+    }
+    stl.flush()
+}
+
+/// Writes all three formats next to each other, named after in_stem.
+pub fn write_all(in_stem : &str, in_molecule : &Molecule, in_options : &ExportOptions) -> io::Result<()> {
+    let parts = tessellate(in_molecule, in_options);
+    write_gltf(&Path::new(in_stem).with_extension("gltf"), &parts)?;
+    write_obj(&Path::new(in_stem).with_extension("obj"), &parts)?;
+    write_stl(&Path::new(in_stem).with_extension("stl"), &printable(in_molecule, in_options))
+}
@@ -1,83 +1,979 @@
 /// Very basic parser of CASTEP files, returning
 /// absolute atomic positions to the main program.
-use std::fs::File;
-use std::error::Error;
+use std::cell::Cell;
 use std::io::prelude::*;
 use std::path::Path;
 use molecule::Molecule;
-use species::DefaultSpecies;
-use model::DefaultModels;
+use lattice::Lattice;
+use species::{DefaultSpecies, Species};
+use elements;
+use compression;
+use trajectory::{Frame, FrameIndex, Frames};
+
+/// Reads any supported structure or trajectory file, going by its name:
+/// .xyz (one or more frames), XDATCAR, CASTEP .castep, .geom or .md, LAMMPS data
+/// (.data, .lmp or data.*) and dump (.dump, .lammpstrj or dump.*) files, or else a CASTEP .cell file.
+/// Any of these may be compressed with gzip, bzip2 or xz (e.g. run.xyz.gz).
+/// LAMMPS atom types 1, 2, ... are the elements in in_type_names, where given.
+pub fn read_file<'a>(
+    fname           : &String,
+    default_species : &'a DefaultSpecies,
+    in_type_names   : &[String],
+) -> Result<Molecule<'a>, String> {
+    let path = Path::new(fname);
+    let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let name = compression::uncompressed_name(&name);
+    if name.ends_with(".xyz") {
+        read_xyz_file(fname, default_species)
+    } else if name.contains("xdatcar") {
+        read_xdatcar_file(fname, default_species)
+    } else if name.ends_with(".castep") {
+        read_castep_file(fname, default_species)
+    } else if name.ends_with(".geom") || name.ends_with(".md") {
+        read_castep_trajectory(fname, default_species)
+    } else if name.ends_with(".data") || name.ends_with(".lmp") || name.starts_with("data.") {
+        read_lammps_data_file(fname, default_species, in_type_names)
+    } else if name.ends_with(".dump") || name.ends_with(".lammpstrj") || name.starts_with("dump.") {
+        read_lammps_dump_file(fname, default_species, in_type_names)
+    } else {
+        read_cell_file(fname, default_species)
+    }
+}
+
+fn read_text(fname : &str) -> Result<String, String> {
+    let mut text = String::new();
+    compression::open(fname)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| format!("Could not read {}: {}", fname, e))?;
+    Ok(text)
+}
+
+/// The first in_count numbers on a line, or an error naming the line.
+fn parse_numbers(in_line : &str, in_count : usize) -> Result<Vec<f32>, String> {
+    let numbers : Vec<f32> = in_line.split_whitespace()
+        .take(in_count)
+        .map(|word| word.parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|_| format!("Expected {} numbers in \"{}\"", in_count, in_line.trim()))?;
+    if numbers.len() < in_count {
+        return Err(format!("Expected {} numbers in \"{}\"", in_count, in_line.trim()));
+    }
+    Ok(numbers)
+}
+
+/// Adds the atoms of the first frame, then the frames themselves, starting on the first.
+fn build_molecule<'a>(
+    in_species : &[&'a Species<'a>],
+    in_frames  : Frames,
+) -> Result<Molecule<'a>, String> {
+    let mut frames = in_frames;
+    let first = frames.get(&0)?;
+    let mut molecule = Molecule::new();
+    if let Some(lattice) = first.lattice() {
+        molecule.set_lattice(lattice);
+    }
+    for (species, position) in in_species.iter().zip(first.positions()) {
+        molecule.add_atom(species, position);
+    }
+    molecule.set_frames(frames)?;
+    Ok(molecule)
+}
+
+/// Where each frame starts in a file, then where the file ends, for a FrameIndex.
+/// in_frame_length gives the number of lines in a frame from its first line,
+/// or None if the line doesn't start one.
+fn index_frames(fname : &str, in_frame_length : &dyn Fn(&str) -> Result<Option<usize>, String>) -> Result<Vec<u64>, String> {
+    let mut reader = compression::open(fname).map_err(|e| format!("Could not read {}: {}", fname, e))?;
+    let mut offsets = Vec::new();
+    let mut offset = 0u64;
+    let mut line = String::new();
+    // lines still to skip in the current frame
+    let mut remaining = 0;
+    loop {
+        line.clear();
+        let length = reader.read_line(&mut line).map_err(|e| format!("Could not read {}: {}", fname, e))?;
+        if length == 0 {break;}
+        if remaining > 0 {
+            remaining -= 1;
+        } else if let Some(lines) = in_frame_length(&line)? {
+            offsets.push(offset);
+            remaining = lines.max(1)-1;
+        }
+        offset += length as u64;
+    }
+    offsets.push(offset);
+    Ok(offsets)
+}
+
+/// Reads an XYZ file of one or more frames, each an atom count, a comment line and
+/// a line per atom of "symbol x y z" in Å. Extended XYZ comments may give the cell as
+/// Lattice="ax ay az bx by bz cx cy cz". Frames are indexed, and read when they are shown.
+pub fn read_xyz_file<'a>(fname : &str, default_species : &'a DefaultSpecies) -> Result<Molecule<'a>, String> {
+    let frame_length = |line : &str| -> Result<Option<usize>, String> {
+        if line.trim().is_empty() {return Ok(None);}
+        match line.trim().parse::<usize>() {
+            Ok(count) => Ok(Some(count+2)),
+            Err(_) => Err(format!("Expected an atom count in {}, not \"{}\"", fname, line.trim())),
+        }
+    };
+    let offsets = index_frames(fname, &frame_length)?;
+    if offsets.len() < 2 {
+        return Err(format!("There are no atoms in {}", fname));
+    }
+    let mut index = FrameIndex::new(fname, offsets, xyz_frame);
+    let (labels, _) = parse_xyz_frame(&index.text(&0)?)?;
+    let species : Vec<&'a Species<'a>> = labels.iter().map(|label| default_species.by_label(label)).collect();
+    build_molecule(&species, Frames::OnDisk(index))
+}
+
+/// The species labels and frame in the text of one XYZ frame.
+fn parse_xyz_frame(in_text : &str) -> Result<(Vec<String>, Frame), String> {
+    let mut lines = in_text.lines().skip_while(|line| line.trim().is_empty());
+    let line = lines.next().unwrap_or("");
+    let count : usize = line.trim().parse()
+        .map_err(|_| format!("Expected an atom count, not \"{}\"", line.trim()))?;
+    let lattice = match lines.next() {
+        Some(comment) => xyz_lattice(comment)?,
+        None => None,
+    };
+    let mut labels = Vec::new();
+    let mut positions = Vec::new();
+    for i in 0..count {
+        let line = lines.next().ok_or(format!("The frame stops after {} of {} atoms", i, count))?;
+        let words : Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            return Err(format!("There is a blank line for atom {}", i+1));
+        }
+        let position = parse_numbers(&words[1..].join(" "), 3)?;
+        labels.push(words[0].to_string());
+        positions.push([position[0], position[1], position[2]]);
+    }
+    Ok((labels, Frame::new(positions, lattice)))
+}
+
+fn xyz_frame(in_text : &str) -> Result<Frame, String> {parse_xyz_frame(in_text).map(|(_, frame)| frame)}
+
+/// The cell from an extended XYZ comment line, if it has one.
+fn xyz_lattice(in_comment : &str) -> Result<Option<Lattice>, String> {
+    let start = match in_comment.find("Lattice=\"") {
+        Some(start) => start+"Lattice=\"".len(),
+        None => return Ok(None),
+    };
+    let length = in_comment[start..].find('"').ok_or(format!("Unfinished lattice in \"{}\"", in_comment))?;
+    let numbers = parse_numbers(&in_comment[start..start+length], 9)?;
+    let mut vectors = [[0.0f32;3];3];
+    for k in 0..3 {
+        for l in 0..3 {
+            vectors[k][l] = numbers[3*k+l];
+        }
+    }
+    Ok(Some(Lattice::new(&vectors, &[0.0;3])))
+}
+
+/// Reads a VASP XDATCAR: a POSCAR-style header (comment, scale, three lattice vectors,
+/// species, counts) and then blocks of fractional coordinates, each after a
+/// "Direct configuration=" line. Runs with a changing cell repeat the header before each block.
+/// Frames are indexed, from each block or from the header before it, and read when they are shown.
+pub fn read_xdatcar_file<'a>(fname : &str, default_species : &'a DefaultSpecies) -> Result<Molecule<'a>, String> {
+    let head = read_head(fname, 7)?;
+    let head : Vec<&str> = head.iter().map(|line| line.as_str()).collect();
+    let (lattice, names, counts) = parse_xdatcar_header(&head).map_err(|e| format!("{} in {}", e, fname))?;
+    let count : usize = counts.iter().sum();
+
+    // the comment line starts a repeated header
+    let comment = head[0].trim().to_string();
+    let frame_length = move |line : &str| -> Result<Option<usize>, String> {
+        let line = line.trim();
+        Ok(if !comment.is_empty() && line == comment {
+            Some(8+count)
+        } else if line.to_lowercase().starts_with("direct") {
+            Some(1+count)
+        } else {
+            None
+        })
+    };
+    let offsets = index_frames(fname, &frame_length)?;
+    let mut species : Vec<&'a Species<'a>> = Vec::new();
+    for (name, &count) in names.iter().zip(counts.iter()) {
+        for _ in 0..count {
+            species.push(default_species.by_label(name));
+        }
+    }
+    if offsets.len() < 2 {
+        return Err(format!("There are no frames in {}", fname));
+    }
+    let index = FrameIndex::new(fname, offsets, move |text : &str| xdatcar_frame(text, &lattice, &count));
+    build_molecule(&species, Frames::OnDisk(index))
+}
+
+/// Up to the first in_count lines of a file.
+fn read_head(fname : &str, in_count : usize) -> Result<Vec<String>, String> {
+    compression::open(fname)
+        .and_then(|reader| reader.lines().take(in_count).collect::<Result<Vec<String>, _>>())
+        .map_err(|e| format!("Could not read {}: {}", fname, e))
+}
+
+/// The cell, species names and counts of atoms from the seven lines of an XDATCAR header.
+fn parse_xdatcar_header<'b>(in_lines : &[&'b str]) -> Result<(Lattice, Vec<&'b str>, Vec<usize>), String> {
+    if in_lines.len() < 7 {
+        return Err("The header stops early".to_string());
+    }
+    let scale = parse_numbers(in_lines[1], 1)?[0];
+    let mut vectors = [[0.0f32;3];3];
+    for k in 0..3 {
+        let vector = parse_numbers(in_lines[2+k], 3)?;
+        vectors[k] = [vector[0], vector[1], vector[2]];
+    }
+    // a negative scale is the volume the cell should have
+    let scale = if scale < 0.0 {
+        (-scale/Lattice::new(&vectors, &[0.0;3]).volume().abs()).cbrt()
+    } else {
+        scale
+    };
+    for vector in &mut vectors {
+        for x in vector.iter_mut() {
+            *x *= scale;
+        }
+    }
+    let names : Vec<&str> = in_lines[5].split_whitespace().collect();
+    let counts : Vec<usize> = in_lines[6].split_whitespace()
+        .map(|word| word.parse::<usize>())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| format!("Expected atom counts, not \"{}\"", in_lines[6].trim()))?;
+    if names.len() != counts.len() {
+        return Err(format!("The header names {} species but counts {}", names.len(), counts.len()));
+    }
+    Ok((Lattice::centred(&vectors), names, counts))
+}
+
+/// The frame in the text of one XDATCAR block, in its own header's cell if it starts with one,
+/// or else in in_lattice, with in_count atoms.
+fn xdatcar_frame(in_text : &str, in_lattice : &Lattice, in_count : &usize) -> Result<Frame, String> {
+    let lines : Vec<&str> = in_text.lines().skip_while(|line| line.trim().is_empty()).collect();
+    let starts_block = lines.first().map_or(false, |line| line.trim().to_lowercase().starts_with("direct"));
+    let (lattice, count, first) = if starts_block {
+        (*in_lattice, *in_count, 1)
+    } else {
+        let (lattice, _, counts) = parse_xdatcar_header(&lines)?;
+        (lattice, counts.iter().sum(), 8)
+    };
+    let mut positions = Vec::new();
+    for i in 0..count {
+        let line = lines.get(first+i).ok_or(format!("The frame stops after {} of {} atoms", i, count))?;
+        let fractional = parse_numbers(line, 3)?;
+        positions.push(lattice.to_cartesian(&[fractional[0], fractional[1], fractional[2]]));
+    }
+    Ok(Frame::new(positions, Some(lattice)))
+}
 
 /// Given a valid CASTEP cell file, scrape atomic types, positions and lattice 
 /// vectors into memory. Calculate absolute positions and pass them to main.rs
-/// to construct the molecule. 
+/// to construct the molecule. Positions may be fractional (positions_frac) or
+/// absolute (positions_abs), and either block may start with its units.
+/// Each atom keeps the label it was given, e.g. "Fe:1", as its "species" category, and
+/// the coordinates it was given, to write back; everything else in the file is kept
+/// on the molecule, to be written back out too.
+/// A missing lattice vector or a line that can't be read is an error naming it.
 ///
 /// Example: 
 /// cargo run --release test/salt.cell
-pub fn read_cell_file<'a>(fname : &String, default_species : &'a DefaultSpecies) -> Molecule<'a> {
+pub fn read_cell_file<'a>(fname : &String, default_species : &'a DefaultSpecies) -> Result<Molecule<'a>, String> {
+    let text = read_text(fname)?;
+    let (lattice, atoms, fractional, extras) = parse_cell(&text).map_err(|e| format!("{} in {}", e, fname))?;
 
-    let path = Path::new("test.cell");
-    let display = path.display();
+    let mut molecule = Molecule::new();
+    molecule.set_lattice(&lattice);
+    molecule.set_cell_extras(extras);
+    for &(ref label, ref coordinates) in &atoms {
+        molecule.add_atom(default_species.by_label(label), &cell_position(&lattice, coordinates, &fractional));
+    }
+    for (atom, &(ref label, ref coordinates)) in molecule.atoms_mut().iter_mut().zip(atoms.iter()) {
+        atom.set_category("species", label);
+        atom.set_cell_coordinates(coordinates, &fractional);
+    }
+    Ok(molecule)
+}
 
-    let mut file = File::open(fname).unwrap();
-    let mut flines = String::new();
+/// The cell, the atoms (each its species label and coordinates), whether the coordinates
+/// are fractional (or else Å from the cell's corner) and the other lines of the text of
+/// a .cell file. The cell is centred on the origin.
+pub fn parse_cell(in_text : &str) -> Result<(Lattice, Vec<(String, [f64;3])>, bool, Vec<String>), String> {
+    let mut lattice_vectors : Vec<[f32;3]> = Vec::new();
+    let mut atoms : Vec<(String, [f64;3])> = Vec::new();
+    let mut fractional = true;
+    let mut extras : Vec<String> = Vec::new();
 
-    file.read_to_string(&mut flines);
-    println!("{} contains: \n{}", fname, flines);
-    let flines : Vec<&str> = flines.split_terminator('\n').collect();
+    // the file is read a line at a time, noting which block each line is in
+    let is_structure = |block : &str| block == "lattice_cart" || block == "positions_frac" || block == "positions_abs";
+    let mut block = String::new();
+    // Å in the block's length unit
+    let mut unit = 1.0;
+    for line in in_text.lines() {
+        let lower = line.trim().to_lowercase();
+        if lower.starts_with("%block") {
+            block = lower["%block".len()..].trim().to_string();
+            unit = 1.0;
+            if block == "positions_abs" {fractional = false;}
+            if !is_structure(&block) {extras.push(line.to_string());}
+            continue;
+        } else if lower.starts_with("%endblock") {
+            if !is_structure(&block) {extras.push(line.to_string());}
+            block.clear();
+            continue;
+        } else if !is_structure(&block) {
+            extras.push(line.to_string());
+            continue;
+        }
+        // anything after a ! or # is a comment
+        let content = line.split(|c| c == '!' || c == '#').next().unwrap_or("").trim();
+        let unit_name = content.to_lowercase();
+        if content.is_empty() {
+            continue;
+        } else if unit_name == "ang" || unit_name == "bohr" || unit_name == "a0" || unit_name == "nm" {
+            unit = match unit_name.as_str() {"ang" => 1.0, "nm" => 10.0, _ => BOHR};
+        } else if block == "lattice_cart" {
+            if content.split_whitespace().count() != 3 {
+                return Err(format!("Expected a lattice vector, not \"{}\"", line.trim()));
+            }
+            let vector = parse_numbers(content, 3)?;
+            lattice_vectors.push([unit*vector[0], unit*vector[1], unit*vector[2]]);
+        } else {
+            // "label x y z", with anything after the coordinates, e.g. "SPIN=1", left alone
+            let words : Vec<&str> = content.split_whitespace().collect();
+            let coordinates = words.get(1..4)
+                .and_then(|words| words.iter().map(|word| word.parse::<f64>().ok()).collect::<Option<Vec<f64>>>())
+                .ok_or(format!("Expected a species and three numbers in \"{}\"", line.trim()))?;
+            let scale = if block == "positions_abs" {unit as f64} else {1.0};
+            atoms.push((words[0].to_string(), [scale*coordinates[0], scale*coordinates[1], scale*coordinates[2]]));
+        }
+    }
 
-    let mut lattice_cart : Vec<Vec<f32>> = Vec::new();
-    let mut positions_frac : Vec<Vec<f32>> = Vec::new();
-    let mut species_list : Vec<&str> = Vec::new();
+    if lattice_vectors.len() != 3 {
+        return Err(format!("Expected 3 lattice vectors, not {}", lattice_vectors.len()));
+    }
+    let lattice = Lattice::centred(&[lattice_vectors[0], lattice_vectors[1], lattice_vectors[2]]);
+    Ok((lattice, atoms, fractional, extras))
+}
 
-    for (i, line) in flines.iter().enumerate() {
-        if line.to_lowercase() == "%block lattice_cart" {
-            let mut j = i;
-            loop {
-                j += 1;
-                if flines[j].to_lowercase() == "%endblock lattice_cart" {
-                    break;
-                } else {
-                    let temp : Vec<f32> = flines[j]
-                        .split_whitespace().map(|s| s.parse::<f32>().unwrap()).collect();
-                    lattice_cart.push(temp);
+/// Where coordinates from a .cell file put an atom in in_lattice: fractional ones across
+/// the cell, or else absolute ones from its corner.
+pub fn cell_position(in_lattice : &Lattice, in_coordinates : &[f64;3], in_fractional : &bool) -> [f32;3] {
+    let coordinates = [in_coordinates[0] as f32, in_coordinates[1] as f32, in_coordinates[2] as f32];
+    if *in_fractional {
+        in_lattice.to_cartesian(&coordinates)
+    } else {
+        let origin = in_lattice.origin();
+        [coordinates[0]+origin[0], coordinates[1]+origin[1], coordinates[2]+origin[2]]
+    }
+}
+
+/// Å in a Bohr radius.
+const BOHR : f32 = 0.529177211;
+/// eV in a Hartree.
+const HARTREE : f32 = 27.211386;
+/// ps in the atomic unit of time.
+const ATOMIC_TIME : f32 = 2.418884e-5;
+
+/// Reads a CASTEP .geom (geometry optimisation) or .md (molecular dynamics) file.
+/// After the header, each step is a block of lines tagged at the end, in atomic units:
+/// "<-- E" energies (the first is the total), "<-- h" the three lattice vectors, and
+/// "SPECIES INDEX x y z" lines for "<-- R" positions, "<-- V" velocities and "<-- F" forces.
+/// Blocks are separated by blank lines. Other tags (T, P, S, hv) are skipped.
+/// Steps are indexed, and read when they are shown.
+pub fn read_castep_trajectory<'a>(fname : &str, default_species : &'a DefaultSpecies) -> Result<Molecule<'a>, String> {
+    // a step starts on the first line after a blank one, once past the header
+    let in_header = Cell::new(false);
+    let after_blank = Cell::new(true);
+    let frame_length = |line : &str| -> Result<Option<usize>, String> {
+        let line = line.trim();
+        if line == "BEGIN header" {
+            in_header.set(true);
+        } else if in_header.get() {
+            if line == "END header" {in_header.set(false);}
+        } else {
+            let starts = after_blank.get() && !line.is_empty();
+            after_blank.set(line.is_empty());
+            if starts {return Ok(Some(1));}
+        }
+        Ok(None)
+    };
+    let offsets = index_frames(fname, &frame_length)?;
+    if offsets.len() < 2 {
+        return Err(format!("There are no steps in {}", fname));
+    }
+    let mut index = FrameIndex::new(fname, offsets, castep_step);
+    let (labels, _) = parse_castep_step(&index.text(&0)?)
+        .map_err(|e| format!("Could not read the first step of {}: {}", fname, e))?;
+    let species : Vec<&'a Species<'a>> = labels.iter().map(|label| default_species.by_label(label)).collect();
+    build_molecule(&species, Frames::OnDisk(index))
+}
+
+/// The species labels and frame in the text of one step of a CASTEP .geom or .md file.
+fn parse_castep_step(in_text : &str) -> Result<(Vec<String>, Frame), String> {
+    let mut energy : Option<f32> = None;
+    let mut vectors : Vec<[f32;3]> = Vec::new();
+    let mut labels : Vec<String> = Vec::new();
+    let mut positions : Vec<[f32;3]> = Vec::new();
+    let mut velocities : Vec<[f32;3]> = Vec::new();
+    let mut forces : Vec<[f32;3]> = Vec::new();
+    for line in in_text.lines() {
+        let (values, tag) = match line.find("<--") {
+            Some(arrow) => (&line[..arrow], line[arrow+3..].trim()),
+            None => continue,
+        };
+        match tag {
+            "E" => {
+                if energy.is_none() {
+                    energy = Some(HARTREE*parse_numbers(values, 1)?[0]);
+                }
+            },
+            "h" => {
+                let vector = parse_numbers(values, 3)?;
+                vectors.push([BOHR*vector[0], BOHR*vector[1], BOHR*vector[2]]);
+            },
+            "R" | "V" | "F" => {
+                let words : Vec<&str> = values.split_whitespace().collect();
+                if words.len() < 5 {
+                    return Err(format!("Expected a species, index and three numbers in \"{}\"", line.trim()));
+                }
+                let vector = parse_numbers(&words[2..].join(" "), 3)?;
+                let unit = match tag {
+                    "R" => BOHR,
+                    "V" => BOHR/ATOMIC_TIME,
+                    _ => HARTREE/BOHR,
+                };
+                let vector = [unit*vector[0], unit*vector[1], unit*vector[2]];
+                match tag {
+                    "R" => {
+                        labels.push(words[0].to_string());
+                        positions.push(vector);
+                    },
+                    "V" => velocities.push(vector),
+                    _ => forces.push(vector),
                 }
+            },
+            _ => {},
+        }
+    }
+    if positions.is_empty() {
+        return Err("There are no positions in the step".to_string());
+    }
+    let lattice = if vectors.len() == 3 {
+        Some(Lattice::new(&[vectors[0], vectors[1], vectors[2]], &[0.0;3]))
+    } else {
+        None
+    };
+    let count = positions.len();
+    let mut frame = Frame::new(positions, lattice);
+    if forces.len() == count {frame.set_forces(forces);}
+    if velocities.len() == count {frame.set_velocities(velocities);}
+    if let Some(energy) = energy {frame.set_energy(&energy);}
+    Ok((labels, frame))
+}
+
+fn castep_step(in_text : &str) -> Result<Frame, String> {parse_castep_step(in_text).map(|(_, frame)| frame)}
+
+/// Reads the main CASTEP output. Each set of fractional coordinates printed (at the start,
+/// and after every geometry step) is a frame, in the unit cell printed last before it.
+/// Forces, final energies and Mulliken charges and spins belong to the frame before them,
+/// and become the "charge" and "spin" properties. The final configuration of an
+/// optimisation, which repeats the last step, doesn't make a new frame.
+/// Frames are indexed, from the unit cell or coordinates that start them, and read when they are shown.
+pub fn read_castep_file<'a>(fname : &str, default_species : &'a DefaultSpecies) -> Result<Molecule<'a>, String> {
+    // a frame starts at its unit cell, or at its coordinates if the cell wasn't printed again
+    let finished = Cell::new(false);
+    let has_cell = Cell::new(false);
+    let frame_length = |line : &str| -> Result<Option<usize>, String> {
+        if finished.get() {
+            return Ok(None);
+        } else if line.contains("Final Configuration") {
+            finished.set(true);
+        } else if line.contains("Real Lattice(A)") {
+            has_cell.set(true);
+            return Ok(Some(1));
+        } else if line.contains("Fractional coordinates of atoms") {
+            let starts = !has_cell.get();
+            has_cell.set(false);
+            if starts {return Ok(Some(1));}
+        }
+        Ok(None)
+    };
+    let offsets = index_frames(fname, &frame_length)?;
+    if offsets.len() < 2 {
+        return Err(format!("There are no atomic coordinates in {}", fname));
+    }
+    let mut index = FrameIndex::new(fname, offsets.clone(), |text : &str| castep_frame(text, None));
+    let (labels, first) = parse_castep_frame(&index.text(&0)?, None)
+        .map_err(|e| format!("Could not read the first frame of {}: {}", fname, e))?;
+    // later frames without a cell of their own keep the first one
+    let lattice = first.lattice().cloned();
+    let index = FrameIndex::new(fname, offsets, move |text : &str| castep_frame(text, lattice));
+    let species : Vec<&'a Species<'a>> = labels.iter().map(|label| default_species.by_label(label)).collect();
+    build_molecule(&species, Frames::OnDisk(index))
+}
+
+/// The species labels and frame in the text of one frame of a CASTEP output file,
+/// in in_lattice unless the text gives a cell before its coordinates.
+fn parse_castep_frame(in_text : &str, in_lattice : Option<Lattice>) -> Result<(Vec<String>, Frame), String> {
+    let lines : Vec<&str> = in_text.lines().collect();
+    let mut lattice = in_lattice;
+    let mut labels : Vec<String> = Vec::new();
+    let mut frame : Option<Frame> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.contains("Real Lattice(A)") && frame.is_none() {
+            // three rows of a, b, c, with the reciprocal vectors alongside
+            let mut vectors = [[0.0f32;3];3];
+            for k in 0..3 {
+                let vector = parse_numbers(lines.get(i+1+k).ok_or("The frame stops in a unit cell".to_string())?, 3)?;
+                vectors[k] = [vector[0], vector[1], vector[2]];
             }
-        } else if line.to_lowercase() == "%block positions_frac" {
-            let mut j = i;
-            loop {
-                j += 1;
-                if flines[j].to_lowercase() == "%endblock positions_frac" {
-                    break;
-                } else {
-                    let temp : Vec<&str> = flines[j].split_whitespace().collect();
-                    let mut temp_pos : Vec<f32> = Vec::new();
-                    for k in 1..4 {
-                        temp_pos.push(temp[k].parse().unwrap());
-                    }
-                    let atom = temp[0];
-                    positions_frac.push(temp_pos);
-                    species_list.push(atom);
+            lattice = Some(Lattice::centred(&vectors));
+            i += 4;
+        } else if line.contains("Fractional coordinates of atoms") && frame.is_none() {
+            // rows of "x  SPECIES  INDEX  u  v  w  x", between a dashed line and a line of x's
+            let lattice = lattice.ok_or("There are coordinates before a unit cell".to_string())?;
+            let mut positions = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim().starts_with("xxxx") {
+                let words : Vec<&str> = lines[i].split_whitespace().collect();
+                if words.len() >= 6 && words[0] == "x" && words[2].parse::<usize>().is_ok() {
+                    let fractional = parse_numbers(&words[3..6].join(" "), 3)?;
+                    labels.push(words[1].to_string());
+                    positions.push(lattice.to_cartesian(&[fractional[0], fractional[1], fractional[2]]));
+                }
+                i += 1;
+            }
+            frame = Some(Frame::new(positions, Some(lattice)));
+        } else if line.trim_start().starts_with('*') && line.contains(" Forces ") {
+            // rows of "*  SPECIES  INDEX  fx  fy  fz  *" in eV/Å, until a line of stars
+            let mut forces = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim().starts_with("*****") {
+                let words : Vec<&str> = lines[i].split_whitespace().collect();
+                if words.len() >= 6 && words[0] == "*" && words[2].parse::<usize>().is_ok() {
+                    let force = parse_numbers(&words[3..6].join(" "), 3)?;
+                    forces.push([force[0], force[1], force[2]]);
                 }
+                i += 1;
+            }
+            if let Some(ref mut frame) = frame {
+                if forces.len() == frame.positions().len() {frame.set_forces(forces);}
+            }
+        } else if line.trim_start().starts_with("Final energy") {
+            // "Final energy, E             =  -8.448 eV", or "Final energy =" in older versions
+            let energy = line.split('=').nth(1).and_then(|value| value.split_whitespace().next());
+            if let (Some(frame), Some(Ok(energy))) = (frame.as_mut(), energy.map(|e| e.parse::<f32>())) {
+                frame.set_energy(&energy);
             }
+            i += 1;
+        } else if line.contains("Atomic Populations (Mulliken)") {
+            // a table between lines of '=': the charge is the last column, or the one
+            // before the spin in spin-polarised runs, whose second rows ("dn:") are skipped
+            let mut charges = Vec::new();
+            let mut spins = Vec::new();
+            let mut has_spin = false;
+            let mut rules = 0;
+            i += 1;
+            while i < lines.len() && rules < 2 {
+                let line = lines[i].trim();
+                i += 1;
+                if line.starts_with("====") {
+                    rules += 1;
+                    continue;
+                }
+                if rules == 0 {
+                    if line.contains("Spin") {has_spin = true;}
+                    continue;
+                }
+                let words : Vec<&str> = line.split_whitespace().collect();
+                if words.len() < 3 || words[1].parse::<usize>().is_err() {continue;}
+                let numbers : Vec<f32> = words.iter().filter_map(|word| word.parse::<f32>().ok()).collect();
+                if has_spin && numbers.len() >= 3 {
+                    charges.push(numbers[numbers.len()-2]);
+                    spins.push(numbers[numbers.len()-1]);
+                } else if let Some(&charge) = numbers.last() {
+                    charges.push(charge);
+                }
+            }
+            if let Some(ref mut frame) = frame {
+                if charges.len() == frame.positions().len() {frame.set_property("charge", charges);}
+                if spins.len() == frame.positions().len() {frame.set_property("spin", spins);}
+            }
+        } else {
+            i += 1;
         }
     }
+    let frame = frame.ok_or("There are no atomic coordinates in the frame".to_string())?;
+    Ok((labels, frame))
+}
 
-    println!("Parsed lattice vectors: {:?}", lattice_cart);
-    println!("Parsed fractional coordinates: {:?}", positions_frac);
-    println!("Parsed atomic species: {:?}", species_list);
+fn castep_frame(in_text : &str, in_lattice : Option<Lattice>) -> Result<Frame, String> {
+    parse_castep_frame(in_text, in_lattice).map(|(_, frame)| frame)
+}
 
-    let mut molecule = Molecule::new();
+/// The species of LAMMPS atom type in_type: named in in_type_names, or else by the comment
+/// or mass given for it in a data file's Masses section, or else unknown.
+fn lammps_species<'a>(
+    default_species : &'a DefaultSpecies,
+    in_type         : &usize,
+    in_type_names   : &[String],
+    in_masses       : &[(usize, f32, String)],
+) -> Result<&'a Species<'a>, String> {
+    if *in_type == 0 {
+        return Err("LAMMPS atom types start at 1, not 0".to_string());
+    }
+    if let Some(name) = in_type_names.get(in_type-1) {
+        return Ok(default_species.by_label(name));
+    }
+    if let Some(&(_, mass, ref comment)) = in_masses.iter().find(|entry| entry.0 == *in_type) {
+        if elements::find(&elements::symbol_from_label(comment)).is_some() {
+            return Ok(default_species.by_label(comment));
+        }
+        if let Some(symbol) = elements::symbol_from_mass(&mass) {
+            return Ok(default_species.by_label(symbol));
+        }
+    }
+    Ok(default_species.by_label(&format!("type {}", in_type)))
+}
+
+/// The cell of a LAMMPS box, from its lower and upper bounds and tilt factors xy, xz, yz.
+fn lammps_lattice(in_low : &[f32;3], in_high : &[f32;3], in_tilt : &[f32;3]) -> Lattice {
+    Lattice::new(&[
+        [in_high[0]-in_low[0], 0.0                 , 0.0                 ],
+        [in_tilt[0]          , in_high[1]-in_low[1], 0.0                 ],
+        [in_tilt[1]          , in_tilt[2]          , in_high[2]-in_low[2]],
+    ], in_low)
+}
+
+/// in_position moved by whole cells, to undo LAMMPS wrapping it back into the box.
+fn unwrap_image(in_lattice : &Lattice, in_position : &[f32;3], in_image : &[f32;3]) -> [f32;3] {
+    let shift = in_lattice.direction(in_image);
+    [in_position[0]+shift[0], in_position[1]+shift[1], in_position[2]+shift[2]]
+}
+
+/// Reads a LAMMPS data file as written by write_data: a header of counts and box bounds,
+/// then sections. Atoms may be in the atomic (id type x y z), charge (id type q x y z)
+/// or full (id mol type q x y z) styles, given by the comment after "Atoms" or else told
+/// apart by the number of columns, each optionally followed by image flags.
+/// Charges become the "charge" property, and molecule IDs and types categories.
+pub fn read_lammps_data_file<'a>(
+    fname           : &str,
+    default_species : &'a DefaultSpecies,
+    in_type_names   : &[String],
+) -> Result<Molecule<'a>, String> {
+    let text = read_text(fname)?;
+    let mut low = [0.0f32;3];
+    let mut high = [1.0f32;3];
+    let mut tilt = [0.0f32;3];
+    // (type, mass, comment)
+    let mut masses : Vec<(usize, f32, String)> = Vec::new();
+    // (id, molecule, type, charge, position, image)
+    let mut atoms : Vec<(usize, Option<usize>, usize, Option<f32>, [f32;3], [f32;3])> = Vec::new();
+    let mut velocities : Vec<(usize, [f32;3])> = Vec::new();
+    let mut section = String::new();
+    let mut style = String::new();
+
+    // the first line is a comment
+    for line in text.lines().skip(1) {
+        let (content, comment) = match line.find('#') {
+            Some(hash) => (&line[..hash], line[hash+1..].trim()),
+            None => (line, ""),
+        };
+        let words : Vec<&str> = content.split_whitespace().collect();
+        if words.is_empty() {continue;}
+        if words[0].parse::<f32>().is_err() {
+            // a section heading, e.g. "Atoms # full"
+            section = words.join(" ");
+            if section == "Atoms" {style = comment.to_string();}
+            continue;
+        }
+        let numbers : Vec<f32> = words.iter().filter_map(|word| word.parse::<f32>().ok()).collect();
+        if section.is_empty() {
+            // the header, e.g. "0.0 10.0 xlo xhi" or "5.0 0.0 0.0 xy xz yz"
+            if words.len() < 2 {continue;}
+            // exactly in_count numbers before as many keywords, or else an error
+            let header_numbers = |in_count : usize| -> Result<Vec<f32>, String> {
+                if words.len() != 2*in_count {
+                    return Err(format!("Could not read \"{}\" in the header of {}", line.trim(), fname));
+                }
+                parse_numbers(&words[..in_count].join(" "), in_count).map_err(|e| format!("{} in {}", e, fname))
+            };
+            match &words[words.len()-2..] {
+                ["xlo", "xhi"] | ["ylo", "yhi"] | ["zlo", "zhi"] => {
+                    let l = match words[words.len()-2] {"xlo" => 0, "ylo" => 1, _ => 2};
+                    let bounds = header_numbers(2)?;
+                    low[l] = bounds[0];
+                    high[l] = bounds[1];
+                },
+                ["xz", "yz"] => {
+                    let factors = header_numbers(3)?;
+                    tilt = [factors[0], factors[1], factors[2]];
+                },
+                _ => {},
+            }
+            continue;
+        }
+        let bad_line = || format!("Could not read \"{}\" in the {} section of {}", line.trim(), section, fname);
+        // IDs, types and molecules are integers, which needn't survive a trip through f32
+        let integer = |column : usize| words.get(column).and_then(|word| word.parse::<usize>().ok()).ok_or_else(bad_line);
+        match section.as_str() {
+            "Masses" => {
+                if numbers.len() < 2 {return Err(bad_line());}
+                masses.push((integer(0)?, numbers[1], comment.to_string()));
+            },
+            "Atoms" => {
+                let columns = if style.is_empty() {
+                    match numbers.len() {
+                        5 | 8 => "atomic",
+                        6 | 9 => "charge",
+                        _ => "full",
+                    }
+                } else {
+                    style.as_str()
+                };
+                let (molecule, kind, charge, first) = match columns {
+                    "atomic" => (None, 1, None, 2),
+                    "charge" => (None, 1, Some(2), 3),
+                    "full" => (Some(1), 2, Some(3), 4),
+                    _ => return Err(format!("{} has atoms in the {} style, which oxide can't read", fname, style)),
+                };
+                if numbers.len() < first+3 {return Err(bad_line());}
+                let image = if numbers.len() >= first+6 {
+                    [numbers[first+3], numbers[first+4], numbers[first+5]]
+                } else {
+                    [0.0;3]
+                };
+                atoms.push((
+                    integer(0)?,
+                    match molecule {Some(column) => Some(integer(column)?), None => None},
+                    integer(kind)?,
+                    charge.map(|column| numbers[column]),
+                    [numbers[first], numbers[first+1], numbers[first+2]],
+                    image,
+                ));
+            },
+            "Velocities" => {
+                if numbers.len() < 4 {return Err(bad_line());}
+                velocities.push((integer(0)?, [numbers[1], numbers[2], numbers[3]]));
+            },
+            _ => {},
+        }
+    }
+    if atoms.is_empty() {
+        return Err(format!("There are no atoms in {}", fname));
+    }
 
-    for (i, atom) in species_list.iter().enumerate() {
-        let mut temp_pos : [f32; 3] = [0.0; 3];
-        for k in 0..3 {
-            for l in 0..3 {
-                temp_pos[l] += lattice_cart[k][l] * positions_frac[i][k] - lattice_cart[k][l]/2.0;
+    atoms.sort_by_key(|atom| atom.0);
+    velocities.sort_by_key(|velocity| velocity.0);
+    let lattice = lammps_lattice(&low, &high, &tilt);
+    let mut types : Vec<(usize, &'a Species<'a>)> = Vec::new();
+    let mut species = Vec::new();
+    for atom in &atoms {
+        if !types.iter().any(|entry| entry.0 == atom.2) {
+            types.push((atom.2, lammps_species(default_species, &atom.2, in_type_names, &masses)?));
+        }
+        species.push(types.iter().find(|entry| entry.0 == atom.2).unwrap().1);
+    }
+    let positions = atoms.iter().map(|atom| unwrap_image(&lattice, &atom.4, &atom.5)).collect();
+    let mut frame = Frame::new(positions, Some(lattice));
+    if velocities.len() == atoms.len() {
+        frame.set_velocities(velocities.iter().map(|velocity| velocity.1).collect());
+    }
+    if atoms.iter().all(|atom| atom.3.is_some()) {
+        frame.set_property("charge", atoms.iter().map(|atom| atom.3.unwrap()).collect());
+    }
+    let mut molecule = build_molecule(&species, Frames::InMemory(vec![frame]))?;
+    for (atom, data) in molecule.atoms_mut().iter_mut().zip(atoms.iter()) {
+        atom.set_category("type", &format!("{}", data.2));
+        if let Some(number) = data.1 {
+            atom.set_category("molecule", &format!("{}", number));
+        }
+    }
+    Ok(molecule)
+}
+
+/// Reads a LAMMPS dump file written with dump custom (or atom). Frames are indexed by their
+/// ITEM: TIMESTEP lines, and read when they are shown.
+/// Positions may be unscaled (x y z), scaled (xs ys zs), unwrapped (xu yu zu, xsu ysu zsu)
+/// or wrapped with image flags (ix iy iz). Elements come from an element column, or else
+/// from the types. Velocities and forces are read, and any other numeric columns become
+/// properties named after them, except q, which is "charge".
+pub fn read_lammps_dump_file<'a>(
+    fname           : &str,
+    default_species : &'a DefaultSpecies,
+    in_type_names   : &[String],
+) -> Result<Molecule<'a>, String> {
+    let frame_length = |line : &str| -> Result<Option<usize>, String> {
+        Ok(if line.trim().starts_with("ITEM: TIMESTEP") {Some(1)} else {None})
+    };
+    let offsets = index_frames(fname, &frame_length)?;
+    if offsets.len() < 2 {
+        return Err(format!("There are no frames in {}", fname));
+    }
+    let mut index = FrameIndex::new(fname, offsets, dump_frame);
+    let (_, labels) = parse_dump_frame(&index.text(&0)?)
+        .map_err(|e| format!("Could not read the first frame of {}: {}", fname, e))?;
+    let column = |name : &str| labels.iter().find(|entry| entry.0 == name).map(|entry| &entry.1);
+
+    let mut species : Vec<&'a Species<'a>> = Vec::new();
+    match (column("element"), column("type")) {
+        (Some(elements), _) => for element in elements {
+            species.push(default_species.by_label(element));
+        },
+        (None, Some(types)) => {
+            let mut known : Vec<(&String, &'a Species<'a>)> = Vec::new();
+            for kind in types {
+                let atom_species = match known.iter().find(|entry| entry.0 == kind) {
+                    Some(entry) => entry.1,
+                    None => {
+                        let number = kind.parse::<usize>().map_err(|_| format!("\"{}\" is not an atom type", kind))?;
+                        let atom_species = lammps_species(default_species, &number, in_type_names, &[])?;
+                        known.push((kind, atom_species));
+                        atom_species
+                    },
+                };
+                species.push(atom_species);
+            }
+        },
+        (None, None) => return Err(format!("{} gives neither elements nor types", fname)),
+    }
+
+    let mut molecule = build_molecule(&species, Frames::OnDisk(index))?;
+    for &(name, key) in [("type", "type"), ("molecule", "mol")].iter() {
+        if let Some(values) = column(key) {
+            for (atom, value) in molecule.atoms_mut().iter_mut().zip(values) {
+                atom.set_category(name, value);
+            }
+        }
+    }
+    Ok(molecule)
+}
+
+fn dump_frame(in_text : &str) -> Result<Frame, String> {parse_dump_frame(in_text).map(|(frame, _)| frame)}
+
+/// The frame in the text of one dump frame, and its element, type and mol columns
+/// where it has them, in order of atom ID.
+fn parse_dump_frame(in_text : &str) -> Result<(Frame, Vec<(String, Vec<String>)>), String> {
+    let mut lines = in_text.lines();
+    let mut count = 0;
+    let mut lattice = lammps_lattice(&[0.0;3], &[1.0;3], &[0.0;3]);
+
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.starts_with("ITEM: NUMBER OF ATOMS") {
+            let line = lines.next().ok_or("The frame stops after ITEM: NUMBER OF ATOMS".to_string())?;
+            count = line.trim().parse::<usize>().map_err(|_| format!("Expected an atom count, not \"{}\"", line.trim()))?;
+        } else if line.starts_with("ITEM: BOX BOUNDS") {
+            // "lo hi [tilt]" per axis, where the bounds of a tilted box include the tilt
+            let mut bounds = Vec::new();
+            for _ in 0..3 {
+                let line = lines.next().ok_or("The frame stops in its box".to_string())?;
+                let columns = if line.split_whitespace().count() >= 3 {3} else {2};
+                bounds.push(parse_numbers(line, columns)?);
+            }
+            let tilt = [
+                *bounds[0].get(2).unwrap_or(&0.0),
+                *bounds[1].get(2).unwrap_or(&0.0),
+                *bounds[2].get(2).unwrap_or(&0.0),
+            ];
+            let x_extra = [0.0, tilt[0], tilt[1], tilt[0]+tilt[1]];
+            let low = [
+                bounds[0][0]-x_extra.iter().cloned().fold(0.0, f32::min),
+                bounds[1][0]-tilt[2].min(0.0),
+                bounds[2][0],
+            ];
+            let high = [
+                bounds[0][1]-x_extra.iter().cloned().fold(0.0, f32::max),
+                bounds[1][1]-tilt[2].max(0.0),
+                bounds[2][1],
+            ];
+            lattice = lammps_lattice(&low, &high, &tilt);
+        } else if line.starts_with("ITEM: ATOMS") {
+            let columns : Vec<&str> = line["ITEM: ATOMS".len()..].split_whitespace().collect();
+            let column = |name : &str| columns.iter().position(|c| *c == name);
+            let triple = |names : [&str;3]| -> Option<[usize;3]> {
+                match (column(names[0]), column(names[1]), column(names[2])) {
+                    (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+                    _ => None,
+                }
+            };
+            let (position_columns, scaled, wrapped) = if let Some(c) = triple(["xu", "yu", "zu"]) {
+                (c, false, false)
+            } else if let Some(c) = triple(["xsu", "ysu", "zsu"]) {
+                (c, true, false)
+            } else if let Some(c) = triple(["x", "y", "z"]) {
+                (c, false, true)
+            } else if let Some(c) = triple(["xs", "ys", "zs"]) {
+                (c, true, true)
+            } else {
+                return Err(format!("There are no atomic positions in \"{}\"", line));
+            };
+            let image_columns = if wrapped {triple(["ix", "iy", "iz"])} else {None};
+            let velocity_columns = triple(["vx", "vy", "vz"]);
+            let force_columns = triple(["fx", "fy", "fz"]);
+            let id_column = column("id");
+            let label_columns = ["element", "type", "mol"];
+            let mut used : Vec<usize> = id_column.iter().cloned().collect();
+            used.extend(label_columns.iter().filter_map(|name| column(name)));
+            for &columns in [Some(position_columns), image_columns, velocity_columns, force_columns].iter() {
+                if let Some(columns) = columns {used.extend_from_slice(&columns);}
+            }
+
+            let mut rows : Vec<(usize, Vec<&str>)> = Vec::new();
+            for i in 0..count {
+                let line = lines.next().ok_or(format!("The frame stops after {} of {} atoms", i, count))?;
+                let words : Vec<&str> = line.split_whitespace().collect();
+                if words.len() < columns.len() {
+                    return Err(format!("Expected {} columns in \"{}\"", columns.len(), line.trim()));
+                }
+                let id = match id_column {
+                    Some(c) => words[c].parse::<usize>().map_err(|_| format!("\"{}\" is not an atom ID", words[c]))?,
+                    None => i+1,
+                };
+                rows.push((id, words));
+            }
+            // atoms can come in any order
+            rows.sort_by_key(|row| row.0);
+
+            let number = |words : &Vec<&str>, c : usize| -> Result<f32, String> {
+                words[c].parse::<f32>().map_err(|_| format!("\"{}\" is not a number", words[c]))
+            };
+            let vector = |words : &Vec<&str>, c : [usize;3]| -> Result<[f32;3], String> {
+                Ok([number(words, c[0])?, number(words, c[1])?, number(words, c[2])?])
+            };
+            let mut positions = Vec::new();
+            for &(_, ref words) in &rows {
+                let mut position = vector(words, position_columns)?;
+                if scaled {
+                    position = lattice.to_cartesian(&position);
+                }
+                if let Some(c) = image_columns {
+                    position = unwrap_image(&lattice, &position, &vector(words, c)?);
+                }
+                positions.push(position);
+            }
+
+            let mut frame = Frame::new(positions, Some(lattice));
+            if let Some(c) = velocity_columns {
+                frame.set_velocities(rows.iter().map(|row| vector(&row.1, c)).collect::<Result<Vec<_>, _>>()?);
+            }
+            if let Some(c) = force_columns {
+                frame.set_forces(rows.iter().map(|row| vector(&row.1, c)).collect::<Result<Vec<_>, _>>()?);
+            }
+            for (c, name) in columns.iter().enumerate() {
+                if used.contains(&c) {continue;}
+                let values : Result<Vec<f32>, String> = rows.iter().map(|row| number(&row.1, c)).collect();
+                if let Ok(values) = values {
+                    frame.set_property(if *name == "q" {"charge"} else {name}, values);
+                }
             }
+            let labels = label_columns.iter()
+                .filter_map(|name| column(name).map(|c| (name.to_string(), rows.iter().map(|row| row.1[c].to_string()).collect())))
+                .collect();
+            return Ok((frame, labels));
         }
-        // just stick to oxygen for now
-        molecule.add_atom(default_species.oxygen(), &temp_pos);
     }
-   return molecule
+    Err("The frame has no ITEM: ATOMS".to_string())
 }
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use molecule::Molecule;
+use lattice::Lattice;
+use file_input::cell_position;
+
+/// Writes the molecule as a CASTEP .cell file: its lattice vectors in lattice_cart,
+/// then its atoms in positions_frac, or in positions_abs (Å from the cell's corner) if
+/// not in_fractional. Atoms keep the labels they were read with, e.g. "Fe:1", so that
+/// species_pot and spin blocks still match them, and atoms that haven't moved keep the
+/// coordinates they were read with. Other numbers are written as the shortest text
+/// that reads back as the same single-precision value.
+/// Anything else read from a .cell file, e.g. kpoints, constraints and species_pot,
+/// is written after them, with any comments that headed it kept at the top.
+///
+/// Example:
+/// write_cell_file(Path::new("salt_edited.cell"), &molecule, &true)
+pub fn write_cell_file(in_path : &Path, in_molecule : &Molecule, in_fractional : &bool) -> io::Result<()> {
+    let lattice = match in_molecule.lattice() {
+        Some(lattice) => lattice,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "a .cell file needs a unit cell")),
+    };
+    let atoms : Vec<CellAtom> = in_molecule.atoms().iter()
+        .map(|atom| (
+            atom.category("species").unwrap_or(atom.species().name()).to_string(),
+            *atom.position(),
+            atom.cell_coordinates(),
+        ))
+        .collect();
+    let text = format_cell(lattice, &atoms, in_fractional, in_molecule.cell_extras());
+    File::create(in_path)?.write_all(text.as_bytes())
+}
+
+/// An atom to write to a .cell file: its species label, Cartesian position and any
+/// coordinates it was read with, and whether those were fractional.
+pub type CellAtom = (String, [f32;3], Option<([f64;3], bool)>);
+
+/// The text of a .cell file for a cell and its atoms, followed by in_extras,
+/// the other lines of the file they came from.
+pub fn format_cell(
+    in_lattice    : &Lattice,
+    in_atoms      : &[CellAtom],
+    in_fractional : &bool,
+    in_extras     : &[String],
+) -> String {
+    // the comments at the top stay there, and the blank lines around the blocks are written afresh
+    let mut header = in_extras.iter()
+        .take_while(|line| line.trim().is_empty() || line.trim().starts_with('#') || line.trim().starts_with('!'))
+        .count();
+    while header > 0 && in_extras[header-1].trim().is_empty() {
+        header -= 1;
+    }
+    let rest : Vec<&String> = in_extras[header..].iter().skip_while(|line| line.trim().is_empty()).collect();
+
+    let mut lines = vec![WRITTEN_BY.to_string()];
+    // a file written before says so once
+    lines.extend(in_extras[..header].iter().filter(|line| line.trim() != WRITTEN_BY).cloned());
+    lines.push(String::new());
+
+    lines.push("%BLOCK LATTICE_CART".to_string());
+    lines.push("ang".to_string());
+    for vector in in_lattice.vectors() {
+        lines.push(format!("  {:>16} {:>16} {:>16}", number(&vector[0]), number(&vector[1]), number(&vector[2])));
+    }
+    lines.push("%ENDBLOCK LATTICE_CART".to_string());
+    lines.push(String::new());
+
+    let block = if *in_fractional {"POSITIONS_FRAC"} else {"POSITIONS_ABS"};
+    lines.push(format!("%BLOCK {}", block));
+    if !*in_fractional {
+        lines.push("ang".to_string());
+    }
+    let origin = in_lattice.origin();
+    for &(ref label, ref position, coordinates) in in_atoms {
+        let coordinates : [String;3] = match coordinates {
+            // the coordinates read still put the atom exactly where it is
+            Some((coordinates, fractional)) if fractional == *in_fractional
+                && cell_position(in_lattice, &coordinates, &fractional) == *position => {
+                [number(&coordinates[0]), number(&coordinates[1]), number(&coordinates[2])]
+            },
+            _ => {
+                let position = if *in_fractional {
+                    in_lattice.to_fractional(position)
+                } else {
+                    [position[0]-origin[0], position[1]-origin[1], position[2]-origin[2]]
+                };
+                [number(&position[0]), number(&position[1]), number(&position[2])]
+            },
+        };
+        lines.push(format!("{:<4} {:>16} {:>16} {:>16}", label, coordinates[0], coordinates[1], coordinates[2]));
+    }
+    lines.push(format!("%ENDBLOCK {}", block));
+
+    if !rest.is_empty() {
+        lines.push(String::new());
+    }
+    lines.extend(rest.into_iter().cloned());
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+const WRITTEN_BY : &str = "# Written by Oxide";
+
+/// A number as the shortest text that reads back as the same value, so that nothing
+/// is lost in writing it; zero is written without a minus sign.
+fn number<T : fmt::Display + Into<f64> + Copy>(in_value : &T) -> String {
+    if (*in_value).into() == 0.0 {
+        "0".to_string()
+    } else {
+        format!("{}", in_value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use file_input::parse_cell;
+
+    fn read_salt() -> String {
+        let mut text = String::new();
+        File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test/salt.cell")).unwrap().read_to_string(&mut text).unwrap();
+        text
+    }
+
+    fn assert_close(in_a : &[f32;3], in_b : &[f32;3], in_tolerance : f32) {
+        for l in 0..3 {
+            assert!((in_a[l]-in_b[l]).abs() < in_tolerance, "{:?} is not {:?}", in_a, in_b);
+        }
+    }
+
+    /// The atoms of a parsed .cell file as write_cell_file gives them to format_cell.
+    fn cell_atoms(in_lattice : &Lattice, in_atoms : &[(String, [f64;3])], in_fractional : &bool) -> Vec<CellAtom> {
+        in_atoms.iter()
+            .map(|&(ref label, coordinates)| {
+                (label.clone(), cell_position(in_lattice, &coordinates, in_fractional), Some((coordinates, *in_fractional)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn salt_round_trips() {
+        let (lattice, atoms, fractional, extras) = parse_cell(&read_salt()).unwrap();
+        assert_eq!(atoms.len(), 128);
+        let positions = cell_atoms(&lattice, &atoms, &fractional);
+        for &fractional_again in &[true, false] {
+            let text = format_cell(&lattice, &positions, &fractional_again, &extras);
+            let (lattice_again, atoms_again, _, extras_again) = parse_cell(&text).unwrap();
+            assert_eq!(lattice.vectors(), lattice_again.vectors());
+            let positions_again = cell_atoms(&lattice_again, &atoms_again, &fractional_again);
+            assert_eq!(positions.len(), positions_again.len());
+            for (atom, atom_again) in positions.iter().zip(positions_again.iter()) {
+                assert_eq!(atom.0, atom_again.0);
+                assert_close(&atom.1, &atom_again.1, 1e-5);
+            }
+            // the comments at the top survive, with a line saying who wrote the file
+            let comments = |lines : &Vec<String>| -> Vec<String> {
+                lines.iter().filter(|line| !line.trim().is_empty() && line.trim() != WRITTEN_BY).cloned().collect()
+            };
+            assert_eq!(comments(&extras), comments(&extras_again));
+        }
+        // fractional coordinates come back exactly as they were read
+        let text = format_cell(&lattice, &positions, &true, &extras);
+        assert_eq!(parse_cell(&text).unwrap().1, atoms);
+    }
+
+    #[test]
+    fn moved_atoms_are_written_where_they_are() {
+        let (lattice, atoms, fractional, extras) = parse_cell(&read_salt()).unwrap();
+        let mut positions = cell_atoms(&lattice, &atoms, &fractional);
+        positions[0].1[0] += 1.0;
+        let text = format_cell(&lattice, &positions, &true, &extras);
+        let (_, atoms_again, _, _) = parse_cell(&text).unwrap();
+        let positions_again = cell_atoms(&lattice, &atoms_again, &true);
+        assert_close(&positions[0].1, &positions_again[0].1, 1e-5);
+        assert_eq!(atoms_again[1], atoms[1]);
+    }
+
+    #[test]
+    fn labels_and_comments_survive() {
+        let text = "\
+%BLOCK LATTICE_CART
+  4.0 0.0 0.0 ! a
+  0.0 4.0 0.0
+  0.0 0.0 4.0
+%ENDBLOCK LATTICE_CART
+%BLOCK POSITIONS_FRAC
+Fe:1 0.0 0.0 0.0 ! up
+Fe:2 0.5 0.5 0.5
+%ENDBLOCK POSITIONS_FRAC
+%BLOCK SPECIES_POT
+Fe:1 Fe_00.usp
+Fe:2 Fe_00.usp
+%ENDBLOCK SPECIES_POT
+";
+        let (lattice, atoms, fractional, extras) = parse_cell(text).unwrap();
+        assert_eq!(atoms[0].0, "Fe:1");
+        assert_eq!(atoms[1].0, "Fe:2");
+        let written = format_cell(&lattice, &cell_atoms(&lattice, &atoms, &fractional), &true, &extras);
+        assert!(written.contains("Fe:1"));
+        assert!(written.contains("Fe:2 Fe_00.usp"));
+    }
+
+    #[test]
+    fn malformed_lattice_is_an_error() {
+        let text = "%BLOCK LATTICE_CART\n4.0 0.0\n0.0 4.0 0.0\n0.0 0.0 4.0\n%ENDBLOCK LATTICE_CART\n";
+        assert!(parse_cell(text).is_err());
+        let text = "%BLOCK LATTICE_CART\n4.0 0.0 0.0 1.0\n0.0 4.0 0.0\n0.0 0.0 4.0\n%ENDBLOCK LATTICE_CART\n";
+        assert!(parse_cell(text).is_err());
+    }
+}
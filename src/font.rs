@@ -0,0 +1,142 @@
+// ============================================================
+// Font
+// ============================================================
+/// An 8x16 bitmap font for on-screen text: printable ASCII, then ° and Å.
+/// Each glyph is 16 rows, top first, with the most significant bit on the left.
+///
+/// The glyphs are from the X11 "Sony Fixed" 8x16 font,
+/// Copyright (c) 1987, 1988 Sony Corp.
+pub const GLYPH_WIDTH : u32 = 8;
+pub const GLYPH_HEIGHT : u32 = 16;
+
+const GLYPHS : [[u8;16];97] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x00, 0x38, 0x38, 0x38, 0x38, 0x38, 0x38, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x10, 0x38, 0x10], // '!'
+    [0x6C, 0x6C, 0x24, 0x24, 0x48, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x00, 0x12, 0x12, 0x12, 0x7F, 0x24, 0x24, 0x24, 0x24, 0x24, 0xFE, 0x48, 0x48, 0x48, 0x48, 0x00], // '#'
+    [0x10, 0x38, 0x54, 0x92, 0x96, 0x90, 0x50, 0x38, 0x14, 0x12, 0xD2, 0x92, 0x94, 0x78, 0x10, 0x10], // '$'
+    [0x02, 0x62, 0x94, 0x94, 0x94, 0x98, 0x68, 0x10, 0x10, 0x2C, 0x32, 0x52, 0x52, 0x52, 0x8C, 0x80], // '%'
+    [0x00, 0x30, 0x48, 0x48, 0x48, 0x50, 0x20, 0x2E, 0x54, 0x54, 0x94, 0x88, 0x8C, 0x72, 0x00, 0x00], // '&'
+    [0xE0, 0xE0, 0x20, 0x20, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '\''
+    [0x02, 0x04, 0x08, 0x08, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x08, 0x08, 0x04, 0x02], // '('
+    [0x80, 0x40, 0x20, 0x20, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x20, 0x20, 0x40, 0x80], // ')'
+    [0x00, 0x00, 0x00, 0x10, 0x38, 0x92, 0xD6, 0x38, 0xD6, 0x92, 0x38, 0x10, 0x00, 0x00, 0x00, 0x00], // '*'
+    [0x00, 0x00, 0x00, 0x10, 0x10, 0x10, 0x10, 0xFE, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0, 0xE0, 0x20, 0x20, 0xC0], // ','
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFE, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0xE0, 0xE0, 0x40, 0x00], // '.'
+    [0x02, 0x02, 0x04, 0x04, 0x08, 0x08, 0x08, 0x10, 0x10, 0x10, 0x20, 0x20, 0x40, 0x40, 0x80, 0x80], // '/'
+    [0x00, 0x18, 0x24, 0x24, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x24, 0x24, 0x18, 0x00], // '0'
+    [0x00, 0x10, 0x70, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00], // '1'
+    [0x00, 0x18, 0x24, 0x42, 0x62, 0x02, 0x04, 0x08, 0x08, 0x10, 0x20, 0x22, 0x42, 0x7E, 0x00, 0x00], // '2'
+    [0x00, 0x38, 0x44, 0x82, 0x82, 0x02, 0x04, 0x38, 0x04, 0x02, 0x82, 0x82, 0x44, 0x38, 0x00, 0x00], // '3'
+    [0x00, 0x08, 0x18, 0x28, 0x28, 0x48, 0x48, 0x88, 0x88, 0xFE, 0x08, 0x08, 0x08, 0x3C, 0x00, 0x00], // '4'
+    [0x00, 0xFC, 0x80, 0x80, 0x80, 0xB8, 0xC4, 0x82, 0x02, 0x02, 0xC2, 0x82, 0x44, 0x38, 0x00, 0x00], // '5'
+    [0x00, 0x3C, 0x42, 0x46, 0x80, 0x80, 0xB8, 0xC4, 0x82, 0x82, 0x82, 0x82, 0x44, 0x38, 0x00, 0x00], // '6'
+    [0x00, 0xFE, 0x82, 0x82, 0x04, 0x04, 0x04, 0x08, 0x08, 0x08, 0x08, 0x10, 0x10, 0x10, 0x10, 0x00], // '7'
+    [0x00, 0x38, 0x44, 0x82, 0x82, 0x82, 0x44, 0x38, 0x44, 0x82, 0x82, 0x82, 0x44, 0x38, 0x00, 0x00], // '8'
+    [0x00, 0x38, 0x44, 0x82, 0x82, 0x82, 0x82, 0x46, 0x3A, 0x02, 0x02, 0x82, 0x44, 0x38, 0x00, 0x00], // '9'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x38, 0x38, 0x00, 0x00, 0x00, 0x00, 0x00, 0x38, 0x38, 0x00, 0x00], // ':'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x38, 0x38, 0x00, 0x00, 0x00, 0x00, 0x38, 0x38, 0x18, 0x10, 0x30], // ';'
+    [0x02, 0x04, 0x04, 0x08, 0x08, 0x10, 0x10, 0x20, 0x20, 0x10, 0x10, 0x08, 0x08, 0x04, 0x04, 0x02], // '<'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0xFE, 0x00, 0x00, 0x00, 0xFE, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '='
+    [0x80, 0x40, 0x40, 0x20, 0x20, 0x10, 0x10, 0x08, 0x08, 0x10, 0x10, 0x20, 0x20, 0x40, 0x40, 0x80], // '>'
+    [0x00, 0x38, 0x44, 0x82, 0xC2, 0x02, 0x04, 0x04, 0x08, 0x10, 0x10, 0x00, 0x00, 0x10, 0x38, 0x10], // '?'
+    [0x00, 0x3C, 0x42, 0x82, 0x9A, 0xA6, 0xA2, 0xA2, 0xA2, 0xA6, 0x9A, 0x80, 0x42, 0x3C, 0x00, 0x00], // '@'
+    [0x00, 0x10, 0x28, 0x28, 0x28, 0x44, 0x44, 0x44, 0x44, 0x7C, 0x82, 0x82, 0x82, 0xC6, 0x00, 0x00], // 'A'
+    [0x00, 0xF8, 0x44, 0x42, 0x42, 0x42, 0x44, 0x78, 0x44, 0x42, 0x42, 0x42, 0x42, 0xFC, 0x00, 0x00], // 'B'
+    [0x00, 0x3A, 0x46, 0x42, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x82, 0x42, 0x42, 0x3C, 0x00, 0x00], // 'C'
+    [0x00, 0xF8, 0x44, 0x44, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x44, 0x44, 0xF8, 0x00, 0x00], // 'D'
+    [0x00, 0xFE, 0x42, 0x42, 0x40, 0x48, 0x48, 0x78, 0x48, 0x48, 0x42, 0x42, 0x42, 0xFE, 0x00, 0x00], // 'E'
+    [0x00, 0xFE, 0x42, 0x42, 0x40, 0x48, 0x48, 0x78, 0x48, 0x48, 0x40, 0x40, 0x40, 0xF0, 0x00, 0x00], // 'F'
+    [0x00, 0x1A, 0x26, 0x42, 0x40, 0x80, 0x80, 0x8F, 0x82, 0x82, 0x82, 0x42, 0x66, 0x1A, 0x00, 0x00], // 'G'
+    [0x00, 0xE7, 0x42, 0x42, 0x42, 0x42, 0x7E, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0xE7, 0x00, 0x00], // 'H'
+    [0x00, 0xFE, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0xFE, 0x00, 0x00], // 'I'
+    [0x00, 0x1F, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x82, 0x82, 0x82, 0x44, 0x38, 0x00, 0x00], // 'J'
+    [0x00, 0xE6, 0x44, 0x44, 0x48, 0x48, 0x70, 0x50, 0x48, 0x48, 0x44, 0x44, 0x42, 0xE3, 0x00, 0x00], // 'K'
+    [0x00, 0xF0, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x42, 0x42, 0x42, 0xFE, 0x00, 0x00], // 'L'
+    [0x00, 0x82, 0xC6, 0xAA, 0xAA, 0xAA, 0x92, 0x92, 0x92, 0x82, 0x82, 0x82, 0x82, 0xC6, 0x00, 0x00], // 'M'
+    [0x00, 0x87, 0xC2, 0xA2, 0xA2, 0xA2, 0x92, 0x92, 0x92, 0x8A, 0x8A, 0x8A, 0x86, 0xC2, 0x00, 0x00], // 'N'
+    [0x00, 0x38, 0x44, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x44, 0x38, 0x00, 0x00], // 'O'
+    [0x00, 0xF8, 0x44, 0x42, 0x42, 0x42, 0x42, 0x44, 0x78, 0x40, 0x40, 0x40, 0x40, 0xF0, 0x00, 0x00], // 'P'
+    [0x00, 0x38, 0x44, 0x44, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0xBA, 0x44, 0x44, 0x38, 0x08, 0x06], // 'Q'
+    [0x00, 0xF8, 0x44, 0x42, 0x42, 0x42, 0x44, 0x78, 0x48, 0x44, 0x44, 0x44, 0x42, 0xE3, 0x00, 0x00], // 'R'
+    [0x00, 0x34, 0x4C, 0x84, 0x80, 0x80, 0x60, 0x18, 0x04, 0x82, 0x82, 0x82, 0xC4, 0xB8, 0x00, 0x00], // 'S'
+    [0x00, 0xFE, 0x92, 0x92, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00], // 'T'
+    [0x00, 0xE7, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x3C, 0x00, 0x00], // 'U'
+    [0x00, 0xC6, 0x82, 0x82, 0x82, 0x82, 0x44, 0x44, 0x44, 0x44, 0x28, 0x28, 0x10, 0x10, 0x00, 0x00], // 'V'
+    [0x00, 0xC6, 0x82, 0x82, 0x82, 0x92, 0x92, 0x92, 0xAA, 0xAA, 0xAA, 0x44, 0x44, 0x44, 0x00, 0x00], // 'W'
+    [0x00, 0xEE, 0x44, 0x44, 0x28, 0x28, 0x10, 0x28, 0x28, 0x28, 0x44, 0x44, 0x82, 0xC6, 0x00, 0x00], // 'X'
+    [0x00, 0xC6, 0x82, 0x44, 0x44, 0x44, 0x28, 0x28, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00], // 'Y'
+    [0x00, 0xFE, 0x84, 0x88, 0x08, 0x10, 0x10, 0x10, 0x20, 0x20, 0x42, 0x42, 0x82, 0xFE, 0x00, 0x00], // 'Z'
+    [0x1E, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1E], // '['
+    [0x00, 0x80, 0x80, 0x40, 0x40, 0x20, 0x20, 0x10, 0x10, 0x08, 0x08, 0x04, 0x04, 0x02, 0x02, 0x00], // '\\'
+    [0xF0, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0xF0], // ']'
+    [0x10, 0x28, 0x44, 0x82, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFE], // '_'
+    [0x30, 0x30, 0x20, 0x20, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x42, 0x02, 0x3E, 0x42, 0x82, 0x82, 0x86, 0x7B, 0x00, 0x00], // 'a'
+    [0x00, 0xC0, 0x40, 0x40, 0x40, 0x78, 0x44, 0x42, 0x42, 0x42, 0x42, 0x42, 0x44, 0x78, 0x00, 0x00], // 'b'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x3A, 0x46, 0x82, 0x80, 0x80, 0x80, 0x82, 0x42, 0x3C, 0x00, 0x00], // 'c'
+    [0x00, 0x06, 0x04, 0x04, 0x04, 0x3C, 0x44, 0x84, 0x84, 0x84, 0x84, 0x84, 0x44, 0x3E, 0x00, 0x00], // 'd'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x38, 0x44, 0x82, 0xFE, 0x80, 0x80, 0x82, 0x42, 0x3C, 0x00, 0x00], // 'e'
+    [0x00, 0x0E, 0x11, 0x10, 0x10, 0xFE, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00], // 'f'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x3B, 0x44, 0x44, 0x44, 0x38, 0x40, 0x78, 0x84, 0x82, 0x82, 0x7C], // 'g'
+    [0x00, 0xC0, 0x40, 0x40, 0x40, 0x5C, 0x62, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0xE7, 0x00, 0x00], // 'h'
+    [0x18, 0x18, 0x00, 0x00, 0x00, 0x78, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0xFF, 0x00, 0x00], // 'i'
+    [0x06, 0x06, 0x00, 0x00, 0x00, 0x3E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x82, 0x82, 0x44, 0x38], // 'j'
+    [0x00, 0xC0, 0x40, 0x40, 0x40, 0x42, 0x44, 0x48, 0x58, 0x64, 0x44, 0x42, 0x42, 0xE3, 0x00, 0x00], // 'k'
+    [0x00, 0x78, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0xFF, 0x00, 0x00], // 'l'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x6C, 0x92, 0x92, 0x92, 0x92, 0x92, 0x92, 0x92, 0xDB, 0x00, 0x00], // 'm'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0xDC, 0x62, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0xE7, 0x00, 0x00], // 'n'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x38, 0x44, 0x82, 0x82, 0x82, 0x82, 0x82, 0x44, 0x38, 0x00, 0x00], // 'o'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x44, 0x42, 0x42, 0x42, 0x42, 0x44, 0x78, 0x40, 0x40, 0xF0], // 'p'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x3E, 0x44, 0x84, 0x84, 0x84, 0x84, 0x44, 0x3C, 0x04, 0x04, 0x1E], // 'q'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0xEC, 0x32, 0x22, 0x20, 0x20, 0x20, 0x20, 0x20, 0xFC, 0x00, 0x00], // 'r'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x3A, 0x46, 0x42, 0x40, 0x3C, 0x02, 0x82, 0xC2, 0xBC, 0x00, 0x00], // 's'
+    [0x00, 0x00, 0x20, 0x20, 0x20, 0xFC, 0x20, 0x20, 0x20, 0x20, 0x20, 0x22, 0x22, 0x1C, 0x00, 0x00], // 't'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0xC6, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x46, 0x39, 0x00, 0x00], // 'u'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0xC6, 0x82, 0x82, 0x44, 0x44, 0x44, 0x28, 0x28, 0x10, 0x00, 0x00], // 'v'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x92, 0x92, 0x92, 0x92, 0xAA, 0xAA, 0x44, 0x44, 0x44, 0x00, 0x00], // 'w'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0xEE, 0x44, 0x28, 0x28, 0x10, 0x28, 0x28, 0x44, 0xEE, 0x00, 0x00], // 'x'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0xE7, 0x42, 0x22, 0x24, 0x14, 0x08, 0x08, 0x10, 0x90, 0xA0, 0x40], // 'y'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x7E, 0x44, 0x08, 0x08, 0x10, 0x10, 0x22, 0x42, 0xFE, 0x00, 0x00], // 'z'
+    [0x06, 0x08, 0x08, 0x08, 0x08, 0x08, 0x10, 0x20, 0x10, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x06], // '{'
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10], // '|'
+    [0xC0, 0x20, 0x20, 0x20, 0x20, 0x20, 0x10, 0x08, 0x10, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0xC0], // '}'
+    [0x60, 0x92, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+    [0x00, 0x38, 0x44, 0x44, 0x44, 0x38, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '°'
+    [0x38, 0x44, 0x38, 0x10, 0x28, 0x28, 0x44, 0x44, 0x44, 0x7C, 0x82, 0x82, 0x82, 0xC6, 0x00, 0x00], // 'Å'
+];
+
+pub fn glyph_count() -> usize {GLYPHS.len()}
+
+/// The position of a character in the font, with '?' standing in for anything missing.
+pub fn glyph_index(in_character : &char) -> usize {
+    match *in_character {
+        c @ ' '..='~' => c as usize-' ' as usize,
+        '°' => 95,
+        'Å' => 96,
+        _ => '?' as usize-' ' as usize,
+    }
+}
+
+/// The glyphs side by side in one row, as RGBA with the top row first.
+/// Set pixels are opaque white and the rest transparent black.
+pub fn atlas() -> (Vec<u8>, u32, u32) {
+    let width = GLYPH_WIDTH*GLYPHS.len() as u32;
+    let mut pixels = vec![0u8; (width*GLYPH_HEIGHT*4) as usize];
+    for (i, glyph) in GLYPHS.iter().enumerate() {
+        for (y, row) in glyph.iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                if row & (0x80 >> x) != 0 {
+                    let start = ((y as u32*width+i as u32*GLYPH_WIDTH+x)*4) as usize;
+                    for value in &mut pixels[start..start+4] {
+                        *value = 255;
+                    }
+                }
+            }
+        }
+    }
+    (pixels, width, GLYPH_HEIGHT)
+}
@@ -0,0 +1,113 @@
+extern crate glium;
+
+use glium::Surface;
+
+use camera::Camera;
+use lattice::Lattice;
+use text::{TextBatch, TextRenderer};
+use vector::normalised;
+use vertex::Vertex;
+
+// ============================================================
+// Axis gizmo
+// ============================================================
+/// A small triad of arrows in the bottom-right corner showing which way
+/// the lattice vectors a, b, c (or x, y, z without a cell) point.
+/// It turns with the camera but ignores zooming and panning.
+pub struct AxisGizmo<'a> {
+    _program : &'a glium::Program,
+    /// The length of an arrow pointing across the screen, in pixels.
+    _length  : f32,
+    _margin  : f32,
+}
+
+impl<'a> AxisGizmo<'a> {
+    pub fn new(in_program : &'a glium::Program) -> AxisGizmo<'a> {
+        AxisGizmo {
+            _program : in_program,
+            _length  : 40.0,
+            _margin  : 24.0,
+        }
+    }
+
+    pub fn draw<S : Surface>(
+        &self,
+        in_display       : &glium::backend::glutin_backend::GlutinFacade,
+        in_target        : &mut S,
+        in_camera        : &Camera,
+        in_lattice       : Option<&Lattice>,
+        in_text_renderer : &TextRenderer,
+    ) {
+        let (axes, names) = match in_lattice {
+            Some(lattice) => (*lattice.vectors(), ["a", "b", "c"]),
+            None => ([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]], ["x", "y", "z"]),
+        };
+        // red, green and blue, as is usual for axes
+        let colours = [[0.8, 0.1, 0.1f32], [0.1, 0.6, 0.1], [0.1, 0.2, 0.8]];
+
+        let screen_size = in_camera.screen_size();
+        let w = screen_size[0].max(1) as f32;
+        let h = screen_size[1].max(1) as f32;
+        let origin = [w-self._margin-self._length, h-self._margin-self._length];
+        let to_ndc = |pixel : [f32;2]| Vertex::new([2.0*pixel[0]/w-1.0, 1.0-2.0*pixel[1]/h, 0.0], [0.0;3]);
+        let identity = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0f32],
+        ];
+        let params = glium::DrawParameters {
+            line_width : Some(2.0),
+            .. Default::default()
+        };
+
+        let mut labels = Vec::new();
+        for k in 0..3 {
+            // the arrow in view space, where x is right and y is up
+            let direction = in_camera.quaternion().rotate_vector(&normalised(&axes[k]));
+            let tip = [origin[0]+self._length*direction[0], origin[1]-self._length*direction[1]];
+
+            // the shaft, and a head made of two short strokes back from the tip
+            let mut vertices = vec![to_ndc(origin), to_ndc(tip)];
+            let screen_length = (direction[0]*direction[0]+direction[1]*direction[1]).sqrt();
+            if screen_length > 0.1 {
+                let along = [direction[0]/screen_length, -direction[1]/screen_length];
+                let across = [-along[1], along[0]];
+                let head = 6.0;
+                for side in [-1.0, 1.0f32].iter() {
+                    vertices.push(to_ndc(tip));
+                    vertices.push(to_ndc([
+                        tip[0]-head*along[0]+side*0.5*head*across[0],
+                        tip[1]-head*along[1]+side*0.5*head*across[1],
+                    ]));
+                }
+            }
+
+            let uniforms = uniform!{
+                mv_matrix     : identity,
+                mvp_matrix    : identity,
+                colour        : colours[k],
+                transparency  : 0.0f32,
+                slice_plane_0 : [0.0f32;4],
+                slice_plane_1 : [0.0f32;4],
+            };
+            in_target.draw(
+                &glium::VertexBuffer::new(in_display, &vertices).unwrap(),
+                &glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
+                self._program,
+                &uniforms,
+                &params,
+            ).unwrap();
+
+            // the label goes just beyond the tip
+            let label = [origin[0]+1.3*self._length*direction[0], origin[1]-1.3*self._length*direction[1]];
+            labels.push((names[k], label, colours[k]));
+        }
+
+        for &(name, pixel, colour) in &labels {
+            let mut batch = TextBatch::new(screen_size);
+            batch.add_centred(name, &pixel, &0.0);
+            in_text_renderer.draw(in_display, in_target, &batch, &colour, &false);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::Instant;
+
+use text::{self, TextBatch};
+
+// ============================================================
+// Label mode
+// ============================================================
+/// What, if anything, to write next to each atom.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LabelMode {
+    Off,
+    Element,
+    Index,
+    /// Labels set with the label command.
+    Custom,
+}
+
+impl LabelMode {
+    pub fn next(&self) -> LabelMode {
+        match *self {
+            LabelMode::Off => LabelMode::Element,
+            LabelMode::Element => LabelMode::Index,
+            LabelMode::Index => LabelMode::Custom,
+            LabelMode::Custom => LabelMode::Off,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LabelMode::Off => "off",
+            LabelMode::Element => "elements",
+            LabelMode::Index => "indices",
+            LabelMode::Custom => "custom",
+        }
+    }
+}
+
+// ============================================================
+// Heads-up display
+// ============================================================
+/// The text drawn over the scene: what is being viewed and how fast,
+/// the latest status message and the command prompt.
+pub struct Hud {
+    _title          : String,
+    _formula        : String,
+    _atom_count     : usize,
+    _hidden_count   : usize,
+    /// The trajectory frame being shown and how many there are, counting from 1.
+    _trajectory     : (usize, usize),
+    /// The total energy of the frame, in eV.
+    _energy         : Option<f32>,
+    /// Frames drawn since _fps_start, and the rate over the last second.
+    _frames         : u32,
+    _fps_start      : Instant,
+    _fps            : f32,
+    _status         : Option<(String, Instant)>,
+    _status_seconds : f32,
+}
+
+impl Hud {
+    pub fn new(in_title : &str, in_formula : &str, in_atom_count : &usize) -> Hud {
+        Hud {
+            _title          : in_title.to_string(),
+            _formula        : in_formula.to_string(),
+            _atom_count     : *in_atom_count,
+            _hidden_count   : 0,
+            _trajectory     : (1, 1),
+            _energy         : None,
+            _frames         : 0,
+            _fps_start      : Instant::now(),
+            _fps            : 0.0,
+            _status         : None,
+            _status_seconds : 3.0,
+        }
+    }
+
+    /// Updates what is being viewed, e.g. after atoms are deleted.
+    pub fn set_atoms(&mut self, in_formula : &str, in_atom_count : &usize) {
+        self._formula = in_formula.to_string();
+        self._atom_count = *in_atom_count;
+    }
+
+    pub fn set_hidden_count(&mut self, in_hidden_count : &usize) {self._hidden_count = *in_hidden_count}
+
+    /// Shows which trajectory frame is on screen, from 0, unless there is only one.
+    pub fn set_frame(&mut self, in_frame : &usize, in_frame_count : &usize) {
+        self._trajectory = (in_frame+1, *in_frame_count);
+    }
+
+    pub fn set_energy(&mut self, in_energy : &Option<f32>) {self._energy = *in_energy}
+
+    /// Shows a message for a few seconds.
+    pub fn set_status(&mut self, in_message : &str) {
+        self._status = Some((in_message.to_string(), Instant::now()));
+    }
+
+    /// Counts frames for the frame rate. Call once a frame.
+    pub fn tick(&mut self) {
+        self._frames += 1;
+        let seconds = self._fps_start.elapsed().as_secs_f32();
+        if seconds >= 1.0 {
+            self._fps = self._frames as f32/seconds;
+            self._frames = 0;
+            self._fps_start = Instant::now();
+        }
+        let expired = match self._status {
+            Some((_, ref start)) => start.elapsed().as_secs_f32() > self._status_seconds,
+            None => false,
+        };
+        if expired {
+            self._status = None;
+        }
+    }
+
+    /// Lays out the display: information in the top-left corner,
+    /// with the status message and any command prompt along the bottom.
+    pub fn layout(&self, in_batch : &mut TextBatch, in_screen_size : &[u32;2], in_prompt : Option<&str>) {
+        let margin = 8.0;
+        let info = format!(
+            "{}\n{}\n{} atom{}{}\n{}{}{:.0} fps",
+            self._title,
+            self._formula,
+            self._atom_count,
+            if self._atom_count == 1 {""} else {"s"},
+            if self._hidden_count > 0 {format!(" ({} hidden)", self._hidden_count)} else {String::new()},
+            if self._trajectory.1 > 1 {
+                format!("frame {}/{}\n", self._trajectory.0, self._trajectory.1)
+            } else {
+                String::new()
+            },
+            match self._energy {
+                Some(energy) => format!("E = {:.4} eV\n", energy),
+                None => String::new(),
+            },
+            self._fps,
+        );
+        in_batch.add(&info, &[margin, margin], &0.0);
+
+        let mut bottom = in_screen_size[1] as f32-margin;
+        if let Some(prompt) = in_prompt {
+            bottom -= text::line_height();
+            in_batch.add(&format!("> {}_", prompt), &[margin, bottom], &0.0);
+        }
+        if let Some((ref message, _)) = self._status {
+            bottom -= text::text_size(message)[1];
+            in_batch.add(message, &[margin, bottom], &0.0);
+        }
+    }
+}
+
+/// Lays out the key bindings in as many columns as it takes to fit the window,
+/// starting below the information in the top-left corner.
+pub fn layout_help(in_batch : &mut TextBatch, in_screen_size : &[u32;2], in_lines : &Vec<String>) {
+    let margin = 8.0;
+    let top = margin+7.0*text::line_height();
+    let rows = (((in_screen_size[1] as f32-top-margin)/text::line_height()) as usize).max(1);
+    let mut left = margin;
+    for column in in_lines.chunks(rows) {
+        let column = column.join("\n");
+        in_batch.add(&column, &[left, top], &0.0);
+        left += text::text_size(&column)[0]+3.0*margin;
+    }
+}
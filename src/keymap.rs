@@ -0,0 +1,416 @@
+//! Key bindings: which key does what.
+//!
+//! The defaults can be overridden in a keys file, one action per line:
+//!
+//! ```text
+//! # comments start with a hash
+//! zoom_in = Up, Equals
+//! orbit_left = Shift+Left
+//! save_view_1 = Ctrl+1
+//! toggle_fxaa = none
+//! ```
+//!
+//! Listing an action replaces its default keys, and a key bound to one
+//! action is taken away from any other.
+
+extern crate glium;
+
+use glium::glutin::VirtualKeyCode;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+// ============================================================
+// Actions
+// ============================================================
+/// Everything that can be bound to a key.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    OpenPrompt,
+    Quit,
+    ToggleHelp,
+    ToggleFxaa,
+    ZoomIn,
+    ZoomOut,
+    SpinClockwise,
+    SpinAnticlockwise,
+    AzimuthUp,
+    AzimuthDown,
+    OrbitLeft,
+    OrbitRight,
+    ResetCamera,
+    FitToView,
+    ToggleProjection,
+    ToggleInertia,
+    ViewAlongA,
+    ViewAlongB,
+    ViewAlongC,
+    /// Go to the view bookmarked under a digit.
+    RecallView(u8),
+    /// Bookmark the current view under a digit.
+    SaveView(u8),
+    CycleLabels,
+    CycleRepresentation,
+    /// Cycle the representation of just the selected atoms.
+    CycleSelectionRepresentation,
+    CycleColours,
+    /// Show or hide a species, counting from 1 in the order they appear in the file.
+    ToggleSpecies(u8),
+    /// Move the latest slicing plane back along its normal, or forward.
+    SweepSliceBack,
+    SweepSliceForward,
+    /// Trajectory playback.
+    TogglePlayback,
+    NextFrame,
+    PreviousFrame,
+    FasterPlayback,
+    SlowerPlayback,
+    ToggleLooping,
+    KeepMeasurement,
+    ClearMeasurements,
+    ExportMeasurements,
+    TogglePolyhedra,
+    Export,
+}
+
+/// Every action, in the order they are listed in the help.
+pub fn all_actions() -> Vec<Action> {
+    let mut actions = vec![
+        Action::OpenPrompt,
+        Action::Quit,
+        Action::ToggleHelp,
+        Action::ToggleFxaa,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::SpinClockwise,
+        Action::SpinAnticlockwise,
+        Action::AzimuthUp,
+        Action::AzimuthDown,
+        Action::OrbitLeft,
+        Action::OrbitRight,
+        Action::ResetCamera,
+        Action::FitToView,
+        Action::ToggleProjection,
+        Action::ToggleInertia,
+        Action::ViewAlongA,
+        Action::ViewAlongB,
+        Action::ViewAlongC,
+    ];
+    for digit in 0..10 {actions.push(Action::RecallView(digit));}
+    for digit in 0..10 {actions.push(Action::SaveView(digit));}
+    actions.extend_from_slice(&[
+        Action::CycleLabels,
+        Action::CycleRepresentation,
+        Action::CycleSelectionRepresentation,
+        Action::CycleColours,
+    ]);
+    for number in 1..11 {actions.push(Action::ToggleSpecies(number));}
+    actions.extend_from_slice(&[
+        Action::SweepSliceBack,
+        Action::SweepSliceForward,
+        Action::TogglePlayback,
+        Action::NextFrame,
+        Action::PreviousFrame,
+        Action::FasterPlayback,
+        Action::SlowerPlayback,
+        Action::ToggleLooping,
+        Action::KeepMeasurement,
+        Action::ClearMeasurements,
+        Action::ExportMeasurements,
+        Action::TogglePolyhedra,
+        Action::Export,
+    ]);
+    actions
+}
+
+impl Action {
+    /// The name used in the keys file, e.g. "zoom_in" or "save_view_3".
+    pub fn name(&self) -> String {
+        match *self {
+            Action::RecallView(digit) => format!("recall_view_{}", digit),
+            Action::SaveView(digit) => format!("save_view_{}", digit),
+            Action::ToggleSpecies(number) => format!("toggle_species_{}", number),
+            _ => {
+                // ZoomIn -> zoom_in
+                let mut name = String::new();
+                for c in format!("{:?}", self).chars() {
+                    if c.is_uppercase() && !name.is_empty() {
+                        name.push('_');
+                    }
+                    name.extend(c.to_lowercase());
+                }
+                name
+            },
+        }
+    }
+
+    pub fn from_name(in_name : &str) -> Option<Action> {
+        all_actions().into_iter().find(|action| action.name() == in_name)
+    }
+
+    pub fn description(&self) -> String {
+        match *self {
+            Action::OpenPrompt => "open the command prompt".to_string(),
+            Action::Quit => "quit".to_string(),
+            Action::ToggleHelp => "show or hide this help".to_string(),
+            Action::ToggleFxaa => "toggle anti-aliasing".to_string(),
+            Action::ZoomIn => "zoom in".to_string(),
+            Action::ZoomOut => "zoom out".to_string(),
+            Action::SpinClockwise => "spin clockwise".to_string(),
+            Action::SpinAnticlockwise => "spin anticlockwise".to_string(),
+            Action::AzimuthUp => "tilt up".to_string(),
+            Action::AzimuthDown => "tilt down".to_string(),
+            Action::OrbitLeft => "orbit left".to_string(),
+            Action::OrbitRight => "orbit right".to_string(),
+            Action::ResetCamera => "reset the camera".to_string(),
+            Action::FitToView => "fit the structure to the window".to_string(),
+            Action::ToggleProjection => "toggle orthographic projection".to_string(),
+            Action::ToggleInertia => "toggle mouse inertia".to_string(),
+            Action::ViewAlongA => "view along a".to_string(),
+            Action::ViewAlongB => "view along b".to_string(),
+            Action::ViewAlongC => "view along c".to_string(),
+            Action::RecallView(digit) => format!("go to view {}", digit),
+            Action::SaveView(digit) => format!("save view {}", digit),
+            Action::CycleLabels => "cycle atom labels".to_string(),
+            Action::CycleRepresentation => "cycle ball-and-stick, space-filling, licorice, ...".to_string(),
+            Action::CycleSelectionRepresentation => "cycle how the selected atoms are drawn".to_string(),
+            Action::CycleColours => "colour atoms by element, fragment, charge, ...".to_string(),
+            Action::ToggleSpecies(number) => format!("show or hide species {}", number),
+            Action::SweepSliceBack => "move the slice back".to_string(),
+            Action::SweepSliceForward => "move the slice forward".to_string(),
+            Action::TogglePlayback => "play or pause the trajectory".to_string(),
+            Action::NextFrame => "step to the next frame".to_string(),
+            Action::PreviousFrame => "step to the previous frame".to_string(),
+            Action::FasterPlayback => "play faster".to_string(),
+            Action::SlowerPlayback => "play slower".to_string(),
+            Action::ToggleLooping => "toggle looping at the end of the trajectory".to_string(),
+            Action::KeepMeasurement => "keep the measurement".to_string(),
+            Action::ClearMeasurements => "clear kept measurements".to_string(),
+            Action::ExportMeasurements => "write measurements to CSV".to_string(),
+            Action::TogglePolyhedra => "toggle polyhedra in exports".to_string(),
+            Action::Export => "export glTF, OBJ and STL models".to_string(),
+        }
+    }
+}
+
+// ============================================================
+// Key bindings
+// ============================================================
+/// A key, and whether Ctrl and Shift must be held with it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct KeyBinding {
+    pub key     : VirtualKeyCode,
+    pub control : bool,
+    pub shift   : bool,
+}
+
+impl KeyBinding {
+    pub fn new(in_key : VirtualKeyCode) -> KeyBinding {
+        KeyBinding {key : in_key, control : false, shift : false}
+    }
+
+    pub fn with_control(in_key : VirtualKeyCode) -> KeyBinding {
+        KeyBinding {key : in_key, control : true, shift : false}
+    }
+
+    pub fn with_shift(in_key : VirtualKeyCode) -> KeyBinding {
+        KeyBinding {key : in_key, control : false, shift : true}
+    }
+
+    /// Reads e.g. "K", "Ctrl+1" or "Shift+PageUp".
+    pub fn parse(in_text : &str) -> Result<KeyBinding, String> {
+        let parts : Vec<&str> = in_text.split('+').map(|part| part.trim()).collect();
+        let (modifiers, key) = parts.split_at(parts.len()-1);
+        let mut binding = KeyBinding::new(match key_from_name(key[0]) {
+            Some(key) => key,
+            None => return Err(format!("unknown key \"{}\"", key[0])),
+        });
+        for modifier in modifiers {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => binding.control = true,
+                "shift" => binding.shift = true,
+                _ => return Err(format!("unknown modifier \"{}\"", modifier)),
+            }
+        }
+        Ok(binding)
+    }
+
+    /// e.g. "Ctrl+1".
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+        if self.control {name.push_str("Ctrl+");}
+        if self.shift {name.push_str("Shift+");}
+        name.push_str(&key_name(&self.key));
+        name
+    }
+}
+
+/// Keys that can be bound, as they are written in the keys file.
+const KEYS : &'static [VirtualKeyCode] = &[
+    VirtualKeyCode::Key0, VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4, VirtualKeyCode::Key5, VirtualKeyCode::Key6, VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+    VirtualKeyCode::A, VirtualKeyCode::B, VirtualKeyCode::C, VirtualKeyCode::D, VirtualKeyCode::E,
+    VirtualKeyCode::F, VirtualKeyCode::G, VirtualKeyCode::H, VirtualKeyCode::I, VirtualKeyCode::J,
+    VirtualKeyCode::K, VirtualKeyCode::L, VirtualKeyCode::M, VirtualKeyCode::N, VirtualKeyCode::O,
+    VirtualKeyCode::P, VirtualKeyCode::Q, VirtualKeyCode::R, VirtualKeyCode::S, VirtualKeyCode::T,
+    VirtualKeyCode::U, VirtualKeyCode::V, VirtualKeyCode::W, VirtualKeyCode::X, VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::F1, VirtualKeyCode::F2, VirtualKeyCode::F3, VirtualKeyCode::F4,
+    VirtualKeyCode::F5, VirtualKeyCode::F6, VirtualKeyCode::F7, VirtualKeyCode::F8,
+    VirtualKeyCode::F9, VirtualKeyCode::F10, VirtualKeyCode::F11, VirtualKeyCode::F12,
+    VirtualKeyCode::Escape, VirtualKeyCode::Return, VirtualKeyCode::Space, VirtualKeyCode::Back,
+    VirtualKeyCode::Tab, VirtualKeyCode::Insert, VirtualKeyCode::Delete, VirtualKeyCode::Home,
+    VirtualKeyCode::End, VirtualKeyCode::PageUp, VirtualKeyCode::PageDown,
+    VirtualKeyCode::Left, VirtualKeyCode::Right, VirtualKeyCode::Up, VirtualKeyCode::Down,
+    VirtualKeyCode::Minus, VirtualKeyCode::Equals, VirtualKeyCode::LBracket, VirtualKeyCode::RBracket,
+    VirtualKeyCode::Comma, VirtualKeyCode::Period, VirtualKeyCode::Slash, VirtualKeyCode::Backslash,
+    VirtualKeyCode::Semicolon, VirtualKeyCode::Apostrophe, VirtualKeyCode::Grave,
+];
+
+/// The digit keys are written as plain digits, and everything else as glutin names them.
+fn key_name(in_key : &VirtualKeyCode) -> String {
+    format!("{:?}", in_key).replace("Key", "")
+}
+
+fn key_from_name(in_name : &str) -> Option<VirtualKeyCode> {
+    KEYS.iter().cloned().find(|key| key_name(key).to_lowercase() == in_name.to_lowercase())
+}
+
+// ============================================================
+// Keymap
+// ============================================================
+pub struct Keymap {
+    _bindings : Vec<(KeyBinding, Action)>,
+}
+
+impl Keymap {
+    /// The default keys.
+    pub fn new() -> Keymap {
+        let mut bindings = vec![
+            (KeyBinding::new(VirtualKeyCode::Return), Action::OpenPrompt),
+            (KeyBinding::new(VirtualKeyCode::Escape), Action::Quit),
+            (KeyBinding::new(VirtualKeyCode::F1), Action::ToggleHelp),
+            (KeyBinding::new(VirtualKeyCode::Space), Action::ToggleFxaa),
+            (KeyBinding::new(VirtualKeyCode::Up), Action::ZoomIn),
+            (KeyBinding::new(VirtualKeyCode::Down), Action::ZoomOut),
+            (KeyBinding::new(VirtualKeyCode::Right), Action::SpinClockwise),
+            (KeyBinding::new(VirtualKeyCode::Left), Action::SpinAnticlockwise),
+            (KeyBinding::new(VirtualKeyCode::K), Action::AzimuthUp),
+            (KeyBinding::new(VirtualKeyCode::J), Action::AzimuthDown),
+            (KeyBinding::new(VirtualKeyCode::H), Action::OrbitLeft),
+            (KeyBinding::new(VirtualKeyCode::L), Action::OrbitRight),
+            (KeyBinding::new(VirtualKeyCode::R), Action::ResetCamera),
+            (KeyBinding::new(VirtualKeyCode::F), Action::FitToView),
+            (KeyBinding::new(VirtualKeyCode::O), Action::ToggleProjection),
+            (KeyBinding::new(VirtualKeyCode::I), Action::ToggleInertia),
+            (KeyBinding::new(VirtualKeyCode::A), Action::ViewAlongA),
+            (KeyBinding::new(VirtualKeyCode::B), Action::ViewAlongB),
+            (KeyBinding::new(VirtualKeyCode::C), Action::ViewAlongC),
+        ];
+        let digits = [
+            VirtualKeyCode::Key0, VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3,
+            VirtualKeyCode::Key4, VirtualKeyCode::Key5, VirtualKeyCode::Key6, VirtualKeyCode::Key7,
+            VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+        ];
+        for (digit, &key) in digits.iter().enumerate() {
+            bindings.push((KeyBinding::new(key), Action::RecallView(digit as u8)));
+        }
+        for (digit, &key) in digits.iter().enumerate() {
+            bindings.push((KeyBinding::with_control(key), Action::SaveView(digit as u8)));
+        }
+        // Shift+1 is the first species, ..., Shift+0 the tenth
+        for (digit, &key) in digits.iter().enumerate() {
+            let number = if digit == 0 {10} else {digit as u8};
+            bindings.push((KeyBinding::with_shift(key), Action::ToggleSpecies(number)));
+        }
+        bindings.extend_from_slice(&[
+            (KeyBinding::new(VirtualKeyCode::T), Action::CycleLabels),
+            (KeyBinding::new(VirtualKeyCode::V), Action::CycleRepresentation),
+            (KeyBinding::with_shift(VirtualKeyCode::V), Action::CycleSelectionRepresentation),
+            (KeyBinding::new(VirtualKeyCode::G), Action::CycleColours),
+            (KeyBinding::new(VirtualKeyCode::LBracket), Action::SweepSliceBack),
+            (KeyBinding::new(VirtualKeyCode::RBracket), Action::SweepSliceForward),
+            (KeyBinding::new(VirtualKeyCode::Slash), Action::TogglePlayback),
+            (KeyBinding::new(VirtualKeyCode::Period), Action::NextFrame),
+            (KeyBinding::new(VirtualKeyCode::Comma), Action::PreviousFrame),
+            (KeyBinding::with_shift(VirtualKeyCode::Period), Action::FasterPlayback),
+            (KeyBinding::with_shift(VirtualKeyCode::Comma), Action::SlowerPlayback),
+            (KeyBinding::new(VirtualKeyCode::Backslash), Action::ToggleLooping),
+            (KeyBinding::new(VirtualKeyCode::M), Action::KeepMeasurement),
+            (KeyBinding::new(VirtualKeyCode::Back), Action::ClearMeasurements),
+            (KeyBinding::new(VirtualKeyCode::X), Action::ExportMeasurements),
+            (KeyBinding::new(VirtualKeyCode::P), Action::TogglePolyhedra),
+            (KeyBinding::new(VirtualKeyCode::E), Action::Export),
+        ]);
+        Keymap {_bindings : bindings}
+    }
+
+    /// The default keymap with the overrides in in_path applied.
+    /// A missing file just means there are no overrides.
+    pub fn load(in_path : &Path) -> Result<Keymap, String> {
+        let mut keymap = Keymap::new();
+        let mut text = String::new();
+        match File::open(in_path) {
+            Ok(mut file) => {
+                if let Err(e) = file.read_to_string(&mut text) {
+                    return Err(format!("{}: {}", in_path.display(), e));
+                }
+            },
+            Err(_) => return Ok(keymap),
+        }
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {continue;}
+            let error = |message : String| format!("{}:{}: {}", in_path.display(), i+1, message);
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next().unwrap().trim();
+            let keys = match parts.next() {
+                Some(keys) => keys.trim(),
+                None => return Err(error("expected action = keys".to_string())),
+            };
+            let action = match Action::from_name(name) {
+                Some(action) => action,
+                None => return Err(error(format!("unknown action \"{}\"", name))),
+            };
+            keymap._bindings.retain(|binding| binding.1 != action);
+            if keys == "none" {continue;}
+            for key in keys.split(',') {
+                let binding = KeyBinding::parse(key).map_err(&error)?;
+                keymap.bind(&binding, &action);
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Binds a key to an action, taking it away from whatever it did before.
+    pub fn bind(&mut self, in_binding : &KeyBinding, in_action : &Action) {
+        self._bindings.retain(|binding| binding.0 != *in_binding);
+        self._bindings.push((*in_binding, *in_action));
+    }
+
+    /// The action for a key pressed with the given modifiers, if any.
+    pub fn action(&self, in_key : &VirtualKeyCode, in_control : &bool, in_shift : &bool) -> Option<Action> {
+        let pressed = KeyBinding {key : *in_key, control : *in_control, shift : *in_shift};
+        self._bindings.iter().find(|binding| binding.0 == pressed).map(|binding| binding.1)
+    }
+
+    /// The keys bound to an action, e.g. "Up, Equals".
+    pub fn keys_for(&self, in_action : &Action) -> String {
+        self._bindings.iter()
+            .filter(|binding| binding.1 == *in_action)
+            .map(|binding| binding.0.name())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// One line per bound action, for the help overlay.
+    pub fn help(&self) -> Vec<String> {
+        all_actions().iter()
+            .map(|action| (self.keys_for(action), action))
+            .filter(|&(ref keys, _)| !keys.is_empty())
+            .map(|(keys, action)| format!("{:<12} {}", keys, action.description()))
+            .collect()
+    }
+}
@@ -0,0 +1,115 @@
+use vector::{cross, dot};
+
+// ============================================================
+// Lattice
+// ============================================================
+/// The unit cell of a crystal: three lattice vectors a, b, c (Cartesian, rows)
+/// and the Cartesian position of the cell's corner.
+#[derive(Copy, Clone, Debug)]
+pub struct Lattice {
+    _vectors : [[f32;3];3],
+    _origin  : [f32;3],
+}
+
+impl Lattice {
+    pub fn new(in_vectors : &[[f32;3];3], in_origin : &[f32;3]) -> Lattice {
+        Lattice {
+            _vectors : in_vectors.to_owned(),
+            _origin  : in_origin.to_owned(),
+        }
+    }
+
+    /// A cell with its centre at the Cartesian origin.
+    pub fn centred(in_vectors : &[[f32;3];3]) -> Lattice {
+        let mut origin = [0.0;3];
+        for vector in in_vectors {
+            for l in 0..3 {
+                origin[l] -= vector[l]/2.0;
+            }
+        }
+        Lattice::new(in_vectors, &origin)
+    }
+
+    pub fn vectors(&self) -> &[[f32;3];3] {&self._vectors}
+    pub fn origin(&self) -> &[f32;3] {&self._origin}
+
+    pub fn volume(&self) -> f32 {
+        dot(&self._vectors[0], &cross(&self._vectors[1], &self._vectors[2]))
+    }
+
+    /// The reciprocal lattice vectors a*, b*, c*, without the factor of 2 pi.
+    pub fn reciprocal(&self) -> [[f32;3];3] {
+        let v = &self._vectors;
+        let volume = self.volume();
+        let mut reciprocal = [cross(&v[1], &v[2]), cross(&v[2], &v[0]), cross(&v[0], &v[1])];
+        for vector in &mut reciprocal {
+            for x in vector.iter_mut() {
+                *x /= volume;
+            }
+        }
+        reciprocal
+    }
+
+    /// The Cartesian vector u a + v b + w c.
+    pub fn direction(&self, in_uvw : &[f32;3]) -> [f32;3] {
+        let mut direction = [0.0;3];
+        for k in 0..3 {
+            for l in 0..3 {
+                direction[l] += in_uvw[k]*self._vectors[k][l];
+            }
+        }
+        direction
+    }
+
+    /// The Cartesian normal to the (hkl) plane, h a* + k b* + l c*.
+    pub fn plane_normal(&self, in_hkl : &[f32;3]) -> [f32;3] {
+        let reciprocal = self.reciprocal();
+        let mut normal = [0.0;3];
+        for k in 0..3 {
+            for l in 0..3 {
+                normal[l] += in_hkl[k]*reciprocal[k][l];
+            }
+        }
+        normal
+    }
+
+    pub fn to_cartesian(&self, in_fractional : &[f32;3]) -> [f32;3] {
+        let direction = self.direction(in_fractional);
+        [
+            direction[0]+self._origin[0],
+            direction[1]+self._origin[1],
+            direction[2]+self._origin[2],
+        ]
+    }
+
+    pub fn to_fractional(&self, in_cartesian : &[f32;3]) -> [f32;3] {
+        let relative = [
+            in_cartesian[0]-self._origin[0],
+            in_cartesian[1]-self._origin[1],
+            in_cartesian[2]-self._origin[2],
+        ];
+        // the fractional coordinates are projections onto the reciprocal vectors
+        let reciprocal = self.reciprocal();
+        [
+            dot(&relative, &reciprocal[0]),
+            dot(&relative, &reciprocal[1]),
+            dot(&relative, &reciprocal[2]),
+        ]
+    }
+
+    /// The shortest periodic image of a displacement between two atoms.
+    /// Wrapping the fractional components into [-1/2, 1/2) is exact for
+    /// orthogonal cells and close enough for the reasonably-shaped ones.
+    pub fn minimum_image(&self, in_displacement : &[f32;3]) -> [f32;3] {
+        let reciprocal = self.reciprocal();
+        let mut fractional = [
+            dot(in_displacement, &reciprocal[0]),
+            dot(in_displacement, &reciprocal[1]),
+            dot(in_displacement, &reciprocal[2]),
+        ];
+        for x in fractional.iter_mut() {
+            *x -= x.round();
+        }
+        self.direction(&fractional)
+    }
+}
@@ -0,0 +1,123 @@
+extern crate glium;
+
+use glium::Surface;
+
+use colour_scheme::Legend;
+use text::{self, TextBatch, TextRenderer};
+
+// ============================================================
+// Gradient vertex
+// ============================================================
+#[derive(Copy, Clone)]
+struct GradientVertex {
+    /// Normalised device coordinates.
+    _position : [f32;2],
+    _colour   : [f32;3],
+}
+
+implement_vertex!(GradientVertex, _position, _colour);
+
+// ============================================================
+// Legend renderer
+// ============================================================
+/// Draws the legend for the colour scheme in the top-right corner:
+/// a colour bar for numbers, or a list of names in their colours for groups.
+pub struct LegendRenderer<'a> {
+    _program        : &'a glium::Program,
+    _bar_width      : f32,
+    _bar_height     : f32,
+    _margin         : f32,
+    /// The most groups listed before the rest are summarised.
+    _max_categories : usize,
+}
+
+impl<'a> LegendRenderer<'a> {
+    pub fn new(in_program : &'a glium::Program) -> LegendRenderer<'a> {
+        LegendRenderer {
+            _program        : in_program,
+            _bar_width      : 16.0,
+            _bar_height     : 160.0,
+            _margin         : 8.0,
+            _max_categories : 16,
+        }
+    }
+
+    pub fn draw<S : Surface>(
+        &self,
+        in_display       : &glium::backend::glutin_backend::GlutinFacade,
+        in_target        : &mut S,
+        in_screen_size   : &[u32;2],
+        in_legend        : &Legend,
+        in_text_renderer : &TextRenderer,
+        in_text_colour   : &[f32;3],
+    ) {
+        let w = in_screen_size[0].max(1) as f32;
+        let h = in_screen_size[1].max(1) as f32;
+        let right = w-self._margin;
+        let top = self._margin;
+        match *in_legend {
+            Legend::None => {},
+            Legend::Bar {ref title, map, min, max} => {
+                let mut labels = TextBatch::new(in_screen_size);
+                labels.add(title, &[right-text::text_size(title)[0], top], &0.0);
+                let bar_top = top+text::line_height()+4.0;
+                let bar_left = right-self._bar_width;
+
+                // the bar is a strip of quads, high values at the top
+                let to_ndc = |x : f32, y : f32| [2.0*x/w-1.0, 1.0-2.0*y/h];
+                let steps = 32;
+                let mut vertices = Vec::new();
+                for step in 0..steps+1 {
+                    let t = step as f32/steps as f32;
+                    let y = bar_top+(1.0-t)*self._bar_height;
+                    let colour = map.colour(&t);
+                    vertices.push(GradientVertex {_position : to_ndc(bar_left, y), _colour : colour});
+                    vertices.push(GradientVertex {_position : to_ndc(right, y), _colour : colour});
+                }
+                in_target.draw(
+                    &glium::VertexBuffer::new(in_display, &vertices).unwrap(),
+                    &glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip),
+                    self._program,
+                    &glium::uniforms::EmptyUniforms,
+                    &Default::default(),
+                ).unwrap();
+
+                for &(value, t) in [(max, 0.0), ((min+max)/2.0, 0.5), (min, 1.0f32)].iter() {
+                    let value = format_value(&value);
+                    let size = text::text_size(&value);
+                    let y = bar_top+t*self._bar_height-size[1]/2.0;
+                    labels.add(&value, &[bar_left-4.0-size[0], y], &0.0);
+                }
+                in_text_renderer.draw(in_display, in_target, &labels, in_text_colour, &false);
+            },
+            Legend::Categories {ref title, ref colours} => {
+                let mut batch = TextBatch::new(in_screen_size);
+                batch.add(title, &[right-text::text_size(title)[0], top], &0.0);
+                in_text_renderer.draw(in_display, in_target, &batch, in_text_colour, &false);
+                let mut y = top+text::line_height();
+                for &(ref name, colour) in colours.iter().take(self._max_categories) {
+                    let mut batch = TextBatch::new(in_screen_size);
+                    batch.add(name, &[right-text::text_size(name)[0], y], &0.0);
+                    in_text_renderer.draw(in_display, in_target, &batch, &colour, &false);
+                    y += text::line_height();
+                }
+                if colours.len() > self._max_categories {
+                    let more = format!("and {} more", colours.len()-self._max_categories);
+                    let mut batch = TextBatch::new(in_screen_size);
+                    batch.add(&more, &[right-text::text_size(&more)[0], y], &0.0);
+                    in_text_renderer.draw(in_display, in_target, &batch, in_text_colour, &false);
+                }
+            },
+        }
+    }
+}
+
+/// Short enough for a legend, whatever the size of the number.
+fn format_value(in_value : &f32) -> String {
+    let magnitude = in_value.abs();
+    if magnitude != 0.0 && (magnitude < 0.01 || magnitude >= 1.0e4) {
+        format!("{:.2e}", in_value)
+    } else {
+        format!("{:.3}", in_value)
+    }
+}
@@ -6,17 +6,53 @@ mod vertex;
 mod matrix;
 mod quaternion;
 mod file_input;
+mod file_output;
 mod model;
 mod program;
 mod species;
 mod atom;
 mod molecule;
 mod camera;
+mod vector;
+mod mesh;
+mod export;
+mod mouse;
+mod lattice;
+mod command;
+mod views;
+mod options;
+mod elements;
+mod measurement;
+mod font;
+mod text;
+mod hud;
+mod gizmo;
+mod keymap;
+mod config;
+mod representation;
+mod colour_scheme;
+mod legend;
+mod selection;
+mod plane;
+mod trajectory;
+mod compression;
 
 use glium::{DisplayBuild, Surface};
 use molecule::Molecule;
-use camera::Camera;
+use camera::{Camera, Easing};
+use command::{Command, PlaneSpec};
+use lattice::Lattice;
+use plane::Plane;
+use measurement::Measurement;
+use hud::{Hud, LabelMode};
+use keymap::{Action, Keymap};
+use representation::Representation;
+use colour_scheme::ColourScheme;
+use text::TextBatch;
+use vertex::Vertex;
 use std::env;
+use std::path::Path;
+use std::process;
 
 // ============================================================
 // Main Program
@@ -27,6 +63,34 @@ fn main() {
     // Read command-line arguments
     // ==============================
     let args : Vec<String> = env::args().collect();
+    let options = match options::Options::parse(&args[1..]) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}\n{}", e, options::USAGE);
+            process::exit(1);
+        },
+    };
+
+    // ==============================
+    // Read settings
+    // ==============================
+    // the user's settings, then any for the current project on top
+    let mut config = config::Config::new();
+    let mut config_paths = Vec::new();
+    if let Some(dir) = options::config_dir() {
+        config_paths.push(dir.join("config.toml"));
+    }
+    config_paths.push(Path::new("oxide.toml").to_owned());
+    for path in &config_paths {
+        match config.load(path) {
+            Ok(true) => println!("Read settings from {}", path.display()),
+            Ok(false) => {},
+            Err(e) => {
+                println!("Could not read settings: {}", e);
+                process::exit(1);
+            },
+        }
+    }
 
     // ==============================
     // Make display
@@ -48,17 +112,26 @@ fn main() {
     // ==============================
     // Make species
     // ==============================
-    let default_species = species::DefaultSpecies::new(&default_models);
+    let mut default_species = species::DefaultSpecies::new(&default_models);
+    for style in &config.elements {
+        default_species.apply_style(style);
+    }
+    let default_species = default_species;
 
     // ==================================
     // Make molecule from file or dummy 
     // ==================================
     let mut molecule = Molecule::new();
-    if args.len() > 1 {
+    if let Some(ref fname) = options.file {
         // Load file and, if successful, make models
-        let ref fname = args[1];
-        println!("Loading {}...", &args[1]);
-        molecule = file_input::read_cell_file(fname, &default_species);
+        println!("Loading {}...", fname);
+        molecule = match file_input::read_file(fname, &default_species, &options.types) {
+            Ok(molecule) => molecule,
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            },
+        };
     } else {
         // Make dummy model if no input 
         molecule.add_atom(default_species.sulphur(), &[ 0.0,  0.0, 0.0]);
@@ -77,32 +150,73 @@ fn main() {
         molecule.add_atom(default_species.carbon(), &[ 0.0,  0.0,  0.5]);
         molecule.add_atom(default_species.carbon(), &[ 0.0,  0.0, -0.5]);
     }
+    molecule.set_representation(&config.representation);
+    // bonds are found again whenever atoms move to another frame or are deleted,
+    // keeping the cutoff from the first frame so a hot frame doesn't change it
+    let bond_cutoff = molecule.default_bond_cutoff();
+    let mut bonds = molecule.bonds(&bond_cutoff);
+
     // ==============================
     // Make camera
     // ==============================
-    // camera focus (the point the camera is pointing at)
-    let camera_focus = [0.0,0.0,0.0];
     // camera position
-    let camera_theta_degrees = 0.0;
-    let camera_phi_degrees = 0.0;
-    let camera_psi_degrees = 0.0;
-    let camera_r = 2.0;
-    // field of view and clipping planes
-    let camera_field_of_view_degrees = 90.0;
-    let camera_near_plane = 1.0;
-    let camera_far_plane = 10.0;
+    let camera_theta_degrees = config.theta_degrees;
+    let camera_phi_degrees = config.phi_degrees;
+    let camera_psi_degrees = config.psi_degrees;
+    // field of view
+    let camera_field_of_view_degrees = config.field_of_view_degrees;
+    // how long camera moves such as resetting take
+    let camera_transition_seconds = config.transition_seconds;
+    // the camera focus, distance and clipping planes are fitted to the structure
+    let (scene_centre, scene_radius) = molecule.bounding_sphere();
 
     let mut camera = Camera::new (
         &display,
-        &camera_focus,
+        &scene_centre,
 	&camera_theta_degrees,
 	&camera_phi_degrees,
 	&camera_psi_degrees,
-	&camera_r,
+	&scene_radius,
         &camera_field_of_view_degrees,
-        &camera_near_plane,
-        &camera_far_plane
     );
+    if config.orthographic {
+        camera.toggle_projection();
+    }
+
+    // ==============================
+    // Load saved views
+    // ==============================
+    let mut view_bookmarks = match views::ViewBookmarks::load(Path::new(&options.views_file)) {
+        Ok(bookmarks) => bookmarks,
+        Err(e) => {
+            println!("Could not read saved views: {}", e);
+            process::exit(1);
+        },
+    };
+    if let Some(ref name) = options.view {
+        match view_bookmarks.get(name) {
+            Some(state) => camera.set_state(state),
+            None => {
+                println!("There is no view called {} in {}", name, options.views_file);
+                process::exit(1);
+            },
+        }
+    }
+    camera.set_transition(&camera_transition_seconds, &Easing::SmoothStep);
+
+    // ==============================
+    // Load key bindings
+    // ==============================
+    let keymap = match options.keys_file {
+        Some(ref path) => match Keymap::load(path) {
+            Ok(keymap) => keymap,
+            Err(e) => {
+                println!("Could not read key bindings: {}", e);
+                process::exit(1);
+            },
+        },
+        None => Keymap::new(),
+    };
 
     // ==============================
     // Run everything
@@ -118,27 +232,212 @@ fn main() {
         .. Default::default()
     };
     
-    let light_position = [2.0,0.0,0.0,1.0f32];
+    let light_position = [
+        config.light_position[0],
+        config.light_position[1],
+        config.light_position[2],
+        1.0f32,
+    ];
+    let background = config.background;
+
+    // exported models are named after the input file
+    let export_stem = match options.file {
+        Some(ref fname) => Path::new(fname).file_stem().unwrap().to_string_lossy().into_owned(),
+        None => "oxide".to_string(),
+    };
+    let mut export_polyhedra = false;
+
+    // atoms clicked on, drawn highlighted; shift-click adds up to four for measuring
+    let mut selected_atoms : Vec<usize> = Vec::new();
+    // measurements kept with M, drawn along with the one being made
+    let mut measurements : Vec<Measurement> = Vec::new();
+
+    // atoms are coloured by element until another scheme is chosen
+    let mut colour_scheme = ColourScheme::Element;
+    let (mut atom_colours, mut legend) = colour_scheme.apply(&molecule);
+    // with a focus radius, only atoms that near the selected ones are shown
+    let mut focus_radius : Option<f32> = None;
+    // up to two planes slicing through atoms and bonds; [ and ] sweep the latest one
+    let mut slice_planes : Vec<Plane> = Vec::new();
+    let max_slice_planes = 2;
+    let slice_step = 0.2f32;
+    let cap_colour = [0.6, 0.7, 0.9f32];
+    let cap_params = glium::DrawParameters {
+        depth: glium::Depth {
+            test: glium::DepthTest::IfLess,
+            write: false,
+            .. Default::default()
+        },
+        blend : glium::Blend::alpha_blending(),
+        .. Default::default()
+    };
+    let measurement_colour = [0.2, 0.2, 0.2f32];
+    let line_params = glium::DrawParameters {
+        line_width : Some(2.0),
+        .. Default::default()
+    };
+    // wireframe bonds and point atoms hide, and are hidden by, everything else
+    let wire_params = glium::DrawParameters {
+        depth: glium::Depth {
+            test: glium::DepthTest::IfLess,
+            write: true,
+            .. Default::default()
+        },
+        line_width : Some(2.0),
+        point_size : Some(6.0),
+        .. Default::default()
+    };
+
+    let mut mouse_controls = mouse::MouseControls::new();
+    let mut command_line = command::CommandLine::new();
+    // number keys recall views, and save them with control held down
+    let mut control_held = false;
+    let mut shift_held = false;
+
+    // ==============================
+    // Text
+    // ==============================
+    let text_renderer = text::TextRenderer::new(&display, default_programs.text());
+    let text_colour = [0.1, 0.1, 0.1f32];
+    let title = match options.file {
+        Some(ref fname) => Path::new(fname).file_name().unwrap().to_string_lossy().into_owned(),
+        None => "Dummy molecule".to_string(),
+    };
+    let mut hud = Hud::new(&title, &molecule.formula(), &molecule.atoms().len());
+    hud.set_frame(molecule.frame(), &molecule.frame_count());
+    hud.set_energy(&molecule.energy());
+    let mut playback = trajectory::Playback::new();
+    // set by keys and commands, and by playback, and acted on at the start of the next frame
+    let mut goto_frame : Option<usize> = None;
+    let mut label_mode = LabelMode::Off;
+    let mut show_help = false;
+    let help_keys = keymap.keys_for(&Action::ToggleHelp);
+    if !help_keys.is_empty() {
+        hud.set_status(&format!("Press {} for help", help_keys));
+    }
+    let axis_gizmo = gizmo::AxisGizmo::new(default_programs.line());
+    let legend_renderer = legend::LegendRenderer::new(default_programs.gradient());
 
-    let mut fxaa_enabled = true;
+    let mut fxaa_enabled = config.fxaa;
     let fxaa = fxaa::FxaaSystem::new(&display);
     loop {
+        camera.tick();
+        mouse_controls.tick(&mut camera);
+        hud.tick();
+
+        if let Some(frame) = playback.tick(molecule.frame(), &molecule.frame_count()) {
+            goto_frame = Some(frame);
+        }
+        if let Some(frame) = goto_frame.take() {
+            if let Err(e) = molecule.set_frame(&frame) {
+                playback.set_playing(&false);
+                hud.set_status(&e);
+            }
+            bonds = molecule.bonds(&bond_cutoff);
+            measurements = measurements.iter()
+                .filter_map(|measurement| Measurement::new(&molecule, measurement.atoms()))
+                .collect();
+            // element colours stay put, but properties such as coordination may change
+            if colour_scheme != ColourScheme::Element {
+                let (colours, key) = colour_scheme.apply(&molecule);
+                atom_colours = colours;
+                legend = key;
+            }
+            hud.set_frame(molecule.frame(), &molecule.frame_count());
+            hud.set_energy(&molecule.energy());
+        }
+
+        let focus = focus_radius.map(|radius| (selected_atoms.clone(), radius));
+        if focus != *molecule.focus() {
+            molecule.set_focus(&focus);
+        }
+        hud.set_hidden_count(&molecule.hidden_count());
+
         let light_position = *camera.view_matrix() * light_position;
+        let mut slices = [[0.0f32;4];2];
+        for (slice, plane) in slices.iter_mut().zip(slice_planes.iter()) {
+            *slice = plane.in_view(camera.view_matrix());
+        }
 
         molecule.rotate_atoms_against_camera(&camera);
 
         let mut target = display.draw();
         fxaa::draw(&fxaa, &mut target, fxaa_enabled, |target| {
-            target.clear_color_and_depth((0.93, 0.91, 0.835, 1.0), 1.0);
-            for atom in molecule.atoms() {
+            target.clear_color_and_depth((background[0], background[1], background[2], 1.0), 1.0);
+            let atoms = molecule.atoms();
+            let atom_colour = |i : &usize| {
+                let mut colour = atom_colours[*i];
+                if selected_atoms.contains(i) {
+                    // lighten the selected atom towards white
+                    for x in colour.iter_mut() {
+                        *x = 0.5*(*x+1.0);
+                    }
+                }
+                colour
+            };
+            // lines and points are gathered up by colour, and drawn together
+            let mut lines : Vec<([f32;3], Vec<Vertex>)> = Vec::new();
+            let mut points : Vec<([f32;3], Vec<Vertex>)> = Vec::new();
+
+            // each half of a bond is drawn as its atom is
+            let mut bonded = vec![false; atoms.len()];
+            for &(i, j) in &bonds {
+                if !molecule.visible(&i) || !molecule.visible(&j) {continue;}
+                bonded[i] = true;
+                bonded[j] = true;
+                let a = atoms[i].position();
+                let b = atoms[j].position();
+                let midpoint = [(a[0]+b[0])/2.0, (a[1]+b[1])/2.0, (a[2]+b[2])/2.0];
+                for &(k, end) in [(i, a), (j, b)].iter() {
+                    let representation = molecule.atom_representation(&k);
+                    if let Some(radius) = representation.bond_radius(molecule.stick_radius()) {
+                        let model_matrix = representation::cylinder_matrix(end, &midpoint, &radius);
+                        let mv_matrix = *camera.view_matrix() * model_matrix;
+                        let mvp_matrix = *camera.vp_matrix() * model_matrix;
+                        let uniforms = uniform!{
+                            mv_matrix      : mv_matrix.contents().to_owned(),
+                            mvp_matrix     : mvp_matrix.contents().to_owned(),
+                            colour         : atom_colour(&k),
+                            light_position : light_position,
+                            slice_plane_0  : slices[0],
+                            slice_plane_1  : slices[1],
+                        };
+                        target.draw(
+                            default_models.cylinder().vertex_buffer(),
+                            default_models.cylinder().index_buffer(),
+                            default_models.cylinder().program(),
+                            &uniforms,
+                            &params,
+                        ).unwrap();
+                    } else if representation == Representation::Wireframe {
+                        add_to_batch(&mut lines, &atom_colour(&k), &[
+                            Vertex::new(*end, [0.0;3]),
+                            Vertex::new(midpoint, [0.0;3]),
+                        ]);
+                    }
+                }
+            }
+
+            for (i, atom) in atoms.iter().enumerate() {
+                if !molecule.visible(&i) {continue;}
+                let representation = molecule.atom_representation(&i);
+                if !representation.has_spheres() {
+                    // wireframe atoms are just where their bonds meet, unless they have none
+                    if representation == Representation::Points || !bonded[i] {
+                        add_to_batch(&mut points, &atom_colour(&i), &[Vertex::new(*atom.position(), [0.0;3])]);
+                    }
+                    continue;
+                }
                 let mv_matrix = *camera.view_matrix() * *atom.model_matrix();
                 let mvp_matrix = *camera.vp_matrix() * *atom.model_matrix();
                 let uniforms = uniform!{
                 mv_matrix      : mv_matrix.contents().to_owned(),
                 mvp_matrix     : mvp_matrix.contents().to_owned(),
-                colour         : atom.species().colour().to_owned(),
+                colour         : atom_colour(&i),
                 light_position : light_position,
-                size           : *atom.species().size(),
+                size           : molecule.atom_radius(&i),
+                slice_plane_0  : slices[0],
+                slice_plane_1  : slices[1],
                 };
                 target.draw(
                     atom.species().mesh().vertex_buffer(),
@@ -148,7 +447,131 @@ fn main() {
                     &params,
                 ).unwrap();
             }
+
+            for &(ref batches, primitive) in [
+                (&lines, glium::index::PrimitiveType::LinesList),
+                (&points, glium::index::PrimitiveType::Points),
+            ].iter() {
+                for &(colour, ref vertices) in batches.iter() {
+                    let uniforms = uniform!{
+                        mv_matrix     : camera.view_matrix().contents().to_owned(),
+                        mvp_matrix    : camera.vp_matrix().contents().to_owned(),
+                        colour        : colour,
+                        transparency  : 0.0f32,
+                        slice_plane_0 : slices[0],
+                        slice_plane_1 : slices[1],
+                    };
+                    target.draw(
+                        &glium::VertexBuffer::new(&display, vertices).unwrap(),
+                        &glium::index::NoIndices(primitive),
+                        default_programs.line(),
+                        &uniforms,
+                        &wire_params,
+                    ).unwrap();
+                }
+            }
+
+            // each slice is capped by a translucent sheet, itself cut only by the other slice
+            if !slice_planes.is_empty() {
+                let (scene_centre, scene_radius) = molecule.bounding_sphere();
+                for (k, plane) in slice_planes.iter().enumerate() {
+                    let mut other = [0.0f32;4];
+                    if slice_planes.len() > 1 {other = slices[1-k];}
+                    let uniforms = uniform!{
+                        mv_matrix     : camera.view_matrix().contents().to_owned(),
+                        mvp_matrix    : camera.vp_matrix().contents().to_owned(),
+                        colour        : cap_colour,
+                        transparency  : 0.7f32,
+                        slice_plane_0 : other,
+                        slice_plane_1 : [0.0f32;4],
+                    };
+                    target.draw(
+                        &glium::VertexBuffer::new(&display, &cap_vertices(plane, &scene_centre, &scene_radius)).unwrap(),
+                        &glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip),
+                        default_programs.line(),
+                        &uniforms,
+                        &cap_params,
+                    ).unwrap();
+                }
+            }
+
+            // measurements are drawn dashed, on top of the atoms
+            let mut dashes : Vec<Vertex> = Vec::new();
+            let current = Measurement::new(&molecule, &selected_atoms);
+            for measurement in measurements.iter().chain(current.iter()) {
+                for point in measurement.dashes() {
+                    dashes.push(Vertex::new(point, [0.0;3]));
+                }
+            }
+            if !dashes.is_empty() {
+                let uniforms = uniform!{
+                    mv_matrix     : camera.view_matrix().contents().to_owned(),
+                    mvp_matrix    : camera.vp_matrix().contents().to_owned(),
+                    colour        : measurement_colour,
+                    transparency  : 0.0f32,
+                    slice_plane_0 : [0.0f32;4],
+                    slice_plane_1 : [0.0f32;4],
+                };
+                target.draw(
+                    &glium::VertexBuffer::new(&display, &dashes).unwrap(),
+                    &glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
+                    default_programs.line(),
+                    &uniforms,
+                    &line_params,
+                ).unwrap();
+            }
+
+            // atom labels sit just in front of their atom, so nearer atoms hide them
+            let mut labels = TextBatch::new(camera.screen_size());
+            let eye = camera.position();
+            for (i, atom) in molecule.atoms().iter().enumerate() {
+                let eye = match eye {
+                    Some(eye) => eye,
+                    None => break,
+                };
+                if !molecule.visible(&i) {continue;}
+                let label = match label_mode {
+                    LabelMode::Off => continue,
+                    LabelMode::Element => atom.species().name().to_string(),
+                    LabelMode::Index => format!("{}", i+1),
+                    LabelMode::Custom => match atom.label() {
+                        Some(label) => label.to_string(),
+                        None => continue,
+                    },
+                };
+                let towards_eye = vector::normalised(&vector::sub(&eye, atom.position()));
+                let front = vector::scale(&towards_eye, &molecule.atom_radius(&i));
+                let point = [
+                    atom.position()[0]+front[0],
+                    atom.position()[1]+front[1],
+                    atom.position()[2]+front[2],
+                ];
+                if let Some((pixel, depth)) = camera.project(&point) {
+                    labels.add_centred(&label, &pixel, &depth);
+                }
+            }
+            text_renderer.draw(&display, target, &labels, &text_colour, &true);
+
+            // measurement values are always visible
+            let mut values = TextBatch::new(camera.screen_size());
+            for measurement in measurements.iter().chain(current.iter()) {
+                if let Some((pixel, _)) = camera.project(&measurement.label_position()) {
+                    let value = format!("{:.2} {}", measurement.value(), measurement.unit());
+                    values.add(&value, &[pixel[0]+4.0, pixel[1]+4.0], &0.0);
+                }
+            }
+            text_renderer.draw(&display, target, &values, &measurement_colour, &false);
         });
+
+        // the heads-up display goes on last, so it isn't blurred by FXAA
+        let mut hud_text = TextBatch::new(camera.screen_size());
+        hud.layout(&mut hud_text, camera.screen_size(), command_line.text());
+        if show_help {
+            hud::layout_help(&mut hud_text, camera.screen_size(), &keymap.help());
+        }
+        text_renderer.draw(&display, &mut target, &hud_text, &text_colour, &false);
+        legend_renderer.draw(&display, &mut target, camera.screen_size(), &legend, &text_renderer, &text_colour);
+        axis_gizmo.draw(&display, &mut target, &camera, molecule.lattice(), &text_renderer);
         target.finish().unwrap();
 
         for ev in display.poll_events() {
@@ -160,7 +583,264 @@ fn main() {
                 glium::glutin::Event::Resized(x, y) => {
 		    camera.set_screen_size(&x, &y);
 		},
+
+                // ==============================
+                // Mouse is used
+                // ==============================
+                glium::glutin::Event::MouseInput(state, button) => {
+                    // no pick if the view can't be undone, e.g. while the window has no size
+                    let ray = mouse_controls.mouse_input(&state, &button).and_then(|pixel| camera.ray(&pixel));
+                    if let Some((origin, direction)) = ray {
+                        match (molecule.pick(&origin, &direction, &slice_planes), shift_held) {
+                            (Some(i), true) => {
+                                if let Some(position) = selected_atoms.iter().position(|&j| j == i) {
+                                    selected_atoms.remove(position);
+                                } else {
+                                    selected_atoms.push(i);
+                                    if selected_atoms.len() > 4 {
+                                        selected_atoms.remove(0);
+                                    }
+                                    hud.set_status(&describe_atom(&molecule, &i));
+                                }
+                                if let Some(measurement) = Measurement::new(&molecule, &selected_atoms) {
+                                    hud.set_status(&measurement.describe(&molecule));
+                                }
+                            },
+                            (Some(i), false) => {
+                                selected_atoms = vec![i];
+                                hud.set_status(&describe_atom(&molecule, &i));
+                            },
+                            (None, true) => {},
+                            (None, false) => {
+                                selected_atoms.clear();
+                                hud.set_status("Selection cleared");
+                            },
+                        }
+                    }
+                },
+                glium::glutin::Event::MouseMoved(x, y) => {
+                    mouse_controls.mouse_moved(&mut camera, &x, &y);
+                },
+                glium::glutin::Event::MouseWheel(delta, _) => {
+                    mouse_controls.mouse_wheel(&mut camera, &delta);
+                },
                 
+                // ==============================
+                // Command prompt is open
+                // ==============================
+                glium::glutin::Event::ReceivedCharacter(c) => {
+                    command_line.received_character(&c);
+                },
+                glium::glutin::Event::KeyboardInput (
+                    glium::glutin::ElementState::Pressed,
+                    _,
+                    Some(key)
+                ) if command_line.is_open() => match key {
+                    glium::glutin::VirtualKeyCode::Escape => {
+                        command_line.cancel();
+                        hud.set_status("Command cancelled");
+                    },
+                    glium::glutin::VirtualKeyCode::Return => {
+                        let line = command_line.take().unwrap_or_default();
+                        match command::parse(&line) {
+                            Ok(Command::ViewDirection(uvw)) => {
+                                let lattice = cell_or_cartesian(&molecule);
+                                let uvw = [uvw[0] as f32, uvw[1] as f32, uvw[2] as f32];
+                                view_along(&mut camera, &lattice, &lattice.direction(&uvw));
+                                hud.set_status(&format!("Viewing along {}", line.trim()));
+                            },
+                            Ok(Command::ViewPlane(hkl)) => {
+                                let lattice = cell_or_cartesian(&molecule);
+                                let hkl = [hkl[0] as f32, hkl[1] as f32, hkl[2] as f32];
+                                view_along(&mut camera, &lattice, &lattice.plane_normal(&hkl));
+                                hud.set_status(&format!("Viewing normal to {}", line.trim()));
+                            },
+                            Ok(Command::SaveView(name)) => {
+                                match view_bookmarks.set(&name, &camera.state()) {
+                                    Ok(()) => hud.set_status(&format!("Saved view {} to {}", name, view_bookmarks.path().display())),
+                                    Err(e) => hud.set_status(&format!("Could not save view: {}", e)),
+                                }
+                            },
+                            Ok(Command::RestoreView(name)) => {
+                                match view_bookmarks.get(&name) {
+                                    Some(state) => {
+                                        camera.set_state(state);
+                                        hud.set_status(&format!("Restored view {}", name));
+                                    },
+                                    None => hud.set_status(&format!("There is no view called {}", name)),
+                                }
+                            },
+                            Ok(Command::Label(label)) => {
+                                for &i in &selected_atoms {
+                                    molecule.atoms_mut()[i].set_label(&label);
+                                }
+                                label_mode = LabelMode::Custom;
+                                hud.set_status(&format!("Labelled {} atom(s) {}", selected_atoms.len(), label));
+                            },
+                            Ok(Command::Style(representation)) => {
+                                for &i in &selected_atoms {
+                                    molecule.atoms_mut()[i].set_representation(&representation);
+                                }
+                                camera.set_scene_radius(&molecule.bounding_sphere().1);
+                                hud.set_status(&format!(
+                                    "Drawing {} atom(s) as {}",
+                                    selected_atoms.len(),
+                                    representation.unwrap_or(molecule.representation()).name(),
+                                ));
+                            },
+                            Ok(Command::Colour {name, map, range}) => {
+                                match ColourScheme::new(&molecule, &name, &map, &range) {
+                                    Ok(scheme) => {
+                                        colour_scheme = scheme;
+                                        let (colours, key) = colour_scheme.apply(&molecule);
+                                        atom_colours = colours;
+                                        legend = key;
+                                        hud.set_status(&format!("Colouring atoms by {}", colour_scheme.name()));
+                                    },
+                                    Err(e) => hud.set_status(&e),
+                                }
+                            },
+                            Ok(Command::ColourAtoms(query, colour)) => {
+                                let coloured = selection::indices(&molecule.select(&query));
+                                for &i in &coloured {
+                                    molecule.atoms_mut()[i].set_colour(&colour);
+                                }
+                                let (colours, key) = colour_scheme.apply(&molecule);
+                                atom_colours = colours;
+                                legend = key;
+                                hud.set_status(&match colour {
+                                    Some(_) => format!("Coloured {} atom(s)", coloured.len()),
+                                    None => format!("Coloured {} atom(s) by {}", coloured.len(), colour_scheme.name()),
+                                });
+                            },
+                            Ok(Command::Select(query)) => {
+                                selected_atoms = selection::indices(&molecule.select(&query));
+                                hud.set_status(&format!("Selected {} atom(s)", selected_atoms.len()));
+                            },
+                            Ok(Command::Delete(query)) => {
+                                let deleted = molecule.select(&query);
+                                let count = deleted.iter().filter(|&&x| x).count();
+                                let kept : Vec<bool> = deleted.iter().map(|&x| !x).collect();
+                                molecule = molecule.subset(&kept);
+                                bonds = molecule.bonds(&bond_cutoff);
+                                let (colours, key) = colour_scheme.apply(&molecule);
+                                atom_colours = colours;
+                                legend = key;
+                                // atom numbers have changed, so anything referring to them goes
+                                selected_atoms.clear();
+                                measurements.clear();
+                                hud.set_atoms(&molecule.formula(), &molecule.atoms().len());
+                                hud.set_status(&format!("Deleted {} atom(s)", count));
+                            },
+                            Ok(Command::ExportSelection(query)) => {
+                                let subset = molecule.subset(&molecule.select(&query));
+                                let stem = format!("{}_selection", export_stem);
+                                let mut export_options = export::ExportOptions::new(&subset);
+                                export_options.polyhedra = export_polyhedra;
+                                match export::write_all(&stem, &subset, &export_options) {
+                                    Ok(()) => hud.set_status(&format!(
+                                        "Exported {} atom(s) to {1}.gltf, {1}.obj and {1}.stl",
+                                        subset.atoms().len(),
+                                        stem,
+                                    )),
+                                    Err(e) => hud.set_status(&format!("Export failed: {}", e)),
+                                }
+                            },
+                            Ok(Command::Hide(query)) => {
+                                let mask = molecule.select(&query);
+                                molecule.set_atoms_visible(&mask, &false);
+                                selected_atoms.retain(|i| molecule.visible(i));
+                                hud.set_status(&format!("Hid {} atom(s)", selection::indices(&mask).len()));
+                            },
+                            Ok(Command::Show(query)) => {
+                                let mask = molecule.select(&query);
+                                molecule.set_atoms_visible(&mask, &true);
+                                hud.set_status(&format!("Showed {} atom(s)", selection::indices(&mask).len()));
+                            },
+                            Ok(Command::ShowAll) => {
+                                molecule.show_all();
+                                focus_radius = None;
+                                hud.set_status("Showing every atom");
+                            },
+                            Ok(Command::Clip(Some(spec))) => {
+                                molecule.add_clip_plane(&place_plane(&molecule, &spec));
+                                selected_atoms.retain(|i| molecule.visible(i));
+                                hud.set_status(&format!(
+                                    "Clipping beyond {} (clip off to stop)",
+                                    line.trim()["clip".len()..].trim(),
+                                ));
+                            },
+                            Ok(Command::Clip(None)) => {
+                                molecule.clear_clip_planes();
+                                hud.set_status("Stopped clipping");
+                            },
+                            Ok(Command::Slice(Some(spec))) => {
+                                if slice_planes.len() == max_slice_planes {
+                                    slice_planes.remove(0);
+                                }
+                                slice_planes.push(place_plane(&molecule, &spec));
+                                hud.set_status(&format!(
+                                    "Slicing along {} ([ and ] to move it, slice off to stop)",
+                                    line.trim()["slice".len()..].trim(),
+                                ));
+                            },
+                            Ok(Command::Slice(None)) => {
+                                slice_planes.clear();
+                                hud.set_status("Stopped slicing");
+                            },
+                            Ok(Command::Frame(frame)) => {
+                                if frame <= molecule.frame_count() {
+                                    playback.set_playing(&false);
+                                    goto_frame = Some(frame-1);
+                                } else {
+                                    hud.set_status(&format!("There are only {} frames", molecule.frame_count()));
+                                }
+                            },
+                            Ok(Command::Write {path, fractional}) => {
+                                match file_output::write_cell_file(Path::new(&path), &molecule, &fractional) {
+                                    Ok(()) => hud.set_status(&format!("Wrote {} atom(s) to {}", molecule.atoms().len(), path)),
+                                    Err(e) => hud.set_status(&format!("Could not write {}: {}", path, e)),
+                                }
+                            },
+                            Ok(Command::Focus(radius)) => {
+                                focus_radius = radius;
+                                hud.set_status(&match radius {
+                                    Some(radius) => format!("Showing only atoms within {} Å of the selected atoms", radius),
+                                    None => "Showing atoms however far from the selection".to_string(),
+                                });
+                            },
+                            Err(e) => hud.set_status(&e),
+                        }
+                    },
+                    _ => {},
+                },
+
+                // ==============================
+                // Modifier keys
+                // ==============================
+                glium::glutin::Event::KeyboardInput (
+                    state,
+                    _,
+                    Some(glium::glutin::VirtualKeyCode::LControl)
+                ) | glium::glutin::Event::KeyboardInput (
+                    state,
+                    _,
+                    Some(glium::glutin::VirtualKeyCode::RControl)
+                ) => {
+                    control_held = state == glium::glutin::ElementState::Pressed;
+                },
+                glium::glutin::Event::KeyboardInput (
+                    state,
+                    _,
+                    Some(glium::glutin::VirtualKeyCode::LShift)
+                ) | glium::glutin::Event::KeyboardInput (
+                    state,
+                    _,
+                    Some(glium::glutin::VirtualKeyCode::RShift)
+                ) => {
+                    shift_held = state == glium::glutin::ElementState::Pressed;
+                },
+
                 // ==============================
                 // Key is pressed
                 // ==============================
@@ -168,57 +848,255 @@ fn main() {
                     glium::glutin::ElementState::Pressed,
                     _,
                     Some(key)
-                ) => match key {
-		    glium::glutin::VirtualKeyCode::Escape => return,
-		    glium::glutin::VirtualKeyCode::Space => {
+                ) => match keymap.action(&key, &control_held, &shift_held) {
+                    Some(Action::OpenPrompt) => {
+                        command_line.open();
+                        hud.set_status("Type a command, e.g. [001] or (111), then press Return");
+                    },
+		    Some(Action::Quit) => return,
+                    Some(Action::ToggleHelp) => {
+                        show_help = !show_help;
+                    },
+		    Some(Action::ToggleFxaa) => {
                         fxaa_enabled = !fxaa_enabled;
-                        println! (
+                        hud.set_status(&format!(
 		            "FXAA is now {}",
 		            if fxaa_enabled { "on" } else { "off" }
-		        );
+		        ));
 	            },
-		    glium::glutin::VirtualKeyCode::Up => {
+		    Some(Action::ZoomIn) => {
 		        camera.zoom_in();
-			println! ("Zooming in");
+			hud.set_status("Zooming in");
 		    },
-		    glium::glutin::VirtualKeyCode::Down => {
+		    Some(Action::ZoomOut) => {
 		        camera.zoom_out();
-			println!("Zooming out");
+			hud.set_status("Zooming out");
 		    },
-		    glium::glutin::VirtualKeyCode::Right => {
+		    Some(Action::SpinClockwise) => {
 		        camera.spin_clockwise();
-			println! ("Spinning clockwise");
+			hud.set_status("Spinning clockwise");
 		    },
-		    glium::glutin::VirtualKeyCode::Left => {
+		    Some(Action::SpinAnticlockwise) => {
 		        camera.spin_anticlockwise();
-			println! ("Spinning anticlockwise");
+			hud.set_status("Spinning anticlockwise");
 		    },
-		    glium::glutin::VirtualKeyCode::K => {
+		    Some(Action::AzimuthUp) => {
 		        camera.azimuth_up();
-			println! ("Azimuthing up");
+			hud.set_status("Azimuthing up");
 		    },
-		    glium::glutin::VirtualKeyCode::J => {
+		    Some(Action::AzimuthDown) => {
 		        camera.azimuth_down();
-			println! ("Azimuthing down");
+			hud.set_status("Azimuthing down");
 		    },
-		    glium::glutin::VirtualKeyCode::H => {
+		    Some(Action::OrbitLeft) => {
 		        camera.orbit_left();
-			println! ("Orbiting left");
+			hud.set_status("Orbiting left");
 		    },
-		    glium::glutin::VirtualKeyCode::L => {
+		    Some(Action::OrbitRight) => {
 		        camera.orbit_right();
-			println! ("Orbiting right");
+			hud.set_status("Orbiting right");
 		    },
-                    glium::glutin::VirtualKeyCode::R => {
+                    Some(Action::ResetCamera) => {
                         camera.set_angles (
                             &camera_theta_degrees,
                             &camera_phi_degrees,
                             &camera_psi_degrees,
-                            &camera_r
                         );
-                        println! ("Resetting camera");
+                        let (scene_centre, scene_radius) = molecule.bounding_sphere();
+                        camera.fit_to_sphere(&scene_centre, &scene_radius);
+                        hud.set_status("Resetting camera");
+                    },
+                    Some(Action::SaveView(digit)) => {
+                        let name = format!("{}", digit);
+                        match view_bookmarks.set(&name, &camera.state()) {
+                            Ok(()) => hud.set_status(&format!("Saved view {}", name)),
+                            Err(e) => hud.set_status(&format!("Could not save view: {}", e)),
+                        }
+                    },
+                    Some(Action::RecallView(digit)) => {
+                        let name = format!("{}", digit);
+                        match view_bookmarks.get(&name) {
+                            Some(state) => {
+                                camera.set_state(state);
+                                hud.set_status(&format!("Restored view {}", name));
+                            },
+                            None => hud.set_status(&format!(
+                                "No view saved on {} (save with {})",
+                                name,
+                                keymap.keys_for(&Action::SaveView(digit)),
+                            )),
+                        }
+                    },
+                    Some(Action::ViewAlongA) => {
+                        let lattice = cell_or_cartesian(&molecule);
+                        view_along(&mut camera, &lattice, &lattice.direction(&[1.0, 0.0, 0.0]));
+                        hud.set_status("Viewing along a");
+                    },
+                    Some(Action::ViewAlongB) => {
+                        let lattice = cell_or_cartesian(&molecule);
+                        view_along(&mut camera, &lattice, &lattice.direction(&[0.0, 1.0, 0.0]));
+                        hud.set_status("Viewing along b");
+                    },
+                    Some(Action::ViewAlongC) => {
+                        let lattice = cell_or_cartesian(&molecule);
+                        view_along(&mut camera, &lattice, &lattice.direction(&[0.0, 0.0, 1.0]));
+                        hud.set_status("Viewing along c");
+                    },
+                    Some(Action::FitToView) => {
+                        let (scene_centre, scene_radius) = molecule.bounding_sphere();
+                        camera.fit_to_sphere(&scene_centre, &scene_radius);
+                        hud.set_status("Fitting structure to view");
                     },
-		    _ => {},
+                    Some(Action::ToggleProjection) => {
+                        camera.toggle_projection();
+                        hud.set_status(&format!(
+                            "Projection is now {}",
+                            if camera.orthographic() { "orthographic" } else { "perspective" }
+                        ));
+                    },
+                    Some(Action::ToggleInertia) => {
+                        mouse_controls.toggle_inertia();
+                        hud.set_status(&format!(
+                            "Mouse inertia is now {}",
+                            if mouse_controls.inertia() { "on" } else { "off" }
+                        ));
+                    },
+                    Some(Action::TogglePolyhedra) => {
+                        export_polyhedra = !export_polyhedra;
+                        hud.set_status(&format!(
+                            "Polyhedra in exported models are now {}",
+                            if export_polyhedra { "on" } else { "off" }
+                        ));
+                    },
+                    Some(Action::Export) => {
+                        let mut export_options = export::ExportOptions::new(&molecule);
+                        export_options.polyhedra = export_polyhedra;
+                        match export::write_all(&export_stem, &molecule, &export_options) {
+                            Ok(()) => hud.set_status(&format!("Exported {0}.gltf, {0}.obj and {0}.stl", export_stem)),
+                            Err(e) => hud.set_status(&format!("Export failed: {}", e)),
+                        }
+                    },
+                    Some(Action::CycleLabels) => {
+                        label_mode = label_mode.next();
+                        hud.set_status(&format!("Atom labels are now {}", label_mode.name()));
+                    },
+                    Some(Action::CycleRepresentation) => {
+                        let representation = molecule.representation().next();
+                        molecule.set_representation(&representation);
+                        camera.set_scene_radius(&molecule.bounding_sphere().1);
+                        hud.set_status(&format!("Drawing atoms as {}", representation.name()));
+                    },
+                    Some(Action::CycleSelectionRepresentation) => {
+                        if selected_atoms.is_empty() {
+                            hud.set_status("Click on atoms to select them first");
+                        } else {
+                            let representation = Some(molecule.atom_representation(&selected_atoms[0]).next());
+                            for &i in &selected_atoms {
+                                molecule.atoms_mut()[i].set_representation(&representation);
+                            }
+                            camera.set_scene_radius(&molecule.bounding_sphere().1);
+                            hud.set_status(&format!(
+                                "Drawing {} selected atom(s) as {}",
+                                selected_atoms.len(),
+                                representation.unwrap().name(),
+                            ));
+                        }
+                    },
+                    Some(Action::CycleColours) => {
+                        colour_scheme = colour_scheme.next(&molecule);
+                        let (colours, key) = colour_scheme.apply(&molecule);
+                        atom_colours = colours;
+                        legend = key;
+                        hud.set_status(&format!("Colouring atoms by {}", colour_scheme.name()));
+                    },
+                    Some(Action::ToggleSpecies(number)) => {
+                        match molecule.species_names().get(number as usize-1) {
+                            Some(name) => {
+                                let shown = molecule.toggle_species(name);
+                                selected_atoms.retain(|i| molecule.visible(i));
+                                hud.set_status(&format!("{} is now {}", name, if shown {"shown"} else {"hidden"}));
+                            },
+                            None => hud.set_status(&format!("There is no species {}", number)),
+                        }
+                    },
+                    Some(sweep @ Action::SweepSliceBack) | Some(sweep @ Action::SweepSliceForward) => {
+                        let step = if sweep == Action::SweepSliceBack {-slice_step} else {slice_step};
+                        match slice_planes.pop() {
+                            Some(plane) => {
+                                let plane = plane.moved(&step);
+                                let (scene_centre, _) = molecule.bounding_sphere();
+                                hud.set_status(&format!(
+                                    "Slice {:.2} Å from the centre of the structure",
+                                    -plane.distance(&scene_centre),
+                                ));
+                                slice_planes.push(plane);
+                            },
+                            None => hud.set_status("Type slice (hkl) or slice X Y Z to slice the structure"),
+                        }
+                    },
+                    Some(Action::TogglePlayback) => {
+                        let frame_count = molecule.frame_count();
+                        if frame_count < 2 {
+                            hud.set_status("There is only one frame to play");
+                        } else if playback.playing() {
+                            playback.set_playing(&false);
+                            hud.set_status(&format!("Paused at frame {}/{}", molecule.frame()+1, frame_count));
+                        } else {
+                            // playing from the last frame starts again from the first
+                            if *molecule.frame()+1 == frame_count {
+                                goto_frame = Some(0);
+                            }
+                            playback.set_playing(&true);
+                            hud.set_status(&format!("Playing at {} frames a second", playback.frames_per_second()));
+                        }
+                    },
+                    Some(step @ Action::NextFrame) | Some(step @ Action::PreviousFrame) => {
+                        playback.set_playing(&false);
+                        let frame_count = molecule.frame_count();
+                        goto_frame = Some(if step == Action::NextFrame {
+                            playback.next_frame(molecule.frame(), &frame_count)
+                        } else {
+                            playback.previous_frame(molecule.frame(), &frame_count)
+                        });
+                    },
+                    Some(Action::FasterPlayback) => {
+                        playback.faster();
+                        hud.set_status(&format!("Playing at {} frames a second", playback.frames_per_second()));
+                    },
+                    Some(Action::SlowerPlayback) => {
+                        playback.slower();
+                        hud.set_status(&format!("Playing at {} frames a second", playback.frames_per_second()));
+                    },
+                    Some(Action::ToggleLooping) => {
+                        playback.toggle_looping();
+                        hud.set_status(if playback.looping() {
+                            "Playback goes round from the last frame to the first"
+                        } else {
+                            "Playback stops at the last frame"
+                        });
+                    },
+                    Some(Action::KeepMeasurement) => {
+                        match Measurement::new(&molecule, &selected_atoms) {
+                            Some(measurement) => {
+                                hud.set_status(&format!("Kept {}", measurement.describe(&molecule)));
+                                measurements.push(measurement);
+                                selected_atoms.clear();
+                            },
+                            None => hud.set_status("Shift-click 2, 3 or 4 atoms to measure them"),
+                        }
+                    },
+                    Some(Action::ClearMeasurements) => {
+                        measurements.clear();
+                        hud.set_status("Cleared measurements");
+                    },
+                    Some(Action::ExportMeasurements) => {
+                        let path = format!("{}_measurements.csv", export_stem);
+                        match measurement::write_csv(Path::new(&path), &molecule, &measurements) {
+                            Ok(()) => hud.set_status(&format!("Wrote {} measurement(s) to {}", measurements.len(), path)),
+                            Err(e) => hud.set_status(&format!("Could not write measurements: {}", e)),
+                        }
+                    },
+		    None => {},
                 },
 
                 // ==============================
@@ -229,3 +1107,85 @@ fn main() {
         }
     }
 }
+
+/// What is known about a picked atom, one fact per line.
+fn describe_atom(in_molecule : &Molecule, in_index : &usize) -> String {
+    let atom = &in_molecule.atoms()[*in_index];
+    let position = atom.position();
+    let mut description = format!(
+        "Atom {}: {} at ({:.4}, {:.4}, {:.4}) Å",
+        in_index+1,
+        atom.species().name(),
+        position[0], position[1], position[2],
+    );
+    if let Some(lattice) = in_molecule.lattice() {
+        let fractional = lattice.to_fractional(position);
+        description.push_str(&format!(
+            "\n  fractional ({:.4}, {:.4}, {:.4})", fractional[0], fractional[1], fractional[2]
+        ));
+    }
+    let cutoff = in_molecule.default_bond_cutoff();
+    description.push_str(&format!(
+        "\n  coordination {} (neighbours within {:.3} Å)",
+        in_molecule.coordination(in_index, &cutoff),
+        cutoff,
+    ));
+    description
+}
+
+/// Adds vertices to the batch of their colour, starting a new batch if need be.
+fn add_to_batch(in_batches : &mut Vec<([f32;3], Vec<Vertex>)>, in_colour : &[f32;3], in_vertices : &[Vertex]) {
+    match in_batches.iter().position(|batch| batch.0 == *in_colour) {
+        Some(position) => in_batches[position].1.extend_from_slice(in_vertices),
+        None => in_batches.push((*in_colour, in_vertices.to_vec())),
+    }
+}
+
+/// A square on in_plane centred as near in_centre as it can be, big enough to
+/// cover a sphere of in_radius there, as a triangle strip.
+fn cap_vertices(in_plane : &Plane, in_centre : &[f32;3], in_radius : &f32) -> Vec<Vertex> {
+    let normal = in_plane.normal();
+    let middle = vector::sub(in_centre, &vector::scale(normal, &in_plane.distance(in_centre)));
+    let seed = if normal[0].abs() < 0.9 {[1.0, 0.0, 0.0]} else {[0.0, 1.0, 0.0]};
+    let u = vector::scale(&vector::normalised(&vector::cross(&seed, normal)), in_radius);
+    let v = vector::scale(&vector::normalised(&vector::cross(normal, &u)), in_radius);
+    [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0f32)].iter().map(|&(a, b)| {
+        Vertex::new([
+            middle[0]+a*u[0]+b*v[0],
+            middle[1]+a*u[1]+b*v[1],
+            middle[2]+a*u[2]+b*v[2],
+        ], *normal)
+    }).collect()
+}
+
+/// Places a plane typed at the prompt in the structure.
+fn place_plane(in_molecule : &Molecule, in_spec : &PlaneSpec) -> Plane {
+    match *in_spec {
+        PlaneSpec::Miller(hkl, offset) => {
+            let hkl = [hkl[0] as f32, hkl[1] as f32, hkl[2] as f32];
+            Plane::from_hkl(&cell_or_cartesian(in_molecule), &hkl, &offset)
+        },
+        PlaneSpec::Normal(normal, offset) => Plane::from_normal(&normal, &offset),
+    }
+}
+
+/// The molecule's unit cell, or the Cartesian axes if it has none.
+fn cell_or_cartesian(in_molecule : &Molecule) -> Lattice {
+    match in_molecule.lattice() {
+        Some(lattice) => *lattice,
+        None => Lattice::new(&[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]], &[0.0;3]),
+    }
+}
+
+/// Looks along in_direction with c (or failing that b, then a) pointing up the screen,
+/// as is conventional for crystal structure figures.
+fn view_along(in_camera : &mut Camera, in_lattice : &Lattice, in_direction : &[f32;3]) {
+    let direction = vector::normalised(in_direction);
+    let vectors = in_lattice.vectors();
+    for up in [vectors[2], vectors[1], vectors[0]].iter() {
+        if vector::dot(&direction, &vector::normalised(up)).abs() < 0.9 {
+            in_camera.look_along(&direction, up);
+            return;
+        }
+    }
+}
@@ -18,6 +18,44 @@ impl Matrix {
     }
 
     pub fn contents(&self) -> &[[f32;4];4] {&self._contents}
+
+    /// The inverse, by Gauss-Jordan elimination with partial pivoting.
+    /// Returns None if the matrix is singular.
+    pub fn inverse(&self) -> Option<Matrix> {
+        let mut a = self._contents;
+        let mut inverse = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0f32],
+        ];
+        for column in 0..4 {
+            let mut pivot = column;
+            for row in column+1..4 {
+                if a[row][column].abs() > a[pivot][column].abs() {
+                    pivot = row;
+                }
+            }
+            if a[pivot][column].abs() < 1.0e-12 {return None;}
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = 1.0/a[column][column];
+            for k in 0..4 {
+                a[column][k] *= scale;
+                inverse[column][k] *= scale;
+            }
+            for row in 0..4 {
+                if row == column {continue;}
+                let factor = a[row][column];
+                for k in 0..4 {
+                    a[row][k] -= factor*a[column][k];
+                    inverse[row][k] -= factor*inverse[column][k];
+                }
+            }
+        }
+        Some(Matrix::new(inverse))
+    }
 }
 
 // Matrix multiplication. TODO: use a linear algebra library.
@@ -0,0 +1,198 @@
+use std::f32;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+use molecule::Molecule;
+use vector::{sub, dot, cross, scale, length, normalised};
+
+// ============================================================
+// Measurement
+// ============================================================
+/// A distance (2 atoms), bond angle (3 atoms) or torsion (4 atoms).
+/// In a periodic cell each atom is taken as the image nearest the one before it,
+/// so measurements across the cell walls come out right.
+pub struct Measurement {
+    _atoms  : Vec<usize>,
+    /// The positions of the atoms, unwrapped into one connected chain.
+    _points : Vec<[f32;3]>,
+    _value  : f32,
+}
+
+impl Measurement {
+    /// Returns None unless there are 2, 3 or 4 atoms.
+    pub fn new(in_molecule : &Molecule, in_atoms : &[usize]) -> Option<Measurement> {
+        if in_atoms.len() < 2 || in_atoms.len() > 4 {return None;}
+
+        let mut points = vec![in_molecule.atoms()[in_atoms[0]].position().to_owned()];
+        for pair in in_atoms.windows(2) {
+            let step = in_molecule.separation(&pair[0], &pair[1]);
+            let last = points[points.len()-1];
+            points.push([last[0]+step[0], last[1]+step[1], last[2]+step[2]]);
+        }
+
+        let value = match points.len() {
+            2 => length(&sub(&points[1], &points[0])),
+            3 => angle(&sub(&points[0], &points[1]), &sub(&points[2], &points[1])),
+            _ => dihedral(&points),
+        };
+        Some(Measurement {
+            _atoms  : in_atoms.to_owned(),
+            _points : points,
+            _value  : value,
+        })
+    }
+
+    pub fn atoms(&self) -> &Vec<usize> {&self._atoms}
+
+    /// In Å for distances and degrees for angles.
+    pub fn value(&self) -> &f32 {&self._value}
+
+    pub fn kind(&self) -> &'static str {
+        match self._atoms.len() {
+            2 => "distance",
+            3 => "angle",
+            _ => "dihedral",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        if self._atoms.len() == 2 {"Å"} else {"°"}
+    }
+
+    /// The atoms as e.g. "O3-Na4-O7", numbered from 1.
+    pub fn label(&self, in_molecule : &Molecule) -> String {
+        self._atoms.iter()
+            .map(|&i| format!("{}{}", in_molecule.atoms()[i].species().name(), i+1))
+            .collect::<Vec<String>>()
+            .join("-")
+    }
+
+    /// Where to write the value: the middle of the chain of atoms.
+    pub fn label_position(&self) -> [f32;3] {
+        let mut centre = [0.0;3];
+        for point in &self._points {
+            for l in 0..3 {
+                centre[l] += point[l]/(self._points.len() as f32);
+            }
+        }
+        centre
+    }
+
+    pub fn describe(&self, in_molecule : &Molecule) -> String {
+        format!("{} {} = {:.4} {}", self.kind(), self.label(in_molecule), self._value, self.unit())
+    }
+
+    /// End points of the dashes to draw: dashed lines along the chain of atoms,
+    /// and for an angle a dashed arc around the middle atom.
+    pub fn dashes(&self) -> Vec<[f32;3]> {
+        let dash = 0.1;
+        let mut vertices = Vec::new();
+        for pair in self._points.windows(2) {
+            dashed_line(&mut vertices, &pair[0], &pair[1], &dash);
+        }
+        if self._points.len() == 3 {
+            let centre = self._points[1];
+            let arm_a = sub(&self._points[0], &centre);
+            let arm_b = sub(&self._points[2], &centre);
+            // a straight angle doesn't have a plane to draw the arc in
+            if length(&cross(&arm_a, &arm_b)) < 1.0e-6 {return vertices;}
+            let radius = 0.3*length(&arm_a).min(length(&arm_b));
+            // an arc from a towards b in their common plane
+            let u = normalised(&arm_a);
+            let w = normalised(&cross(&cross(&arm_a, &arm_b), &arm_a));
+            let theta = self._value*f32::consts::PI/180.0;
+            let steps = ((radius*theta/dash).ceil() as usize).max(2);
+            let arc_point = |k : usize| -> [f32;3] {
+                let t = theta*(k as f32)/(steps as f32);
+                let offset = [
+                    radius*(t.cos()*u[0]+t.sin()*w[0]),
+                    radius*(t.cos()*u[1]+t.sin()*w[1]),
+                    radius*(t.cos()*u[2]+t.sin()*w[2]),
+                ];
+                [centre[0]+offset[0], centre[1]+offset[1], centre[2]+offset[2]]
+            };
+            // every other step is a gap
+            for k in (0..steps).step_by(2) {
+                vertices.push(arc_point(k));
+                vertices.push(arc_point(k+1));
+            }
+        }
+        vertices
+    }
+}
+
+/// The angle between two vectors, in degrees.
+fn angle(in_a : &[f32;3], in_b : &[f32;3]) -> f32 {
+    let cos = dot(in_a, in_b)/(length(in_a)*length(in_b));
+    cos.max(-1.0).min(1.0).acos()*180.0/f32::consts::PI
+}
+
+/// The torsion angle of four points, in degrees between -180 and 180.
+fn dihedral(in_points : &Vec<[f32;3]>) -> f32 {
+    let b1 = sub(&in_points[1], &in_points[0]);
+    let b2 = sub(&in_points[2], &in_points[1]);
+    let b3 = sub(&in_points[3], &in_points[2]);
+    let n1 = cross(&b1, &b2);
+    let n2 = cross(&b2, &b3);
+    // positive when clockwise looking down b2 (IUPAC)
+    (length(&b2)*dot(&b1, &n2)).atan2(dot(&n1, &n2))*180.0/f32::consts::PI
+}
+
+/// Appends the end points of dashes (and equal gaps) of length in_dash from in_start to in_end.
+fn dashed_line(in_vertices : &mut Vec<[f32;3]>, in_start : &[f32;3], in_end : &[f32;3], in_dash : &f32) {
+    let line = sub(in_end, in_start);
+    let count = ((length(&line)/in_dash).ceil() as usize).max(1);
+    let step = scale(&line, &(1.0/(count as f32)));
+    for k in (0..count).step_by(2) {
+        let k = k as f32;
+        in_vertices.push([
+            in_start[0]+k*step[0],
+            in_start[1]+k*step[1],
+            in_start[2]+k*step[2],
+        ]);
+        in_vertices.push([
+            in_start[0]+(k+1.0)*step[0],
+            in_start[1]+(k+1.0)*step[1],
+            in_start[2]+(k+1.0)*step[2],
+        ]);
+    }
+}
+
+/// Writes measurements as CSV, one per line.
+pub fn write_csv(
+    in_path         : &Path,
+    in_molecule     : &Molecule,
+    in_measurements : &Vec<Measurement>,
+) -> Result<(), String> {
+    let mut text = String::from("type,atoms,value,unit\n");
+    for measurement in in_measurements {
+        text.push_str(&format!(
+            "{},{},{},{}\n",
+            measurement.kind(),
+            measurement.label(in_molecule),
+            measurement.value(),
+            if measurement.unit() == "Å" {"angstrom"} else {"degrees"},
+        ));
+    }
+    File::create(in_path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| format!("{}: {}", in_path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dihedral_is_positive_clockwise() {
+        let points = vec![[1.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 1.0]];
+        assert!((dihedral(&points)-90.0).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn dihedral_of_mirror_image_is_negative() {
+        let points = vec![[1.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 1.0]];
+        assert!((dihedral(&points)+90.0).abs() < 1.0e-4);
+    }
+}
@@ -0,0 +1,608 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::f32;
+
+use vector::{sub, dot, cross, scale, length, normalised};
+
+// ============================================================
+// Mesh
+// ============================================================
+/// A triangle mesh held in main memory, for writing out to 3D model files.
+/// Unlike model::Model this has no OpenGL buffers. Solids added to it may
+/// overlap, which is fine for drawing; union_surface() makes a single closed
+/// surface from them for a 3D printer.
+/// Triangles are wound anticlockwise when viewed from outside.
+#[derive(Clone)]
+pub struct Mesh {
+    _positions : Vec<[f32;3]>,
+    _normals   : Vec<[f32;3]>,
+    _triangles : Vec<[u32;3]>,
+}
+
+impl Mesh {
+    pub fn new() -> Mesh {
+        Mesh {
+            _positions : Vec::new(),
+            _normals   : Vec::new(),
+            _triangles : Vec::new(),
+        }
+    }
+
+    pub fn positions(&self) -> &Vec<[f32;3]> {&self._positions}
+    pub fn normals(&self) -> &Vec<[f32;3]> {&self._normals}
+    pub fn triangles(&self) -> &Vec<[u32;3]> {&self._triangles}
+    pub fn is_empty(&self) -> bool {self._triangles.is_empty()}
+
+    fn add_vertex(&mut self, in_position : [f32;3], in_normal : [f32;3]) -> u32 {
+        self._positions.push(in_position);
+        self._normals.push(in_normal);
+        (self._positions.len()-1) as u32
+    }
+
+    /// The corners of every triangle, for formats without an index buffer.
+    pub fn triangle_corners(&self) -> Vec<[[f32;3];3]> {
+        self._triangles.iter().map(|t| [
+            self._positions[t[0] as usize],
+            self._positions[t[1] as usize],
+            self._positions[t[2] as usize],
+        ]).collect()
+    }
+
+    /// Copies a unit icosphere (see icosphere()) scaled to in_radius and centred on in_centre.
+    pub fn add_sphere(&mut self, in_unit_sphere : &Mesh, in_centre : &[f32;3], in_radius : &f32) {
+        let offset = self._positions.len() as u32;
+        for normal in &in_unit_sphere._normals {
+            let position = [
+                in_centre[0]+normal[0]*in_radius,
+                in_centre[1]+normal[1]*in_radius,
+                in_centre[2]+normal[2]*in_radius,
+            ];
+            self.add_vertex(position, *normal);
+        }
+        for triangle in &in_unit_sphere._triangles {
+            self._triangles.push([triangle[0]+offset, triangle[1]+offset, triangle[2]+offset]);
+        }
+    }
+
+    /// Adds a cylinder running from in_start to in_end, with flat caps if in_capped.
+    /// Uncapped cylinders that meet end to end make one seamless tube.
+    pub fn add_cylinder(
+        &mut self,
+        in_start    : &[f32;3],
+        in_end      : &[f32;3],
+        in_radius   : &f32,
+        in_segments : &u32,
+        in_capped   : &bool,
+    ) {
+        let axis = sub(in_end, in_start);
+        if length(&axis) == 0.0 {return;}
+        let w = normalised(&axis);
+        // any vector not parallel to the axis will do to start the frame
+        let seed = if w[0].abs() < 0.9 {[1.0, 0.0, 0.0]} else {[0.0, 1.0, 0.0]};
+        let u = normalised(&cross(&seed, &w));
+        let v = cross(&w, &u);
+
+        let segments = (*in_segments).max(3);
+        let mut ring_normals = Vec::new();
+        for s in 0..segments {
+            let angle = 2.0*f32::consts::PI*(s as f32)/(segments as f32);
+            let (sin, cos) = angle.sin_cos();
+            ring_normals.push([
+                u[0]*cos+v[0]*sin,
+                u[1]*cos+v[1]*sin,
+                u[2]*cos+v[2]*sin,
+            ]);
+        }
+        let ring_point = |centre : &[f32;3], normal : &[f32;3]| [
+            centre[0]+normal[0]*in_radius,
+            centre[1]+normal[1]*in_radius,
+            centre[2]+normal[2]*in_radius,
+        ];
+
+        // ==============================
+        // Side
+        // ==============================
+        let side = self._positions.len() as u32;
+        for normal in &ring_normals {
+            self.add_vertex(ring_point(in_start, normal), *normal);
+            self.add_vertex(ring_point(in_end, normal), *normal);
+        }
+        for s in 0..segments {
+            let a = side+2*s;
+            let b = side+2*((s+1)%segments);
+            self._triangles.push([a, b, a+1]);
+            self._triangles.push([a+1, b, b+1]);
+        }
+
+        // ==============================
+        // Caps
+        // ==============================
+        if !*in_capped {return;}
+        let back = [-w[0], -w[1], -w[2]];
+        let start_centre = self.add_vertex(*in_start, back);
+        let end_centre = self.add_vertex(*in_end, w);
+        let start_ring = self._positions.len() as u32;
+        for normal in &ring_normals {
+            self.add_vertex(ring_point(in_start, normal), back);
+        }
+        let end_ring = self._positions.len() as u32;
+        for normal in &ring_normals {
+            self.add_vertex(ring_point(in_end, normal), w);
+        }
+        for s in 0..segments {
+            let t = (s+1)%segments;
+            self._triangles.push([start_centre, start_ring+t, start_ring+s]);
+            self._triangles.push([end_centre, end_ring+s, end_ring+t]);
+        }
+    }
+
+    /// Adds the convex hull of in_points as a flat-shaded solid.
+    /// Coplanar points (e.g. the square faces of a cube) become a single fan,
+    /// so no faces overlap. Fewer than four non-coplanar points add nothing.
+    pub fn add_convex_hull(&mut self, in_points : &Vec<[f32;3]>) {
+        for (corners, outward) in hull_faces(in_points) {
+            self.add_planar_polygon(in_points, &corners, &outward);
+        }
+    }
+
+    /// Fan-triangulates a convex planar polygon, wound anticlockwise about in_normal.
+    fn add_planar_polygon(&mut self, in_points : &Vec<[f32;3]>, in_corners : &Vec<usize>, in_normal : &[f32;3]) {
+        let mut centre = [0.0f32;3];
+        for &c in in_corners {
+            for l in 0..3 {centre[l] += in_points[c][l]/(in_corners.len() as f32);}
+        }
+        // angles are measured from the furthest corner, as one at the centre has no direction
+        let furthest = in_corners.iter()
+            .map(|&c| sub(&in_points[c], &centre))
+            .fold([0.0f32;3], |a, b| if length(&b) > length(&a) {b} else {a});
+        let u = normalised(&furthest);
+        let v = cross(in_normal, &u);
+        let mut corners : Vec<(f32, usize)> = in_corners.iter().map(|&c| {
+            let d = sub(&in_points[c], &centre);
+            (dot(&d, &v).atan2(dot(&d, &u)), c)
+        }).collect();
+        // degenerate faces can still give NaN angles, which sort anywhere rather than panicking
+        corners.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let first = self._positions.len() as u32;
+        for &(_, c) in &corners {
+            self.add_vertex(in_points[c], *in_normal);
+        }
+        for s in 1..(corners.len() as u32)-1 {
+            self._triangles.push([first, first+s, first+s+1]);
+        }
+    }
+}
+
+/// The faces of the convex hull of in_points, as the points on each and its outward unit normal.
+/// Coplanar points share one face. Fewer than four non-coplanar points have no faces.
+fn hull_faces(in_points : &Vec<[f32;3]>) -> Vec<(Vec<usize>, [f32;3])> {
+    let n = in_points.len();
+    let mut scale = 0.0f32;
+    for p in in_points {
+        for q in in_points {
+            scale = scale.max(length(&sub(p, q)));
+        }
+    }
+    let tolerance = 1e-4*scale.max(1e-6);
+
+    let mut faces : Vec<(Vec<usize>, [f32;3])> = Vec::new();
+    for i in 0..n {
+        for j in i+1..n {
+            for k in j+1..n {
+                let normal = cross(
+                    &sub(&in_points[j], &in_points[i]),
+                    &sub(&in_points[k], &in_points[i]),
+                );
+                if length(&normal) < tolerance*tolerance {continue;}
+                let normal = normalised(&normal);
+                let offset = dot(&normal, &in_points[i]);
+
+                // a hull face has every point on the same side of it
+                let mut above = false;
+                let mut below = false;
+                let mut on_face = Vec::new();
+                for (m, p) in in_points.iter().enumerate() {
+                    let height = dot(&normal, p)-offset;
+                    if height > tolerance {
+                        above = true;
+                    } else if height < -tolerance {
+                        below = true;
+                    } else {
+                        on_face.push(m);
+                    }
+                }
+                if above == below {continue;}
+                if faces.iter().any(|face| face.0 == on_face) {continue;}
+
+                let outward = if above {[-normal[0], -normal[1], -normal[2]]} else {normal};
+                faces.push((on_face, outward));
+            }
+        }
+    }
+    faces
+}
+
+// ============================================================
+// Solids
+// ============================================================
+/// A shape with an inside, for joining with others into one closed surface.
+#[derive(Clone, Debug)]
+pub enum Solid {
+    Sphere {
+        centre : [f32;3],
+        radius : f32,
+    },
+    /// A cylinder with flat ends.
+    Cylinder {
+        start  : [f32;3],
+        end    : [f32;3],
+        radius : f32,
+    },
+    /// A convex polyhedron: the planes of its faces as (outward unit normal, offset),
+    /// and its corners.
+    Polyhedron {
+        planes  : Vec<([f32;3], f32)>,
+        corners : Vec<[f32;3]>,
+    },
+}
+
+impl Solid {
+    /// The convex hull of in_points, or None if they don't enclose anything.
+    pub fn polyhedron(in_points : &Vec<[f32;3]>) -> Option<Solid> {
+        let faces = hull_faces(in_points);
+        if faces.is_empty() {return None;}
+        Some(Solid::Polyhedron {
+            planes  : faces.iter().map(|&(ref on_face, normal)| (normal, dot(&normal, &in_points[on_face[0]]))).collect(),
+            corners : in_points.clone(),
+        })
+    }
+
+    /// How far in_point is outside the solid, negative inside. Exact for spheres
+    /// and cylinders; for polyhedra it is zero on the faces and has the right sign.
+    fn distance(&self, in_point : &[f32;3]) -> f32 {
+        match *self {
+            Solid::Sphere {ref centre, radius} => length(&sub(in_point, centre))-radius,
+            Solid::Cylinder {ref start, ref end, radius} => {
+                let axis = sub(end, start);
+                let half_length = length(&axis)/2.0;
+                if half_length == 0.0 {return f32::INFINITY;}
+                let w = normalised(&axis);
+                let relative = sub(in_point, start);
+                let along = dot(&relative, &w);
+                let across = length(&sub(&relative, &scale(&w, &along)));
+                let dx = across-radius;
+                let dy = (along-half_length).abs()-half_length;
+                dx.max(dy).min(0.0)+(dx.max(0.0).powi(2)+dy.max(0.0).powi(2)).sqrt()
+            },
+            Solid::Polyhedron {ref planes, ..} => planes.iter()
+                .map(|&(normal, offset)| dot(&normal, in_point)-offset)
+                .fold(f32::NEG_INFINITY, f32::max),
+        }
+    }
+
+    /// Boxes (lowest and highest corners) which between them hold every point
+    /// within in_margin of the solid. Cylinders are covered by a string of small
+    /// boxes, so long thin bonds at an angle don't take in lots of empty space.
+    fn boxes(&self, in_margin : &f32) -> Vec<([f32;3], [f32;3])> {
+        let around = |centre : &[f32;3], half_width : &f32| (
+            [centre[0]-half_width, centre[1]-half_width, centre[2]-half_width],
+            [centre[0]+half_width, centre[1]+half_width, centre[2]+half_width],
+        );
+        match *self {
+            Solid::Sphere {ref centre, radius} => vec![around(centre, &(radius+in_margin))],
+            Solid::Cylinder {ref start, ref end, radius} => {
+                let reach = radius+in_margin;
+                let axis = sub(end, start);
+                let steps = ((length(&axis)/reach).ceil() as usize).max(1);
+                // every point within reach of the axis is within 1.2 reach of one of the steps
+                (0..steps+1).map(|k| {
+                    let centre = [
+                        start[0]+axis[0]*(k as f32)/(steps as f32),
+                        start[1]+axis[1]*(k as f32)/(steps as f32),
+                        start[2]+axis[2]*(k as f32)/(steps as f32),
+                    ];
+                    around(&centre, &(1.2*reach))
+                }).collect()
+            },
+            Solid::Polyhedron {ref corners, ..} => {
+                let mut low = [f32::INFINITY;3];
+                let mut high = [f32::NEG_INFINITY;3];
+                for corner in corners {
+                    for l in 0..3 {
+                        low[l] = low[l].min(corner[l]-in_margin);
+                        high[l] = high[l].max(corner[l]+in_margin);
+                    }
+                }
+                vec![(low, high)]
+            },
+        }
+    }
+
+    /// The solid with any radius below in_radius raised to it.
+    fn thickened(&self, in_radius : &f32) -> Solid {
+        match *self {
+            Solid::Sphere {centre, radius} => Solid::Sphere {centre, radius : radius.max(*in_radius)},
+            Solid::Cylinder {start, end, radius} => Solid::Cylinder {start, end, radius : radius.max(*in_radius)},
+            Solid::Polyhedron {..} => self.clone(),
+        }
+    }
+}
+
+/// The most points the distance is worked out at before union_surface() coarsens its grid.
+const MAX_GRID_POINTS : f32 = 2.0e7;
+
+/// The surface of the union of in_solids, as one closed mesh with no faces inside it,
+/// e.g. for a 3D printer. The distance to the nearest solid is sampled on a grid of
+/// in_spacing Å near the solids, and the surface where it is zero is made by marching
+/// tetrahedra. The grid is coarsened if it would be too big, and solids thinner than
+/// about three grid spacings are thickened so they don't break up.
+pub fn union_surface(in_solids : &Vec<Solid>, in_spacing : &f32) -> Mesh {
+    let mut spacing = *in_spacing;
+    let points = |in_spacing : &f32| -> f32 {
+        in_solids.iter().flat_map(|solid| solid.boxes(&(2.0*in_spacing))).map(|(low, high)| {
+            (0..3).map(|l| (high[l]-low[l])/in_spacing+1.0).product::<f32>()
+        }).sum()
+    };
+    while points(&spacing) > MAX_GRID_POINTS {
+        spacing *= 1.25;
+    }
+    let solids : Vec<Solid> = in_solids.iter().map(|solid| solid.thickened(&(1.5*spacing))).collect();
+
+    // the distance at grid points within two spacings of a solid; the rest are outside
+    let mut field : HashMap<[i32;3], f32> = HashMap::new();
+    for solid in &solids {
+        for (low, high) in solid.boxes(&(2.0*spacing)) {
+            let from : Vec<i32> = (0..3).map(|l| (low[l]/spacing).floor() as i32).collect();
+            let to : Vec<i32> = (0..3).map(|l| (high[l]/spacing).ceil() as i32).collect();
+            for i in from[0]..to[0]+1 {
+                for j in from[1]..to[1]+1 {
+                    for k in from[2]..to[2]+1 {
+                        let distance = solid.distance(&[i as f32*spacing, j as f32*spacing, k as f32*spacing]);
+                        let value = field.entry([i, j, k]).or_insert(f32::INFINITY);
+                        if distance < *value {*value = distance;}
+                    }
+                }
+            }
+        }
+    }
+    // a value of exactly zero would put a vertex on a grid point, where several edges would share it
+    let value = |in_point : &[i32;3]| -> f32 {
+        match field.get(in_point) {
+            Some(&distance) if distance.abs() < 1e-6*spacing => 1e-6*spacing,
+            Some(&distance) => distance,
+            None => f32::INFINITY,
+        }
+    };
+    let position = |in_point : &[i32;3]| [
+        in_point[0] as f32*spacing,
+        in_point[1] as f32*spacing,
+        in_point[2] as f32*spacing,
+    ];
+
+    // only cubes with a corner near a solid can have surface in them
+    let mut cubes : Vec<[i32;3]> = field.keys()
+        .flat_map(|point| (0..8).map(move |c| [point[0]-(c & 1), point[1]-((c >> 1) & 1), point[2]-((c >> 2) & 1)]))
+        .collect::<HashSet<[i32;3]>>()
+        .into_iter()
+        .collect();
+    cubes.sort();
+
+    let mut mesh = Mesh::new();
+    let mut edge_vertices : HashMap<([i32;3], [i32;3]), u32> = HashMap::new();
+    // Each cube is split into six tetrahedra round its diagonal, the same way in every cube,
+    // so neighbouring tetrahedra meet face to face and the surface has no gaps.
+    let orders = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+    for cube in &cubes {
+        for order in &orders {
+            let mut corners = [*cube;4];
+            for c in 1..4 {
+                corners[c] = corners[c-1];
+                corners[c][order[c-1]] += 1;
+            }
+            let values : Vec<f32> = corners.iter().map(&value).collect();
+            let inside : Vec<usize> = (0..4).filter(|&c| values[c] < 0.0).collect();
+            let outside : Vec<usize> = (0..4).filter(|&c| values[c] >= 0.0).collect();
+            if inside.is_empty() || outside.is_empty() {continue;}
+
+            // where the surface crosses the edge from corner a to corner b
+            let mut crossing = |a : usize, b : usize| -> u32 {
+                let key = if corners[a] < corners[b] {(corners[a], corners[b])} else {(corners[b], corners[a])};
+                if let Some(&index) = edge_vertices.get(&key) {return index;}
+                let t = values[a]/(values[a]-values[b]);
+                let pa = position(&corners[a]);
+                let pb = position(&corners[b]);
+                let index = mesh.add_vertex([
+                    pa[0]+t*(pb[0]-pa[0]),
+                    pa[1]+t*(pb[1]-pa[1]),
+                    pa[2]+t*(pb[2]-pa[2]),
+                ], [0.0;3]);
+                edge_vertices.insert(key, index);
+                index
+            };
+            // each triangle as the edges its corners are on
+            let triangles = match (inside.len(), outside.len()) {
+                (1, _) => vec![[(inside[0], outside[0]), (inside[0], outside[1]), (inside[0], outside[2])]],
+                (_, 1) => vec![[(inside[0], outside[0]), (inside[1], outside[0]), (inside[2], outside[0])]],
+                _ => {
+                    let quad = [
+                        (inside[0], outside[0]),
+                        (inside[0], outside[1]),
+                        (inside[1], outside[1]),
+                        (inside[1], outside[0]),
+                    ];
+                    vec![[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]]
+                },
+            };
+
+            // Triangles face from the inside corners to the outside ones. Which way round they go
+            // is worked out exactly, in whole grid units with each corner at the middle of its edge,
+            // as the real corners can be so close together that rounding would get it wrong.
+            let mut outward = [0i64;3];
+            for l in 0..3 {
+                for &c in &outside {outward[l] += (inside.len() as i64)*(corners[c][l] as i64);}
+                for &c in &inside {outward[l] -= (outside.len() as i64)*(corners[c][l] as i64);}
+            }
+            // (added once crossing() is done with the mesh)
+            let mut new_triangles = Vec::new();
+            for t in triangles {
+                let middle = |edge : &(usize, usize)| -> [i64;3] {
+                    let mut sum = [0i64;3];
+                    for l in 0..3 {sum[l] = corners[edge.0][l] as i64+corners[edge.1][l] as i64;}
+                    sum
+                };
+                let (a, b, c) = (middle(&t[0]), middle(&t[1]), middle(&t[2]));
+                let u = [b[0]-a[0], b[1]-a[1], b[2]-a[2]];
+                let v = [c[0]-a[0], c[1]-a[1], c[2]-a[2]];
+                let normal = [u[1]*v[2]-u[2]*v[1], u[2]*v[0]-u[0]*v[2], u[0]*v[1]-u[1]*v[0]];
+                let facing = normal[0]*outward[0]+normal[1]*outward[1]+normal[2]*outward[2];
+                let t = if facing < 0 {[t[0], t[2], t[1]]} else {t};
+                let triangle = [crossing(t[0].0, t[0].1), crossing(t[1].0, t[1].1), crossing(t[2].0, t[2].1)];
+                new_triangles.push(triangle);
+            }
+            mesh._triangles.extend(new_triangles);
+        }
+    }
+
+    // smooth normals, from the triangles round each vertex weighted by their areas
+    for t in mesh._triangles.clone() {
+        let a = mesh._positions[t[0] as usize];
+        let normal = cross(&sub(&mesh._positions[t[1] as usize], &a), &sub(&mesh._positions[t[2] as usize], &a));
+        for &v in &t {
+            for l in 0..3 {mesh._normals[v as usize][l] += normal[l];}
+        }
+    }
+    for normal in mesh._normals.iter_mut() {
+        if length(normal) > 0.0 {*normal = normalised(normal);}
+    }
+    mesh
+}
+
+// ============================================================
+// Icosphere
+// ============================================================
+/// A unit sphere made by repeatedly subdividing the faces of an icosahedron
+/// and pushing the new vertices out onto the sphere.
+/// Every vertex is shared between its triangles, so the sphere is watertight.
+pub fn icosphere(in_subdivisions : &u32) -> Mesh {
+    let phi = 2.0/(1.0+5.0f32.sqrt()); // as in model::DefaultModels
+    let corners = [
+        [ 0.0,  1.0,  phi], [ 0.0, -1.0,  phi], [ 0.0,  1.0, -phi], [ 0.0, -1.0, -phi],
+        [ phi,  0.0,  1.0], [ phi,  0.0, -1.0], [-phi,  0.0,  1.0], [-phi,  0.0, -1.0],
+        [ 1.0,  phi,  0.0], [-1.0,  phi,  0.0], [ 1.0, -phi,  0.0], [-1.0, -phi,  0.0],
+    ];
+    let faces : [[u32;3];20] = [
+        [0, 8, 2], [0, 2, 9], [1, 3, 10], [1, 11, 3], [4, 0, 6],
+        [4, 6, 1], [5, 7, 2], [5, 3, 7], [8, 4, 10], [8, 10, 5],
+        [9, 11, 6], [9, 7, 11], [0, 4, 8], [0, 9, 6], [1, 10, 4],
+        [1, 6, 11], [2, 8, 5], [2, 7, 9], [3, 5, 10], [3, 11, 7],
+    ];
+
+    let mut mesh = Mesh::new();
+    for corner in &corners {
+        let n = normalised(corner);
+        mesh.add_vertex(n, n);
+    }
+    for face in &faces {
+        mesh._triangles.push(outward_winding(&mesh, face));
+    }
+
+    for _ in 0..*in_subdivisions {
+        let mut midpoints : HashMap<(u32, u32), u32> = HashMap::new();
+        let triangles = mesh._triangles.clone();
+        mesh._triangles.clear();
+        for t in &triangles {
+            let mut m = [0u32;3];
+            for e in 0..3 {
+                let (a, b) = (t[e], t[(e+1)%3]);
+                let key = if a < b {(a, b)} else {(b, a)};
+                m[e] = match midpoints.get(&key) {
+                    Some(&index) => index,
+                    None => {
+                        let pa = mesh._positions[a as usize];
+                        let pb = mesh._positions[b as usize];
+                        let n = normalised(&[pa[0]+pb[0], pa[1]+pb[1], pa[2]+pb[2]]);
+                        let index = mesh.add_vertex(n, n);
+                        midpoints.insert(key, index);
+                        index
+                    }
+                };
+            }
+            mesh._triangles.push([t[0], m[0], m[2]]);
+            mesh._triangles.push([t[1], m[1], m[0]]);
+            mesh._triangles.push([t[2], m[2], m[1]]);
+            mesh._triangles.push([m[0], m[1], m[2]]);
+        }
+    }
+    mesh
+}
+
+/// Flips a triangle on the unit sphere if it is wound the wrong way.
+fn outward_winding(in_mesh : &Mesh, in_triangle : &[u32;3]) -> [u32;3] {
+    let a = in_mesh._positions[in_triangle[0] as usize];
+    let b = in_mesh._positions[in_triangle[1] as usize];
+    let c = in_mesh._positions[in_triangle[2] as usize];
+    if dot(&cross(&sub(&b, &a), &sub(&c, &a)), &a) < 0.0 {
+        [in_triangle[0], in_triangle[2], in_triangle[1]]
+    } else {
+        *in_triangle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether every edge is shared by exactly two triangles, which go along it opposite ways.
+    fn is_closed(in_mesh : &Mesh) -> bool {
+        let mut edges : HashMap<(u32, u32), usize> = HashMap::new();
+        for t in in_mesh.triangles() {
+            for e in 0..3 {
+                *edges.entry((t[e], t[(e+1)%3])).or_insert(0) += 1;
+            }
+        }
+        edges.iter().all(|(&(a, b), &count)| count == 1 && edges.get(&(b, a)) == Some(&1))
+    }
+
+    fn volume(in_mesh : &Mesh) -> f32 {
+        in_mesh.triangle_corners().iter().map(|c| dot(&c[0], &cross(&c[1], &c[2]))/6.0).sum()
+    }
+
+    #[test]
+    fn sphere_volume() {
+        let mesh = union_surface(&vec![Solid::Sphere {centre : [0.1, 0.2, 0.3], radius : 1.0}], &0.1);
+        assert!(is_closed(&mesh));
+        assert!((volume(&mesh)-4.0/3.0*f32::consts::PI).abs() < 0.05);
+    }
+
+    #[test]
+    fn ball_and_stick_union_is_closed() {
+        let corners = vec![
+            [2.0, 0.0, 0.0], [-2.0, 0.0, 0.0], [0.0, 2.0, 0.0],
+            [0.0, -2.0, 0.0], [0.0, 0.0, 2.0], [0.0, 0.0, -2.0],
+        ];
+        let mut solids = vec![Solid::Sphere {centre : [0.0;3], radius : 0.5}];
+        for corner in &corners {
+            solids.push(Solid::Sphere {centre : *corner, radius : 0.4});
+            solids.push(Solid::Cylinder {start : [0.0;3], end : *corner, radius : 0.1});
+        }
+        solids.push(Solid::polyhedron(&corners).unwrap());
+        solids.push(Solid::Cylinder {start : [2.0, 0.0, 0.0], end : [3.0, 1.0, 1.0], radius : 0.01});
+        let mesh = union_surface(&solids, &0.1);
+        assert!(!mesh.is_empty());
+        assert!(is_closed(&mesh));
+        assert!(volume(&mesh) > 0.0);
+    }
+
+    #[test]
+    fn hull_with_point_in_middle_of_face() {
+        // the first corner of the bottom face is its centre, so its angles are NaN
+        let mut mesh = Mesh::new();
+        let points = vec![
+            [0.0, 0.0, 0.0], [-1.0, -1.0, 0.0], [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [0.0, 0.0, 1.0],
+        ];
+        mesh.add_convex_hull(&points);
+        assert!(!mesh.is_empty());
+    }
+}
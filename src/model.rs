@@ -1,6 +1,7 @@
 extern crate glium;
 
 use vertex::Vertex;
+use mesh::Mesh;
 use program;
 
 // ============================================================
@@ -52,6 +53,7 @@ pub struct DefaultModels<'a> {
     _cube        : Model<'a>,
     _icosahedron : Model<'a>,
     _sphere      : Model<'a>,
+    _cylinder    : Model<'a>,
 }
 
 impl<'a> DefaultModels<'a> {
@@ -61,6 +63,8 @@ impl<'a> DefaultModels<'a> {
     ) -> DefaultModels<'a> {
         let sr_1_2 = 1.0/2.0f32.sqrt();    // for tetrahedron
         let phi = 2.0/(1.0+5.0f32.sqrt()); // for icosahedron
+        let mut cylinder = Mesh::new();    // for cylinder
+        cylinder.add_cylinder(&[0.0, 0.0, 0.0], &[0.0, 0.0, 1.0], &1.0, &16, &true);
 
         DefaultModels {
             // ==============================
@@ -200,6 +204,22 @@ impl<'a> DefaultModels<'a> {
                 &vec![0, 2, 1, 3u16],
                 in_default_programs.sphere(),
             ),
+
+            // ==============================
+            // cylinder
+            // ==============================
+            // radius 1, from z=0 to z=1, with caps; used for bonds.
+            _cylinder : Model::new(
+                in_display,
+                &cylinder.positions().iter().zip(cylinder.normals())
+                    .map(|(position, normal)| Vertex::new(*position, *normal))
+                    .collect(),
+                &glium::index::PrimitiveType::TrianglesList,
+                &cylinder.triangles().iter()
+                    .flat_map(|triangle| triangle.iter().map(|&index| index as u16))
+                    .collect(),
+                in_default_programs.polyhedron(),
+            ),
         }
     }
 
@@ -215,4 +235,5 @@ impl<'a> DefaultModels<'a> {
     pub fn icosahedron(&self) -> &Model {&self._icosahedron}
     #[allow(dead_code)]
     pub fn sphere(&self) -> &Model {&self._sphere}
+    pub fn cylinder(&self) -> &Model {&self._cylinder}
 }
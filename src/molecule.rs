@@ -1,6 +1,15 @@
 use species::Species;
 use atom::Atom;
 use camera::Camera;
+use lattice::Lattice;
+use representation::Representation;
+use selection::{AtomInfo, Selection};
+use plane::Plane;
+use trajectory::Frames;
+
+use std::f32;
+
+use vector::{sub, dot, length};
 
 // ============================================================
 // Molecule
@@ -8,24 +17,542 @@ use camera::Camera;
 // Will likely be the top level struct, unless we need something which has an OpenGL thing + this
 /// The molecule. May also be a cluster, crystal motif,...
 pub struct Molecule<'a> {
-    _atoms : Vec<Atom<'a>>,
+    _atoms          : Vec<Atom<'a>>,
+    /// The unit cell, if the molecule is a crystal.
+    _lattice        : Option<Lattice>,
+    /// How atoms are drawn, unless they say otherwise.
+    _representation : Representation,
+    /// The radius of ball-and-stick bonds, set by the smallest atom.
+    _stick_radius   : f32,
+    /// Species whose atoms are hidden.
+    _hidden_species : Vec<String>,
+    /// Atoms beyond any of these planes are hidden.
+    _clip_planes    : Vec<Plane>,
+    /// If set, only atoms within the distance of one of these atoms are shown.
+    _focus          : Option<(Vec<usize>, f32)>,
+    /// Whether each atom is shown, after all of the above.
+    _visibility     : Vec<bool>,
+    /// Snapshots of the atoms moving, if the file held any.
+    _frames         : Frames,
+    /// Which of the frames the atoms are in.
+    _frame          : usize,
+    /// The total energy of that frame, in eV, if the file gave one.
+    _energy         : Option<f32>,
+    /// The properties that frame gave the atoms, e.g. "charge", besides force and speed.
+    _per_frame      : Vec<String>,
+    /// The lines of the .cell file it was read from besides the cell and positions,
+    /// e.g. kpoints and constraints, to write back out with them.
+    _cell_extras    : Vec<String>,
 }
 
 impl<'a> Molecule<'a> {
-    pub fn new() -> Molecule<'a> {Molecule{_atoms : Vec::new()}}
+    pub fn new() -> Molecule<'a> {
+        Molecule {
+            _atoms          : Vec::new(),
+            _lattice        : None,
+            _representation : Representation::BallAndStick,
+            _stick_radius   : 0.1,
+            _hidden_species : Vec::new(),
+            _clip_planes    : Vec::new(),
+            _focus          : None,
+            _visibility     : Vec::new(),
+            _frames         : Frames::InMemory(Vec::new()),
+            _frame          : 0,
+            _energy         : None,
+            _per_frame      : Vec::new(),
+            _cell_extras    : Vec::new(),
+        }
+    }
 
     pub fn add_atom(
         &mut self,
         in_species  : &'a Species,
         in_position : &[f32;3],
-    ) {self._atoms.push(Atom::new(in_species, in_position))}
+    ) {self.push_atom(Atom::new(in_species, in_position))}
+
+    fn push_atom(&mut self, in_atom : Atom<'a>) {
+        let stick_radius = 0.4*in_atom.species().size();
+        if self._atoms.is_empty() || stick_radius < self._stick_radius {
+            self._stick_radius = stick_radius;
+        }
+        // species hidden in the config start off hidden
+        let name = in_atom.species().name().to_string();
+        if !in_atom.species().visible() && !self._hidden_species.contains(&name) {
+            self._hidden_species.push(name);
+        }
+        self._atoms.push(in_atom);
+        let visible = self.is_shown(&(self._atoms.len()-1));
+        self._visibility.push(visible);
+    }
 
     pub fn atoms(&self) -> &Vec<Atom> {&self._atoms}
+    pub fn atoms_mut(&mut self) -> &mut Vec<Atom<'a>> {&mut self._atoms}
+    pub fn lattice(&self) -> Option<&Lattice> {self._lattice.as_ref()}
+    pub fn set_lattice(&mut self, in_lattice : &Lattice) {self._lattice = Some(*in_lattice)}
+    pub fn cell_extras(&self) -> &Vec<String> {&self._cell_extras}
+    pub fn set_cell_extras(&mut self, in_extras : Vec<String>) {self._cell_extras = in_extras}
+    pub fn representation(&self) -> Representation {self._representation}
+    pub fn set_representation(&mut self, in_representation : &Representation) {
+        self._representation = *in_representation
+    }
+    pub fn stick_radius(&self) -> &f32 {&self._stick_radius}
 
-    pub fn rotate_atoms_against_camera(&mut self, in_camera : &Camera) {
+    /// How atom in_index is drawn: its own representation, or else the molecule's.
+    pub fn atom_representation(&self, in_index : &usize) -> Representation {
+        self._atoms[*in_index].representation().unwrap_or(self._representation)
+    }
+
+    /// The radius atom in_index is drawn and picked with.
+    pub fn atom_radius(&self, in_index : &usize) -> f32 {
+        self.atom_representation(in_index).atom_radius(self._atoms[*in_index].species(), &self._stick_radius)
+    }
+
+    /// The chemical formula in Hill order (C, then H, then alphabetical), e.g. "C2 H6 O".
+    pub fn formula(&self) -> String {
+        let mut counts : Vec<(String, usize)> = Vec::new();
+        for atom in &self._atoms {
+            let name = atom.species().name();
+            match counts.iter_mut().find(|count| count.0 == name) {
+                Some(count) => count.1 += 1,
+                None => counts.push((name.to_string(), 1)),
+            }
+        }
+        let has_carbon = counts.iter().any(|count| count.0 == "C");
+        let rank = |name : &str| -> u8 {
+            match name {
+                "C" if has_carbon => 0,
+                "H" if has_carbon => 1,
+                _ => 2,
+            }
+        };
+        counts.sort_by(|a, b| (rank(&a.0), &a.0).cmp(&(rank(&b.0), &b.0)));
+        counts.iter()
+            .map(|&(ref name, count)| if count == 1 {name.clone()} else {format!("{}{}", name, count)})
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// The centre and radius of a sphere containing every atom, including the radius each is
+    /// drawn with. The centre is the middle of the bounding box, which is close enough for framing.
+    pub fn bounding_sphere(&self) -> ([f32;3], f32) {
+        if self._atoms.is_empty() {return ([0.0;3], 1.0);}
+        let mut min = [f32::INFINITY;3];
+        let mut max = [f32::NEG_INFINITY;3];
+        for atom in &self._atoms {
+            for l in 0..3 {
+                min[l] = min[l].min(atom.position()[l]);
+                max[l] = max[l].max(atom.position()[l]);
+            }
+        }
+        let centre = [(min[0]+max[0])/2.0, (min[1]+max[1])/2.0, (min[2]+max[2])/2.0];
+        let mut radius = 0.0f32;
+        for (i, atom) in self._atoms.iter().enumerate() {
+            radius = radius.max(length(&sub(&centre, atom.position()))+self.atom_radius(&i));
+        }
+        (centre, radius)
+    }
+
+    /// The shortest distance between any two atoms, or None if there are fewer than two.
+    pub fn nearest_neighbour_distance(&self) -> Option<f32> {
+        let mut nearest : Option<f32> = None;
+        for (i, atom) in self._atoms.iter().enumerate() {
+            for other in &self._atoms[i+1..] {
+                let distance = length(&sub(atom.position(), other.position()));
+                if nearest.map_or(true, |d| distance < d) {
+                    nearest = Some(distance);
+                }
+            }
+        }
+        nearest
+    }
+
+    /// A bond is any pair of atoms closer together than the cutoff.
+    /// Returns the indices of the bonded atoms, lowest first.
+    /// These are the bonds drawn, so none reach across a periodic cell's faces.
+    pub fn bonds(&self, in_cutoff : &f32) -> Vec<(usize, usize)> {
+        let mut bonds = Vec::new();
+        for (i, atom) in self._atoms.iter().enumerate() {
+            for (j, other) in self._atoms.iter().enumerate().skip(i+1) {
+                if length(&sub(atom.position(), other.position())) < *in_cutoff {
+                    bonds.push((i, j));
+                }
+            }
+        }
+        bonds
+    }
+
+    /// A bond cutoff that catches nearest neighbours and a little slack.
+    pub fn default_bond_cutoff(&self) -> f32 {
+        self.nearest_neighbour_distance().unwrap_or(1.0)*1.15
+    }
+
+    // ==============================
+    // Trajectory
+    // ==============================
+    /// How many frames there are; a single structure is one frame.
+    pub fn frame_count(&self) -> usize {self._frames.len().max(1)}
+
+    /// Which frame the atoms are in, counting from 0.
+    pub fn frame(&self) -> &usize {&self._frame}
+
+    /// Replaces the trajectory, moving the atoms to its first frame.
+    pub fn set_frames(&mut self, in_frames : Frames) -> Result<(), String> {
+        self._frames = in_frames;
+        self.set_frame(&0)
+    }
+
+    /// The total energy of the current frame in eV, if the file gave one.
+    pub fn energy(&self) -> Option<f32> {self._energy}
+
+    /// Moves the atoms, and the cell if the frame has one, to frame in_frame.
+    /// Forces and velocities become the atoms' "force" and "speed" properties,
+    /// alongside any others the frame has; a frame without them takes them away,
+    /// as it does any property the last frame gave that this one doesn't (e.g. charges
+    /// only known for the last step).
+    /// Fails if a frame on disk can't be read.
+    pub fn set_frame(&mut self, in_frame : &usize) -> Result<(), String> {
+        if *in_frame >= self._frames.len() {return Ok(());}
+        let frame = self._frames.get(in_frame)?;
+        if frame.positions().len() != self._atoms.len() {
+            return Err(format!(
+                "Frame {} has {} atoms, not {}",
+                in_frame+1, frame.positions().len(), self._atoms.len(),
+            ));
+        }
+        self._frame = *in_frame;
+        self._energy = frame.energy();
+        let names : Vec<String> = frame.properties().iter().map(|property| property.0.clone()).collect();
+        for (i, atom) in self._atoms.iter_mut().enumerate() {
+            for name in self._per_frame.iter().filter(|name| !names.contains(name)) {
+                atom.remove_property(name);
+            }
+            atom.set_position(&frame.positions()[i]);
+            match frame.forces() {
+                Some(forces) => atom.set_property("force", &length(&forces[i])),
+                None => atom.remove_property("force"),
+            }
+            match frame.velocities() {
+                Some(velocities) => atom.set_property("speed", &length(&velocities[i])),
+                None => atom.remove_property("speed"),
+            }
+            for &(ref name, ref values) in frame.properties() {
+                atom.set_property(name, &values[i]);
+            }
+        }
+        self._per_frame = names;
+        if let Some(lattice) = frame.lattice() {
+            self._lattice = Some(*lattice);
+        }
+        // clipping and focus depend on where the atoms are
+        self.update_visibility();
+        Ok(())
+    }
+
+    // ==============================
+    // Visibility
+    // ==============================
+    /// Whether atom in_index is drawn, picked and exported.
+    pub fn visible(&self, in_index : &usize) -> bool {self._visibility[*in_index]}
+
+    pub fn visible_mask(&self) -> &Vec<bool> {&self._visibility}
+
+    /// How many atoms are hidden, for whatever reason.
+    pub fn hidden_count(&self) -> usize {self._visibility.iter().filter(|&&x| !x).count()}
+
+    fn is_shown(&self, in_index : &usize) -> bool {
+        let atom = &self._atoms[*in_index];
+        if !atom.visible() {return false;}
+        if self._hidden_species.iter().any(|name| name == atom.species().name()) {return false;}
+        if self._clip_planes.iter().any(|plane| plane.cuts(atom.position())) {return false;}
+        match self._focus {
+            Some((ref centres, radius)) if !centres.is_empty() => centres.iter()
+                .any(|j| j == in_index || length(&self.separation(j, in_index)) <= radius),
+            _ => true,
+        }
+    }
+
+    fn update_visibility(&mut self) {
+        self._visibility = (0..self._atoms.len()).map(|i| self.is_shown(&i)).collect();
+    }
+
+    /// The species in the order they first appear, for toggling with number keys.
+    pub fn species_names(&self) -> Vec<String> {
+        let mut names : Vec<String> = Vec::new();
+        for atom in &self._atoms {
+            if !names.iter().any(|name| name == atom.species().name()) {
+                names.push(atom.species().name().to_string());
+            }
+        }
+        names
+    }
+
+    /// Hides a species if it is shown, and shows it if it is hidden.
+    /// Returns whether it is now shown.
+    pub fn toggle_species(&mut self, in_name : &str) -> bool {
+        let shown = match self._hidden_species.iter().position(|name| name == in_name) {
+            Some(position) => {
+                self._hidden_species.remove(position);
+                true
+            },
+            None => {
+                self._hidden_species.push(in_name.to_string());
+                false
+            },
+        };
+        self.update_visibility();
+        shown
+    }
+
+    /// Shows or hides the atoms in in_mask, leaving the rest alone.
+    pub fn set_atoms_visible(&mut self, in_mask : &[bool], in_visible : &bool) {
+        for (atom, &x) in self._atoms.iter_mut().zip(in_mask) {
+            if x {atom.set_visible(in_visible);}
+        }
+        self.update_visibility();
+    }
+
+    /// Shows everything: every atom and species, without clipping or focus.
+    pub fn show_all(&mut self) {
         for atom in &mut self._atoms {
-            atom.rotate_against_camera(in_camera);
+            atom.set_visible(&true);
         }
+        self._hidden_species.clear();
+        self._clip_planes.clear();
+        self._focus = None;
+        self.update_visibility();
+    }
+
+    pub fn add_clip_plane(&mut self, in_plane : &Plane) {
+        self._clip_planes.push(*in_plane);
+        self.update_visibility();
+    }
+
+    pub fn clear_clip_planes(&mut self) {
+        self._clip_planes.clear();
+        self.update_visibility();
+    }
+
+    pub fn focus(&self) -> &Option<(Vec<usize>, f32)> {&self._focus}
+
+    /// Shows only the atoms within a distance of the given atoms, or everything if None.
+    pub fn set_focus(&mut self, in_focus : &Option<(Vec<usize>, f32)>) {
+        self._focus = in_focus.clone();
+        self.update_visibility();
+    }
+
+    // ==============================
+    // Per-atom properties
+    // ==============================
+    /// Properties that can be worked out for any structure.
+    fn derived_property_names(&self) -> Vec<String> {
+        let mut names = vec!["coordination", "x", "y", "z"];
+        if self._lattice.is_some() {
+            names.extend_from_slice(&["frac_a", "frac_b", "frac_c"]);
+        }
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    /// The names of the numbers that atoms have, worked out or from the file.
+    pub fn property_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for atom in &self._atoms {
+            for &(ref name, _) in atom.properties() {
+                if !names.contains(name) {names.push(name.clone());}
+            }
+        }
+        names.extend(self.derived_property_names());
+        names
+    }
+
+    /// The names of the groups that atoms belong to, worked out or from the file.
+    pub fn category_names(&self) -> Vec<String> {
+        let mut names = vec!["element".to_string(), "fragment".to_string()];
+        for atom in &self._atoms {
+            for &(ref name, _) in atom.categories() {
+                if !names.contains(name) {names.push(name.clone());}
+            }
+        }
+        names
     }
-}
 
+    /// Each atom's value of a property, or None if no atom has it.
+    /// Atoms without the property have None.
+    pub fn property_values(&self, in_name : &str) -> Option<Vec<Option<f32>>> {
+        let values : Vec<Option<f32>> = match in_name {
+            "coordination" => {
+                let cutoff = self.default_bond_cutoff();
+                (0..self._atoms.len()).map(|i| Some(self.coordination(&i, &cutoff) as f32)).collect()
+            },
+            "x" | "y" | "z" => {
+                let l = match in_name {"x" => 0, "y" => 1, _ => 2};
+                self._atoms.iter().map(|atom| Some(atom.position()[l])).collect()
+            },
+            "frac_a" | "frac_b" | "frac_c" => {
+                let l = match in_name {"frac_a" => 0, "frac_b" => 1, _ => 2};
+                let lattice = self._lattice?;
+                self._atoms.iter().map(|atom| Some(lattice.to_fractional(atom.position())[l])).collect()
+            },
+            _ => self._atoms.iter().map(|atom| atom.property(in_name)).collect(),
+        };
+        if values.iter().any(|value| value.is_some()) {Some(values)} else {None}
+    }
+
+    /// Each atom's group, or None if no atom has one.
+    pub fn category_values(&self, in_name : &str) -> Option<Vec<Option<String>>> {
+        let values : Vec<Option<String>> = match in_name {
+            "element" => self._atoms.iter().map(|atom| Some(atom.species().name().to_string())).collect(),
+            "fragment" => self.fragments(&self.default_bond_cutoff()).iter().map(|fragment| Some(format!("{}", fragment+1))).collect(),
+            _ => self._atoms.iter().map(|atom| atom.category(in_name).map(|value| value.to_string())).collect(),
+        };
+        if values.iter().any(|value| value.is_some()) {Some(values)} else {None}
+    }
+
+    /// Numbers each atom by the bonded fragment it is in, counting from 0
+    /// in the order the fragments first appear, bonding atoms closer than in_cutoff.
+    /// In a periodic cell, atoms bond across its faces, so a molecule the cell
+    /// boundary cuts through is still one fragment.
+    pub fn fragments(&self, in_cutoff : &f32) -> Vec<usize> {
+        // union-find, pointing each atom towards the first atom of its fragment
+        let mut root : Vec<usize> = (0..self._atoms.len()).collect();
+        fn find(in_root : &mut [usize], in_i : usize) -> usize {
+            let mut i = in_i;
+            while in_root[i] != i {
+                in_root[i] = in_root[in_root[i]];
+                i = in_root[i];
+            }
+            i
+        }
+        for i in 0..self._atoms.len() {
+            for j in i+1..self._atoms.len() {
+                if length(&self.separation(&i, &j)) < *in_cutoff {
+                    let a = find(&mut root, i);
+                    let b = find(&mut root, j);
+                    root[a.max(b)] = a.min(b);
+                }
+            }
+        }
+        let mut numbers : Vec<usize> = Vec::new();
+        let mut fragments = Vec::new();
+        for i in 0..self._atoms.len() {
+            let r = find(&mut root, i);
+            let number = match numbers.iter().position(|&n| n == r) {
+                Some(number) => number,
+                None => {
+                    numbers.push(r);
+                    numbers.len()-1
+                },
+            };
+            fragments.push(number);
+        }
+        fragments
+    }
+
+    // ==============================
+    // Selections
+    // ==============================
+    /// What the selection language can ask about each atom. Coordination and fragments
+    /// are costly in big structures, so are only worked out if in_selection asks about them.
+    pub fn selection_info(&self, in_selection : &Selection) -> Vec<AtomInfo> {
+        let mut infos : Vec<AtomInfo> = self._atoms.iter().map(|atom| AtomInfo {
+            element    : atom.species().name().to_string(),
+            position   : *atom.position(),
+            fractional : self._lattice.map(|lattice| lattice.to_fractional(atom.position())),
+            properties : atom.properties().clone(),
+            categories : atom.categories().clone(),
+        }).collect();
+        let coordination = in_selection.mentions("coordination");
+        let fragment = in_selection.mentions("fragment");
+        if !coordination && !fragment {
+            return infos;
+        }
+        let cutoff = self.default_bond_cutoff();
+        if coordination {
+            for (i, info) in infos.iter_mut().enumerate() {
+                info.properties.push(("coordination".to_string(), self.coordination(&i, &cutoff) as f32));
+            }
+        }
+        if fragment {
+            for (info, fragment) in infos.iter_mut().zip(self.fragments(&cutoff)) {
+                info.categories.push(("fragment".to_string(), format!("{}", fragment+1)));
+            }
+        }
+        infos
+    }
+
+    /// Whether each atom is in the selection. Distances go through the cell walls.
+    pub fn select(&self, in_selection : &Selection) -> Vec<bool> {
+        let distance = |i : &usize, j : &usize| length(&self.separation(i, j));
+        in_selection.evaluate(&self.selection_info(in_selection), &distance)
+    }
+
+    /// A copy of the molecule with just the atoms in in_mask.
+    pub fn subset(&self, in_mask : &[bool]) -> Molecule<'a> {
+        let mut molecule = Molecule::new();
+        molecule._lattice = self._lattice;
+        molecule._representation = self._representation;
+        // atom numbers change, so the focus can't be kept
+        molecule._hidden_species = self._hidden_species.clone();
+        molecule._clip_planes = self._clip_planes.clone();
+        for (atom, &keep) in self._atoms.iter().zip(in_mask) {
+            if keep {
+                molecule.push_atom(atom.clone());
+            }
+        }
+        molecule._frames = self._frames.subset(in_mask);
+        molecule._frame = self._frame;
+        molecule._energy = self._energy;
+        molecule._per_frame = self._per_frame.clone();
+        molecule._cell_extras = self._cell_extras.clone();
+        molecule
+    }
+
+    /// The vector from atom in_i to atom in_j, through the cell walls if that is shorter.
+    pub fn separation(&self, in_i : &usize, in_j : &usize) -> [f32;3] {
+        let displacement = sub(self._atoms[*in_j].position(), self._atoms[*in_i].position());
+        match self._lattice {
+            Some(ref lattice) => lattice.minimum_image(&displacement),
+            None => displacement,
+        }
+    }
+
+    /// The number of atoms within in_cutoff of atom in_index.
+    pub fn coordination(&self, in_index : &usize, in_cutoff : &f32) -> usize {
+        (0..self._atoms.len())
+            .filter(|j| j != in_index && length(&self.separation(in_index, j)) < *in_cutoff)
+            .count()
+    }
+
+    /// The nearest visible atom hit by a ray, treating atoms as spheres of their drawn radius
+    /// and ignoring any part of them sliced away by in_slices.
+    pub fn pick(&self, in_origin : &[f32;3], in_direction : &[f32;3], in_slices : &[Plane]) -> Option<usize> {
+        let mut nearest : Option<(usize, f32)> = None;
+        for (i, atom) in self._atoms.iter().enumerate() {
+            if !self.visible(&i) {continue;}
+            // solve |origin + t direction - position|^2 = size^2 for the smaller t
+            let offset = sub(in_origin, atom.position());
+            let b = dot(&offset, in_direction);
+            let c = dot(&offset, &offset)-self.atom_radius(&i).powi(2);
+            let discriminant = b*b-c;
+            if discriminant < 0.0 {continue;}
+            let t = -b-discriminant.sqrt();
+            if t < 0.0 {continue;}
+            let hit = [
+                in_origin[0]+t*in_direction[0],
+                in_origin[1]+t*in_direction[1],
+                in_origin[2]+t*in_direction[2],
+            ];
+            if in_slices.iter().any(|plane| plane.cuts(&hit)) {continue;}
+            if nearest.map_or(true, |(_, nearest_t)| t < nearest_t) {
+                nearest = Some((i, t));
+            }
+        }
+        nearest.map(|(i, _)| i)
+    }
+
+    pub fn rotate_atoms_against_camera(&mut self, in_camera : &Camera) {
+        for i in 0..self._atoms.len() {
+            let radius = self.atom_radius(&i);
+            self._atoms[i].rotate_against_camera(in_camera, &radius);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+extern crate glium;
+
+use glium::glutin::{ElementState, MouseButton, MouseScrollDelta};
+use std::time::Instant;
+
+use camera::Camera;
+use quaternion::Quaternion;
+
+// ============================================================
+// Mouse controls
+// ============================================================
+/// Turns mouse events into camera movements.
+/// Left-drag rotates with an arcball, right- or middle-drag pans,
+/// and the scroll wheel zooms in proportion to the current distance.
+/// A left click without dragging is passed back so that atoms can be picked.
+/// With inertia on, the structure keeps spinning after a flick and slows down.
+pub struct MouseControls {
+    _position         : [i32;2],
+    /// Where the left button went down, to tell a click from a drag.
+    _press_position   : [i32;2],
+    _rotating         : bool,
+    _panning          : bool,
+    _inertia          : bool,
+    /// Angular velocity as an axis (view space) and speed in radians per second.
+    _spin_axis        : [f32;3],
+    _spin_speed       : f32,
+    _last_drag        : Instant,
+    _last_tick        : Instant,
+}
+
+impl MouseControls {
+    pub fn new() -> MouseControls {
+        MouseControls {
+            _position   : [0, 0],
+            _press_position : [0, 0],
+            _rotating   : false,
+            _panning    : false,
+            _inertia    : true,
+            _spin_axis  : [0.0, 1.0, 0.0],
+            _spin_speed : 0.0,
+            _last_drag  : Instant::now(),
+            _last_tick  : Instant::now(),
+        }
+    }
+
+    pub fn inertia(&self) -> bool {self._inertia}
+    pub fn toggle_inertia(&mut self) {
+        self._inertia = !self._inertia;
+        self._spin_speed = 0.0;
+    }
+
+    /// Returns the pixel that was clicked if the left button was released
+    /// without the mouse moving far, i.e. a click rather than a drag.
+    pub fn mouse_input(&mut self, in_state : &ElementState, in_button : &MouseButton) -> Option<[i32;2]> {
+        let pressed = *in_state == ElementState::Pressed;
+        match *in_button {
+            MouseButton::Left => {
+                self._rotating = pressed;
+                if pressed {
+                    self._spin_speed = 0.0;
+                    self._press_position = self._position;
+                } else {
+                    if self._last_drag.elapsed().as_secs_f32() > 0.05 {
+                        // the mouse stopped before it was released, so no flick
+                        self._spin_speed = 0.0;
+                    }
+                    let dx = self._position[0]-self._press_position[0];
+                    let dy = self._position[1]-self._press_position[1];
+                    if dx*dx+dy*dy <= 9 {
+                        self._spin_speed = 0.0;
+                        return Some(self._position);
+                    }
+                }
+            },
+            MouseButton::Right | MouseButton::Middle => self._panning = pressed,
+            _ => {},
+        }
+        None
+    }
+
+    pub fn mouse_moved(&mut self, in_camera : &mut Camera, in_x : &i32, in_y : &i32) {
+        let previous = self._position;
+        self._position = [*in_x, *in_y];
+        if self._rotating {
+            let from = arcball_point(in_camera.screen_size(), &previous);
+            let to = arcball_point(in_camera.screen_size(), &self._position);
+            let rotation = Quaternion::between(&from, &to);
+            in_camera.rotate(&rotation);
+
+            // remember how fast we were going, in case the button is let go mid-flick
+            let seconds = self._last_drag.elapsed().as_secs_f32().max(1.0e-3);
+            self._last_drag = Instant::now();
+            let (axis, angle) = rotation.axis_angle();
+            self._spin_axis = axis;
+            self._spin_speed = angle/seconds;
+        } else if self._panning {
+            in_camera.pan(
+                &((self._position[0]-previous[0]) as f32),
+                &((self._position[1]-previous[1]) as f32),
+            );
+        }
+    }
+
+    pub fn mouse_wheel(&mut self, in_camera : &mut Camera, in_delta : &MouseScrollDelta) {
+        // positive deltas scroll away from the user, which zooms in
+        let lines = match *in_delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(_, y) => y/20.0,
+        };
+        in_camera.zoom_by(&0.9f32.powf(lines));
+    }
+
+    /// Keeps the structure spinning after a flick. Call once a frame.
+    pub fn tick(&mut self, in_camera : &mut Camera) {
+        let seconds = self._last_tick.elapsed().as_secs_f32();
+        self._last_tick = Instant::now();
+        if in_camera.is_animating() {
+            // an animated move (e.g. a reset) takes over from a flick
+            self._spin_speed = 0.0;
+        }
+        if !self._inertia || self._rotating || self._spin_speed == 0.0 {return;}
+
+        in_camera.rotate(&Quaternion::from_axis_angle(&self._spin_axis, &(self._spin_speed*seconds)));
+
+        // decay with a time constant of half a second, then stop
+        self._spin_speed *= (-seconds/0.5).exp();
+        if self._spin_speed < 0.05 {
+            self._spin_speed = 0.0;
+        }
+    }
+}
+
+/// Maps a pixel onto a unit sphere filling the shorter side of the screen,
+/// in view space. Points outside the sphere are pulled onto its rim.
+fn arcball_point(in_screen_size : &[u32;2], in_pixel : &[i32;2]) -> [f32;3] {
+    let w = in_screen_size[0] as f32;
+    let h = in_screen_size[1] as f32;
+    let m = w.min(h).max(1.0);
+    let x = (2.0*(in_pixel[0] as f32)-w)/m;
+    let y = (h-2.0*(in_pixel[1] as f32))/m;
+    let d2 = x*x+y*y;
+    if d2 <= 1.0 {
+        // the camera looks along +z, so the near side of the ball has negative z
+        [x, y, -(1.0-d2).sqrt()]
+    } else {
+        let d = d2.sqrt();
+        [x/d, y/d, 0.0]
+    }
+}
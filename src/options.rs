@@ -0,0 +1,65 @@
+use std::env;
+use std::path::PathBuf;
+
+// ============================================================
+// Command-line options
+// ============================================================
+/// Usage: oxide [FILE] [--view NAME] [--views FILE] [--keys FILE] [--types A,B,...]
+pub struct Options {
+    /// The structure to load. Without one, a dummy molecule is shown.
+    pub file       : Option<String>,
+    /// A saved view to start from.
+    pub view       : Option<String>,
+    /// Where saved views are kept.
+    pub views_file : String,
+    /// Key binding overrides, by default keys.conf in the config directory.
+    pub keys_file  : Option<PathBuf>,
+    /// The elements of LAMMPS atom types 1, 2, ..., e.g. --types C,H,O.
+    pub types      : Vec<String>,
+}
+
+pub const USAGE : &'static str = "Usage: oxide [FILE] [--view NAME] [--views FILE] [--keys FILE] [--types A,B,...]";
+
+impl Options {
+    /// Reads the options from the command-line arguments (excluding the program name).
+    pub fn parse(in_args : &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            file       : None,
+            view       : None,
+            views_file : "oxide_views.txt".to_string(),
+            keys_file  : config_dir().map(|dir| dir.join("keys.conf")),
+            types      : Vec::new(),
+        };
+        let mut args = in_args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--view" => options.view = Some(value_of(arg, args.next())?),
+                "--views" => options.views_file = value_of(arg, args.next())?,
+                "--keys" => options.keys_file = Some(PathBuf::from(value_of(arg, args.next())?)),
+                "--types" => options.types = value_of(arg, args.next())?
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .collect(),
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ if options.file.is_none() => options.file = Some(arg.clone()),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Where user settings live: $XDG_CONFIG_HOME/oxide, or ~/.config/oxide.
+pub fn config_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => Some(PathBuf::from(dir).join("oxide")),
+        None => env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("oxide")),
+    }
+}
+
+fn value_of(in_flag : &str, in_value : Option<&String>) -> Result<String, String> {
+    match in_value {
+        Some(value) => Ok(value.clone()),
+        None => Err(format!("{} needs a value", in_flag)),
+    }
+}
@@ -0,0 +1,67 @@
+use lattice::Lattice;
+use matrix::Matrix;
+use vector::{dot, length, normalised, scale};
+
+// ============================================================
+// Plane
+// ============================================================
+/// A plane through the structure, for cutting it. Whatever is on the side
+/// the normal points to is cut away.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Plane {
+    /// A unit vector.
+    _normal : [f32;3],
+    /// A point on the plane.
+    _point  : [f32;3],
+}
+
+impl Plane {
+    /// The plane with unit normal along in_normal, in_offset Å from the Cartesian origin.
+    pub fn from_normal(in_normal : &[f32;3], in_offset : &f32) -> Plane {
+        let normal = normalised(in_normal);
+        Plane {
+            _normal : normal,
+            _point  : scale(&normal, in_offset),
+        }
+    }
+
+    /// The lattice plane (hkl) where h x_a + k x_b + l x_c = in_offset in fractional coordinates,
+    /// so an offset of 1 is the next plane of the family.
+    pub fn from_hkl(in_lattice : &Lattice, in_hkl : &[f32;3], in_offset : &f32) -> Plane {
+        let normal = in_lattice.plane_normal(in_hkl);
+        // |normal| is 1/d, the reciprocal of the spacing between planes
+        let spacing = 1.0/length(&normal);
+        let along = scale(&normalised(&normal), &(in_offset*spacing));
+        let origin = in_lattice.origin();
+        Plane {
+            _normal : normalised(&normal),
+            _point  : [origin[0]+along[0], origin[1]+along[1], origin[2]+along[2]],
+        }
+    }
+
+    pub fn normal(&self) -> &[f32;3] {&self._normal}
+
+    /// The same plane, moved in_distance Å along its normal.
+    pub fn moved(&self, in_distance : &f32) -> Plane {
+        let along = scale(&self._normal, in_distance);
+        Plane {
+            _normal : self._normal,
+            _point  : [self._point[0]+along[0], self._point[1]+along[1], self._point[2]+along[2]],
+        }
+    }
+
+    /// The plane as (n, d) in view space, where n.x+d > 0 beyond it, for the shaders.
+    pub fn in_view(&self, in_view_matrix : &Matrix) -> [f32;4] {
+        let n = *in_view_matrix * [self._normal[0], self._normal[1], self._normal[2], 0.0];
+        let p = *in_view_matrix * [self._point[0], self._point[1], self._point[2], 1.0];
+        [n[0], n[1], n[2], -(n[0]*p[0]+n[1]*p[1]+n[2]*p[2])]
+    }
+
+    /// How far in_point is beyond the plane, in Å; negative if it is on the kept side.
+    pub fn distance(&self, in_point : &[f32;3]) -> f32 {
+        dot(&self._normal, in_point)-dot(&self._normal, &self._point)
+    }
+
+    /// Whether in_point is cut away.
+    pub fn cuts(&self, in_point : &[f32;3]) -> bool {self.distance(in_point) > 0.0}
+}
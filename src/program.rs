@@ -3,11 +3,16 @@ extern crate glium;
 pub struct DefaultPrograms {
     _polyhedron : glium::Program,
     _sphere     : glium::Program,
+    _line       : glium::Program,
+    _text       : glium::Program,
+    _gradient   : glium::Program,
 }
 
 impl DefaultPrograms {
     pub fn new(in_display : &glium::backend::glutin_backend::GlutinFacade) -> DefaultPrograms {
         
+        // Slice planes are (n, d) in view space, cutting away where n.x+d > 0; zeros cut nothing.
+
         // ====================
         // Polyhedron shaders
         // ====================
@@ -24,12 +29,14 @@ impl DefaultPrograms {
 
             out vec3 fragment_normal;
             out vec3 fragment_light_vector;
+            out vec3 fragment_position;
 
             void main() {
                 vec4 position = _position*mv_matrix;
                 vec4 normal = normalize(_normal*mv_matrix);
                 vec4 light_vector = light_position-position;
 
+                fragment_position = vec3(position[0],position[1],position[2]);
                 fragment_normal = vec3(normal[0],normal[1],normal[2]);
                 fragment_light_vector = vec3(light_vector[0],light_vector[1],light_vector[2]);
 
@@ -42,13 +49,19 @@ impl DefaultPrograms {
             #version 140
 
             uniform vec3 colour;
+            uniform vec4 slice_plane_0;
+            uniform vec4 slice_plane_1;
 
             in vec3 fragment_normal;
             in vec3 fragment_light_vector;
+            in vec3 fragment_position;
 
             out vec4 color;
 
             void main() {
+                if (dot(slice_plane_0.xyz, fragment_position)+slice_plane_0.w > 0
+                    || dot(slice_plane_1.xyz, fragment_position)+slice_plane_1.w > 0)
+                    discard;
                 float normal_squared = dot(fragment_normal,fragment_normal);
                 float light_distance_squared = dot(fragment_light_vector,fragment_light_vector);
                 float cos_light_angle = clamp (
@@ -78,11 +91,13 @@ impl DefaultPrograms {
             
             out vec2 fragment_xy;
             out vec3 fragment_light_vector;
+            out vec3 fragment_position;
 
             void main() {
                 vec4 position = _position*mv_matrix;
                 vec4 light_vector = light_position-position;
                 
+                fragment_position = vec3(position[0],position[1],position[2]);
                 fragment_xy = vec2(_normal[0],_normal[1]);
                 fragment_light_vector = vec3(light_vector[0],light_vector[1],light_vector[2]);
 
@@ -96,9 +111,12 @@ impl DefaultPrograms {
 
             uniform vec3 colour;
             uniform float size;
+            uniform vec4 slice_plane_0;
+            uniform vec4 slice_plane_1;
             
             in vec2 fragment_xy;
             in vec3 fragment_light_vector;
+            in vec3 fragment_position;
 
             out vec4 color;
 
@@ -107,6 +125,11 @@ impl DefaultPrograms {
                 if (xy_squared > 1)
                     discard;
                 vec3 normal = vec3(fragment_xy[0],fragment_xy[1],-sqrt(1-xy_squared));
+                // the point on the sphere, rather than on the square it is drawn on
+                vec3 surface = fragment_position+vec3(0,0,size*normal[2]);
+                if (dot(slice_plane_0.xyz, surface)+slice_plane_0.w > 0
+                    || dot(slice_plane_1.xyz, surface)+slice_plane_1.w > 0)
+                    discard;
                 vec3 light_vector = vec3 (
                     fragment_light_vector[0],
                     fragment_light_vector[1],
@@ -123,6 +146,112 @@ impl DefaultPrograms {
             }
         "#;
         
+        // ====================
+        // Line shaders
+        // ====================
+        // Flat-coloured lines, e.g. for measurements, and the translucent caps on slicing planes
+        let vertex_shader_line : &'static str = r#"
+            #version 140
+
+            uniform mat4 mv_matrix;
+            uniform mat4 mvp_matrix;
+
+            in vec4 _position;
+
+            out vec3 fragment_position;
+
+            void main() {
+                vec4 position = _position*mv_matrix;
+                fragment_position = vec3(position[0],position[1],position[2]);
+                gl_Position = _position*mvp_matrix;
+            }
+        "#;
+
+        let fragment_shader_line : &'static str = r#"
+            #version 140
+
+            uniform vec3 colour;
+            uniform float transparency;
+            uniform vec4 slice_plane_0;
+            uniform vec4 slice_plane_1;
+
+            in vec3 fragment_position;
+
+            out vec4 color;
+
+            void main() {
+                if (dot(slice_plane_0.xyz, fragment_position)+slice_plane_0.w > 0
+                    || dot(slice_plane_1.xyz, fragment_position)+slice_plane_1.w > 0)
+                    discard;
+                color = vec4(colour, 1.0-transparency);
+            }
+        "#;
+        
+        // ====================
+        // Text shaders
+        // ====================
+        // Glyphs from the font atlas, positioned directly in screen space
+        let vertex_shader_text : &'static str = r#"
+            #version 140
+
+            in vec3 _position;
+            in vec2 _tex_coords;
+
+            out vec2 fragment_tex_coords;
+
+            void main() {
+                fragment_tex_coords = _tex_coords;
+                gl_Position = vec4(_position, 1.0);
+            }
+        "#;
+
+        let fragment_shader_text : &'static str = r#"
+            #version 140
+
+            uniform sampler2D atlas;
+            uniform vec3 colour;
+
+            in vec2 fragment_tex_coords;
+
+            out vec4 color;
+
+            void main() {
+                if (texture(atlas, fragment_tex_coords).a < 0.5)
+                    discard;
+                color = vec4(colour, 1.0);
+            }
+        "#;
+
+        // ====================
+        // Gradient shaders
+        // ====================
+        // Colours given at each vertex and blended between, in screen space, e.g. for colour bars
+        let vertex_shader_gradient : &'static str = r#"
+            #version 140
+
+            in vec2 _position;
+            in vec3 _colour;
+
+            out vec3 fragment_colour;
+
+            void main() {
+                fragment_colour = _colour;
+                gl_Position = vec4(_position, 0.0, 1.0);
+            }
+        "#;
+
+        let fragment_shader_gradient : &'static str = r#"
+            #version 140
+
+            in vec3 fragment_colour;
+
+            out vec4 color;
+
+            void main() {
+                color = vec4(fragment_colour, 1.0);
+            }
+        "#;
+
         DefaultPrograms {
             _polyhedron : glium::Program::from_source(
                 in_display,
@@ -136,9 +265,30 @@ impl DefaultPrograms {
                 fragment_shader_sphere,
                 None
             ).unwrap(),
+            _line : glium::Program::from_source(
+                in_display,
+                vertex_shader_line,
+                fragment_shader_line,
+                None
+            ).unwrap(),
+            _text : glium::Program::from_source(
+                in_display,
+                vertex_shader_text,
+                fragment_shader_text,
+                None
+            ).unwrap(),
+            _gradient : glium::Program::from_source(
+                in_display,
+                vertex_shader_gradient,
+                fragment_shader_gradient,
+                None
+            ).unwrap(),
         }
     }
 
     pub fn polyhedron(&self) -> &glium::Program {&self._polyhedron}
     pub fn sphere(&self) -> &glium::Program {&self._sphere}
+    pub fn line(&self) -> &glium::Program {&self._line}
+    pub fn text(&self) -> &glium::Program {&self._text}
+    pub fn gradient(&self) -> &glium::Program {&self._gradient}
 }
@@ -45,6 +45,129 @@ impl Quaternion {
         ])
     }
 
+    /// A rotation of in_angle radians anticlockwise about in_axis (which need not be normalised).
+    pub fn from_axis_angle (in_axis : &[f32;3], in_angle : &f32) -> Quaternion {
+        let norm = (in_axis[0]*in_axis[0]+in_axis[1]*in_axis[1]+in_axis[2]*in_axis[2]).sqrt();
+        if norm == 0.0 {return Quaternion::new(&1.0, &0.0, &0.0, &0.0);}
+        let (sin, cos) = (in_angle/2.0).sin_cos();
+        Quaternion::new(
+            &cos,
+            &(in_axis[0]*sin/norm),
+            &(in_axis[1]*sin/norm),
+            &(in_axis[2]*sin/norm),
+        )
+    }
+
+    /// The axis (normalised) and angle (radians, 0 to 2 pi) of a unit quaternion.
+    /// The identity gives an angle of zero about an arbitrary axis.
+    pub fn axis_angle (&self) -> ([f32;3], f32) {
+        let sin_half_angle = (self.i()*self.i()+self.j()*self.j()+self.k()*self.k()).sqrt();
+        if sin_half_angle < 1.0e-7 {
+            return ([1.0, 0.0, 0.0], 0.0);
+        }
+        (
+            [self.i()/sin_half_angle, self.j()/sin_half_angle, self.k()/sin_half_angle],
+            2.0*sin_half_angle.atan2(*self.r()),
+        )
+    }
+
+    pub fn dot (&self, in_other : &Quaternion) -> f32 {
+        let mut dot = 0.0;
+        for (a, b) in self._contents.iter().zip(in_other._contents.iter()) {
+            dot += a*b;
+        }
+        dot
+    }
+
+    /// Normalised linear interpolation from in_from (t = 0) to in_to (t = 1).
+    /// Cheaper than slerp, but does not turn at a constant rate.
+    pub fn nlerp (in_from : &Quaternion, in_to : &Quaternion, in_t : &f32) -> Quaternion {
+        // q and -q are the same rotation; pick the one that is the short way round
+        let sign = if in_from.dot(in_to) < 0.0 {-1.0} else {1.0};
+        let mut contents = [0.0;4];
+        for l in 0..4 {
+            contents[l] = (1.0-in_t)*in_from._contents[l]+in_t*sign*in_to._contents[l];
+        }
+        let mut quaternion = Quaternion{_contents : contents};
+        quaternion.normalise();
+        quaternion
+    }
+
+    /// Spherical linear interpolation from in_from (t = 0) to in_to (t = 1),
+    /// turning the short way round at a constant rate.
+    pub fn slerp (in_from : &Quaternion, in_to : &Quaternion, in_t : &f32) -> Quaternion {
+        let mut cos_angle = in_from.dot(in_to);
+        let sign = if cos_angle < 0.0 {-1.0} else {1.0};
+        cos_angle *= sign;
+        if cos_angle > 0.9995 {
+            // too close together for the sines to be accurate
+            return Quaternion::nlerp(in_from, in_to, in_t);
+        }
+        let angle = cos_angle.acos();
+        let a = ((1.0-in_t)*angle).sin()/angle.sin();
+        let b = sign*(in_t*angle).sin()/angle.sin();
+        let mut contents = [0.0;4];
+        for l in 0..4 {
+            contents[l] = a*in_from._contents[l]+b*in_to._contents[l];
+        }
+        let mut quaternion = Quaternion{_contents : contents};
+        quaternion.normalise();
+        quaternion
+    }
+
+    /// Rotates a vector by this (unit) quaternion.
+    pub fn rotate_vector (&self, in_vector : &[f32;3]) -> [f32;3] {
+        let rotated = self.rotation_matrix()*[in_vector[0], in_vector[1], in_vector[2], 0.0];
+        [rotated[0], rotated[1], rotated[2]]
+    }
+
+    /// The quaternion whose rotation_matrix() is in_matrix (a proper rotation, rows first).
+    pub fn from_rotation_matrix (in_matrix : &[[f32;3];3]) -> Quaternion {
+        let m = in_matrix;
+        let trace = m[0][0]+m[1][1]+m[2][2];
+        // divide by the largest of r, i, j, k to keep things stable
+        let mut quaternion = if trace > 0.0 {
+            let s = 0.5/(trace+1.0).sqrt();
+            Quaternion::new(&(0.25/s), &((m[2][1]-m[1][2])*s), &((m[0][2]-m[2][0])*s), &((m[1][0]-m[0][1])*s))
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = 2.0*(1.0+m[0][0]-m[1][1]-m[2][2]).sqrt();
+            Quaternion::new(&((m[2][1]-m[1][2])/s), &(0.25*s), &((m[0][1]+m[1][0])/s), &((m[0][2]+m[2][0])/s))
+        } else if m[1][1] > m[2][2] {
+            let s = 2.0*(1.0+m[1][1]-m[0][0]-m[2][2]).sqrt();
+            Quaternion::new(&((m[0][2]-m[2][0])/s), &((m[0][1]+m[1][0])/s), &(0.25*s), &((m[1][2]+m[2][1])/s))
+        } else {
+            let s = 2.0*(1.0+m[2][2]-m[0][0]-m[1][1]).sqrt();
+            Quaternion::new(&((m[1][0]-m[0][1])/s), &((m[0][2]+m[2][0])/s), &((m[1][2]+m[2][1])/s), &(0.25*s))
+        };
+        quaternion.normalise();
+        quaternion
+    }
+
+    /// The shortest rotation taking unit vector in_from onto unit vector in_to.
+    pub fn between (in_from : &[f32;3], in_to : &[f32;3]) -> Quaternion {
+        let dot = in_from[0]*in_to[0]+in_from[1]*in_to[1]+in_from[2]*in_to[2];
+        if dot < -0.999999 {
+            // Opposite vectors: half a turn about any perpendicular axis.
+            let mut axis = if in_from[0].abs() < 0.9 {
+                [0.0, in_from[2], -in_from[1]]
+            } else {
+                [-in_from[2], 0.0, in_from[0]]
+            };
+            let norm = (axis[0]*axis[0]+axis[1]*axis[1]+axis[2]*axis[2]).sqrt();
+            for element in &mut axis {*element /= norm;}
+            return Quaternion::new(&0.0, &axis[0], &axis[1], &axis[2]);
+        }
+        // (1 + cos, sin*axis) normalises to (cos(angle/2), sin(angle/2)*axis)
+        let mut quaternion = Quaternion::new(
+            &(1.0+dot),
+            &(in_from[1]*in_to[2]-in_from[2]*in_to[1]),
+            &(in_from[2]*in_to[0]-in_from[0]*in_to[2]),
+            &(in_from[0]*in_to[1]-in_from[1]*in_to[0]),
+        );
+        quaternion.normalise();
+        quaternion
+    }
+
     pub fn normalise (&mut self) {
         let mut norm = 0.0;
         for element in &self._contents {
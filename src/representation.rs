@@ -0,0 +1,106 @@
+use matrix::Matrix;
+use species::Species;
+use vector::{cross, length, normalised, sub};
+
+// ============================================================
+// Representation
+// ============================================================
+/// How atoms and bonds are drawn. There is one for the whole structure,
+/// which individual atoms can override.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Representation {
+    /// Spheres at the species size, joined by thin sticks.
+    BallAndStick,
+    /// Spheres at the van der Waals radius, without bonds.
+    SpaceFilling,
+    /// Thick sticks, with spheres of the same radius capping the joints.
+    Licorice,
+    /// Bonds as lines, half in the colour of each atom.
+    Wireframe,
+    /// Atoms as dots, without bonds.
+    Points,
+}
+
+impl Representation {
+    pub fn all() -> Vec<Representation> {
+        vec![
+            Representation::BallAndStick,
+            Representation::SpaceFilling,
+            Representation::Licorice,
+            Representation::Wireframe,
+            Representation::Points,
+        ]
+    }
+
+    pub fn next(&self) -> Representation {
+        let all = Representation::all();
+        let position = all.iter().position(|representation| representation == self).unwrap();
+        all[(position+1)%all.len()]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Representation::BallAndStick => "ball_and_stick",
+            Representation::SpaceFilling => "space_filling",
+            Representation::Licorice => "licorice",
+            Representation::Wireframe => "wireframe",
+            Representation::Points => "points",
+        }
+    }
+
+    pub fn from_name(in_name : &str) -> Option<Representation> {
+        Representation::all().into_iter().find(|representation| representation.name() == in_name)
+    }
+
+    /// Whether atoms are drawn as spheres.
+    pub fn has_spheres(&self) -> bool {
+        match *self {
+            Representation::BallAndStick | Representation::SpaceFilling | Representation::Licorice => true,
+            Representation::Wireframe | Representation::Points => false,
+        }
+    }
+
+    /// The radius an atom is drawn with, and picked by. Atoms drawn without
+    /// spheres are picked as if they were as thick as a stick.
+    pub fn atom_radius(&self, in_species : &Species, in_stick_radius : &f32) -> f32 {
+        match *self {
+            Representation::BallAndStick => *in_species.size(),
+            Representation::SpaceFilling => *in_species.vdw_radius(),
+            Representation::Licorice => licorice_radius(in_stick_radius),
+            Representation::Wireframe | Representation::Points => *in_stick_radius,
+        }
+    }
+
+    /// The radius of the cylinder for half a bond, if bonds are drawn as cylinders.
+    pub fn bond_radius(&self, in_stick_radius : &f32) -> Option<f32> {
+        match *self {
+            Representation::BallAndStick => Some(*in_stick_radius),
+            Representation::Licorice => Some(licorice_radius(in_stick_radius)),
+            _ => None,
+        }
+    }
+}
+
+/// Licorice sticks are twice as thick as ball-and-stick ones.
+fn licorice_radius(in_stick_radius : &f32) -> f32 {2.0*in_stick_radius}
+
+/// The model matrix taking the unit cylinder (radius 1, from z=0 to z=1)
+/// to one of radius in_radius from in_start to in_end.
+pub fn cylinder_matrix(in_start : &[f32;3], in_end : &[f32;3], in_radius : &f32) -> Matrix {
+    let axis = sub(in_end, in_start);
+    if length(&axis) == 0.0 {
+        return Matrix::new([[0.0;4], [0.0;4], [0.0;4], [0.0, 0.0, 0.0, 1.0]]);
+    }
+    // any vector not parallel to the axis will do to start the frame
+    let w = normalised(&axis);
+    let seed = if w[0].abs() < 0.9 {[1.0, 0.0, 0.0]} else {[0.0, 1.0, 0.0]};
+    let u = normalised(&cross(&seed, &w));
+    let v = cross(&w, &u);
+    let r = *in_radius;
+    Matrix::new([
+        [u[0]*r, v[0]*r, axis[0], in_start[0]],
+        [u[1]*r, v[1]*r, axis[1], in_start[1]],
+        [u[2]*r, v[2]*r, axis[2], in_start[2]],
+        [0.0   , 0.0   , 0.0    , 1.0        ],
+    ])
+}
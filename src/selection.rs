@@ -0,0 +1,469 @@
+//! A small query language for picking out atoms, e.g.
+//!
+//! element O and within 3.0 of index 12
+//! z > 5.0
+//! frac_c < 0.5
+//! coordination == 4
+//! not element H
+//! residue HOH
+//! index 1:10 20 (element Na or element Cl)
+//!
+//! Words are combined with and, or, not and brackets; and binds tighter than or.
+//! Queries are evaluated over plain AtomInfo records, so this module knows
+//! nothing about how molecules are stored or drawn.
+
+// ============================================================
+// Atom information
+// ============================================================
+/// What a query can ask about an atom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtomInfo {
+    pub element    : String,
+    pub position   : [f32;3],
+    /// The fractional coordinates, if the structure has a unit cell.
+    pub fractional : Option<[f32;3]>,
+    /// Numbers, e.g. "coordination" or "charge".
+    pub properties : Vec<(String, f32)>,
+    /// Groups, e.g. "residue" = "HOH" or "fragment" = "2".
+    pub categories : Vec<(String, String)>,
+}
+
+impl AtomInfo {
+    /// x, y and z, frac_a, frac_b and frac_c, or a property.
+    fn number(&self, in_name : &str) -> Option<f32> {
+        match in_name {
+            "x" => Some(self.position[0]),
+            "y" => Some(self.position[1]),
+            "z" => Some(self.position[2]),
+            "frac_a" => self.fractional.map(|fractional| fractional[0]),
+            "frac_b" => self.fractional.map(|fractional| fractional[1]),
+            "frac_c" => self.fractional.map(|fractional| fractional[2]),
+            _ => self.properties.iter().find(|property| property.0 == in_name).map(|property| property.1),
+        }
+    }
+
+    /// The element, or a group.
+    fn group(&self, in_name : &str) -> Option<&str> {
+        match in_name {
+            "element" => Some(&self.element),
+            _ => self.categories.iter().find(|category| category.0 == in_name).map(|category| category.1.as_str()),
+        }
+    }
+}
+
+// ============================================================
+// Selection
+// ============================================================
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    fn from_symbol(in_symbol : &str) -> Option<Comparison> {
+        match in_symbol {
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            "==" | "=" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            _ => None,
+        }
+    }
+
+    fn holds(&self, in_a : &f32, in_b : &f32) -> bool {
+        match *self {
+            Comparison::Less => in_a < in_b,
+            Comparison::LessOrEqual => in_a <= in_b,
+            Comparison::Greater => in_a > in_b,
+            Comparison::GreaterOrEqual => in_a >= in_b,
+            Comparison::Equal => in_a == in_b,
+            Comparison::NotEqual => in_a != in_b,
+        }
+    }
+}
+
+/// A parsed query.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Selection {
+    All,
+    Nothing,
+    Not(Box<Selection>),
+    And(Box<Selection>, Box<Selection>),
+    Or(Box<Selection>, Box<Selection>),
+    /// Atoms within a distance of any atom in the selection, including those atoms.
+    Within(f32, Box<Selection>),
+    /// Ranges of atom numbers, counting from 1, inclusive.
+    Index(Vec<(usize, usize)>),
+    /// A number compared with a constant, e.g. z > 5.0.
+    Compare(String, Comparison, f32),
+    /// The element or a group is one of the values, e.g. element O N.
+    Group(String, Vec<String>),
+}
+
+impl Selection {
+    /// Whether each atom is selected. in_distance gives the distance between
+    /// two atoms, by their position in in_atoms, for within.
+    pub fn evaluate<F : Fn(&usize, &usize) -> f32>(&self, in_atoms : &[AtomInfo], in_distance : &F) -> Vec<bool> {
+        match *self {
+            Selection::All => vec![true; in_atoms.len()],
+            Selection::Nothing => vec![false; in_atoms.len()],
+            Selection::Not(ref a) => a.evaluate(in_atoms, in_distance).iter().map(|&x| !x).collect(),
+            Selection::And(ref a, ref b) => {
+                let b = b.evaluate(in_atoms, in_distance);
+                a.evaluate(in_atoms, in_distance).iter().zip(b).map(|(&x, y)| x && y).collect()
+            },
+            Selection::Or(ref a, ref b) => {
+                let b = b.evaluate(in_atoms, in_distance);
+                a.evaluate(in_atoms, in_distance).iter().zip(b).map(|(&x, y)| x || y).collect()
+            },
+            Selection::Within(ref radius, ref a) => {
+                let centres : Vec<usize> = a.evaluate(in_atoms, in_distance).iter()
+                    .enumerate()
+                    .filter(|&(_, &x)| x)
+                    .map(|(j, _)| j)
+                    .collect();
+                (0..in_atoms.len())
+                    .map(|i| centres.iter().any(|j| i == *j || in_distance(&i, j) <= *radius))
+                    .collect()
+            },
+            Selection::Index(ref ranges) => (1..in_atoms.len()+1)
+                .map(|number| ranges.iter().any(|&(first, last)| number >= first && number <= last))
+                .collect(),
+            Selection::Compare(ref name, comparison, ref value) => in_atoms.iter()
+                .map(|atom| atom.number(name).map_or(false, |x| comparison.holds(&x, value)))
+                .collect(),
+            Selection::Group(ref name, ref values) => in_atoms.iter()
+                .map(|atom| atom.group(name).map_or(false, |x| values.iter().any(|value| value == x)))
+                .collect(),
+        }
+    }
+
+    /// Whether the query compares or groups by in_name anywhere, so that
+    /// what is costly to work out, e.g. coordination, is only worked out when asked about.
+    pub fn mentions(&self, in_name : &str) -> bool {
+        match *self {
+            Selection::All | Selection::Nothing | Selection::Index(_) => false,
+            Selection::Not(ref a) | Selection::Within(_, ref a) => a.mentions(in_name),
+            Selection::And(ref a, ref b) | Selection::Or(ref a, ref b) => a.mentions(in_name) || b.mentions(in_name),
+            Selection::Compare(ref name, _, _) | Selection::Group(ref name, _) => name == in_name,
+        }
+    }
+}
+
+/// The positions of the selected atoms in a mask from evaluate().
+pub fn indices(in_mask : &[bool]) -> Vec<usize> {
+    in_mask.iter().enumerate().filter(|&(_, &x)| x).map(|(i, _)| i).collect()
+}
+
+// ============================================================
+// Parsing
+// ============================================================
+pub fn parse(in_text : &str) -> Result<Selection, String> {
+    let tokens = tokenise(in_text);
+    if tokens.is_empty() {
+        return Err("The selection is empty".to_string());
+    }
+    let mut parser = Parser {_tokens : tokens, _next : 0};
+    let selection = parser.or()?;
+    match parser.peek() {
+        Some(token) => Err(format!("Unexpected \"{}\" in selection", token)),
+        None => Ok(selection),
+    }
+}
+
+/// Splits into words, brackets and comparison operators, which needn't have spaces around them.
+fn tokenise(in_text : &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut chars = in_text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let symbol = match c {
+            '(' | ')' => Some(c.to_string()),
+            '<' | '>' | '=' | '!' => {
+                let mut symbol = c.to_string();
+                if chars.peek() == Some(&'=') {
+                    symbol.push(chars.next().unwrap());
+                }
+                Some(symbol)
+            },
+            _ => None,
+        };
+        if c.is_whitespace() || symbol.is_some() {
+            if !word.is_empty() {
+                tokens.push(word.clone());
+                word.clear();
+            }
+            if let Some(symbol) = symbol {
+                tokens.push(symbol);
+            }
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+/// Words which end a list of values.
+fn is_keyword(in_token : &str) -> bool {
+    match in_token {
+        "and" | "or" | "not" | "(" | ")" | "within" | "of" => true,
+        _ => Comparison::from_symbol(in_token).is_some(),
+    }
+}
+
+struct Parser {
+    _tokens : Vec<String>,
+    _next   : usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {self._tokens.get(self._next).map(|token| token.as_str())}
+
+    fn take(&mut self) -> Option<String> {
+        let token = self._tokens.get(self._next).cloned();
+        if token.is_some() {self._next += 1;}
+        token
+    }
+
+    fn expect(&mut self, in_token : &str) -> Result<(), String> {
+        match self.take() {
+            Some(ref token) if token == in_token => Ok(()),
+            Some(token) => Err(format!("Expected \"{}\" but found \"{}\"", in_token, token)),
+            None => Err(format!("Expected \"{}\" at the end", in_token)),
+        }
+    }
+
+    fn number(&mut self, in_what : &str) -> Result<f32, String> {
+        match self.take() {
+            Some(token) => token.parse::<f32>().map_err(|_| format!("Expected {} but found \"{}\"", in_what, token)),
+            None => Err(format!("Expected {} at the end", in_what)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Selection, String> {
+        let mut selection = self.and()?;
+        while self.peek() == Some("or") {
+            self.take();
+            selection = Selection::Or(Box::new(selection), Box::new(self.and()?));
+        }
+        Ok(selection)
+    }
+
+    fn and(&mut self) -> Result<Selection, String> {
+        let mut selection = self.unary()?;
+        while self.peek() == Some("and") {
+            self.take();
+            selection = Selection::And(Box::new(selection), Box::new(self.unary()?));
+        }
+        Ok(selection)
+    }
+
+    fn unary(&mut self) -> Result<Selection, String> {
+        if self.peek() == Some("not") {
+            self.take();
+            return Ok(Selection::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Selection, String> {
+        let token = match self.take() {
+            Some(token) => token,
+            None => return Err("The selection ends too soon".to_string()),
+        };
+        match token.as_str() {
+            "(" => {
+                let selection = self.or()?;
+                self.expect(")")?;
+                Ok(selection)
+            },
+            "all" => Ok(Selection::All),
+            "none" => Ok(Selection::Nothing),
+            "within" => {
+                let radius = self.number("a distance")?;
+                if radius < 0.0 {
+                    return Err("within needs a distance of zero or more".to_string());
+                }
+                self.expect("of")?;
+                Ok(Selection::Within(radius, Box::new(self.unary()?)))
+            },
+            "index" => {
+                let mut ranges = Vec::new();
+                while let Some(word) = self.peek().map(|word| word.to_string()) {
+                    if is_keyword(&word) {break;}
+                    self.take();
+                    ranges.push(parse_range(&word)?);
+                }
+                if ranges.is_empty() {
+                    return Err("index needs atom numbers, e.g. index 12 or index 1:10".to_string());
+                }
+                Ok(Selection::Index(ranges))
+            },
+            _ if is_keyword(&token) => Err(format!("Unexpected \"{}\" in selection", token)),
+            name => {
+                let comparison = self.peek().and_then(Comparison::from_symbol);
+                if let Some(comparison) = comparison {
+                    self.take();
+                    let value = self.number("a number")?;
+                    return Ok(Selection::Compare(name.to_string(), comparison, value));
+                }
+                let mut values = Vec::new();
+                while let Some(word) = self.peek().map(|word| word.to_string()) {
+                    if is_keyword(&word) {break;}
+                    self.take();
+                    values.push(word);
+                }
+                if values.is_empty() {
+                    return Err(format!("{} needs a comparison or some values, e.g. {} > 1.0 or {} A B", name, name, name));
+                }
+                Ok(Selection::Group(name.to_string(), values))
+            },
+        }
+    }
+}
+
+/// "12" or "1:10".
+fn parse_range(in_word : &str) -> Result<(usize, usize), String> {
+    let number = |text : &str| match text.parse::<usize>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(format!("\"{}\" is not an atom number (they count from 1)", text)),
+    };
+    let mut parts = in_word.splitn(2, ':');
+    let first = number(parts.next().unwrap())?;
+    let last = match parts.next() {
+        Some(last) => number(last)?,
+        None => first,
+    };
+    if last < first {
+        return Err(format!("\"{}\" runs backwards", in_word));
+    }
+    Ok((first, last))
+}
+
+// ============================================================
+// Tests
+// ============================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(in_element : &str, in_position : [f32;3]) -> AtomInfo {
+        AtomInfo {
+            element    : in_element.to_string(),
+            position   : in_position,
+            fractional : Some([in_position[0]/10.0, in_position[1]/10.0, in_position[2]/10.0]),
+            properties : Vec::new(),
+            categories : Vec::new(),
+        }
+    }
+
+    /// A water molecule, a lone oxygen and a sodium along the x axis.
+    fn atoms() -> Vec<AtomInfo> {
+        let mut atoms = vec![
+            atom("O", [0.0, 0.0, 0.0]),
+            atom("H", [1.0, 0.0, 0.0]),
+            atom("H", [0.0, 1.0, 0.0]),
+            atom("O", [5.0, 0.0, 6.0]),
+            atom("Na", [9.0, 0.0, 0.0]),
+        ];
+        for (i, &coordination) in [2.0, 1.0, 1.0, 0.0, 0.0].iter().enumerate() {
+            atoms[i].properties.push(("coordination".to_string(), coordination));
+        }
+        for i in 0..3 {
+            atoms[i].categories.push(("residue".to_string(), "HOH".to_string()));
+        }
+        atoms
+    }
+
+    fn select(in_query : &str) -> Vec<usize> {
+        let atoms = atoms();
+        let distance = |i : &usize, j : &usize| {
+            let a = atoms[*i].position;
+            let b = atoms[*j].position;
+            ((a[0]-b[0]).powi(2)+(a[1]-b[1]).powi(2)+(a[2]-b[2]).powi(2)).sqrt()
+        };
+        indices(&parse(in_query).unwrap().evaluate(&atoms, &distance))
+    }
+
+    #[test]
+    fn elements() {
+        assert_eq!(select("element O"), vec![0, 3]);
+        assert_eq!(select("element O Na"), vec![0, 3, 4]);
+        assert_eq!(select("not element H"), vec![0, 3, 4]);
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(select("z > 5.0"), vec![3]);
+        assert_eq!(select("x>=5"), vec![3, 4]);
+        assert_eq!(select("frac_c < 0.5"), vec![0, 1, 2, 4]);
+        assert_eq!(select("coordination == 1"), vec![1, 2]);
+        assert_eq!(select("coordination != 0"), vec![0, 1, 2]);
+        // atoms without the property never match
+        assert_eq!(select("charge < 100"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn groups_and_indices() {
+        assert_eq!(select("residue HOH"), vec![0, 1, 2]);
+        assert_eq!(select("index 2"), vec![1]);
+        assert_eq!(select("index 1:2 5"), vec![0, 1, 4]);
+    }
+
+    #[test]
+    fn within() {
+        assert_eq!(select("within 1.5 of index 1"), vec![0, 1, 2]);
+        assert_eq!(select("element O and within 3.0 of index 2"), vec![0]);
+        assert_eq!(select("within 0 of element Na"), vec![4]);
+    }
+
+    #[test]
+    fn precedence() {
+        // and binds tighter than or
+        assert_eq!(select("element Na or element O and z > 1"), vec![3, 4]);
+        assert_eq!(select("(element Na or element O) and z < 1"), vec![0, 4]);
+        assert_eq!(select("not not element H"), vec![1, 2]);
+        assert_eq!(select("all and not none"), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn parse_tree() {
+        assert_eq!(parse("z > 5.0").unwrap(), Selection::Compare("z".to_string(), Comparison::Greater, 5.0));
+        assert_eq!(
+            parse("not element H").unwrap(),
+            Selection::Not(Box::new(Selection::Group("element".to_string(), vec!["H".to_string()]))),
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("").is_err());
+        assert!(parse("element").is_err());
+        assert!(parse("z >").is_err());
+        assert!(parse("z > high").is_err());
+        assert!(parse("(element O").is_err());
+        assert!(parse("element O)").is_err());
+        assert!(parse("within of index 1").is_err());
+        assert!(parse("index 0").is_err());
+        assert!(parse("index 5:2").is_err());
+        assert!(parse("element O and").is_err());
+    }
+
+    #[test]
+    fn mentions() {
+        assert!(!parse("element O").unwrap().mentions("coordination"));
+        assert!(parse("element O and not coordination == 4").unwrap().mentions("coordination"));
+        assert!(parse("within 3.0 of fragment 2").unwrap().mentions("fragment"));
+        assert!(!parse("within 3.0 of fragment 2").unwrap().mentions("coordination"));
+    }
+}
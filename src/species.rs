@@ -1,31 +1,51 @@
+use std::cell::RefCell;
+
 use model;
 use model::Model;
+use elements;
+use config::ElementStyle;
 
 // ============================================================
 // Species
 // ============================================================
 pub struct Species<'a> {
-    _mesh   : &'a Model<'a>,
-    _size   : f32,
-    _colour : [f32;3],
+    _name       : String,
+    _mesh       : &'a Model<'a>,
+    _size       : f32,
+    _colour     : [f32;3],
+    _visible    : bool,
+    /// The van der Waals radius, for space-filling models.
+    _vdw_radius : f32,
 }
 
 impl<'a> Species<'a> {
     pub fn new (
-        in_mesh   : &'a Model,
-        in_size   : &f32,
-        in_colour : &[f32;3],
+        in_name       : &str,
+        in_mesh       : &'a Model,
+        in_size       : &f32,
+        in_colour     : &[f32;3],
+        in_vdw_radius : &f32,
     ) -> Species<'a> {
         Species {
-            _mesh   : in_mesh,
-            _size   : in_size.to_owned(),
-            _colour : in_colour.to_owned()
+            _name       : in_name.to_string(),
+            _mesh       : in_mesh,
+            _size       : in_size.to_owned(),
+            _colour     : in_colour.to_owned(),
+            _visible    : true,
+            _vdw_radius : in_vdw_radius.to_owned(),
         }
     }
 
+    pub fn name(&self) -> &str {&self._name}
     pub fn mesh(&self) -> &Model {&self._mesh}
     pub fn size(&self) -> &f32  {&self._size}
     pub fn colour(&self) -> &[f32;3] {&self._colour}
+    pub fn visible(&self) -> bool {self._visible}
+    pub fn vdw_radius(&self) -> &f32 {&self._vdw_radius}
+
+    pub fn set_size(&mut self, in_size : &f32) {self._size = *in_size}
+    pub fn set_colour(&mut self, in_colour : &[f32;3]) {self._colour = *in_colour}
+    pub fn set_visible(&mut self, in_visible : &bool) {self._visible = *in_visible}
 }
 
 pub struct DefaultSpecies<'a> {
@@ -33,6 +53,12 @@ pub struct DefaultSpecies<'a> {
     _nickel  : Species<'a>,
     _sulphur : Species<'a>,
     _oxygen  : Species<'a>,
+    /// One species per element, for structures read from files.
+    _elements : Vec<Species<'a>>,
+    /// Anything whose label is not an element.
+    _unknown  : Species<'a>,
+    /// The unknown labels already warned about, so each is reported once.
+    _unknown_labels : RefCell<Vec<String>>,
 }
 
 impl<'a> DefaultSpecies<'a> {
@@ -47,13 +73,26 @@ impl<'a> DefaultSpecies<'a> {
         let green     = [102.0/255.0,166.0/255.0, 30.0/255.0];
         let yellow    = [230.0/255.0,171.0/255.0,  2.0/255.0];
         // let brown     = [166.0/255.0,118.0/255.0, 29.0/255.0];
-        // let grey      = [102.0/255.0,102.0/255.0,102.0/255.0];
+        let grey      = [102.0/255.0,102.0/255.0,102.0/255.0];
+
+        // atoms are drawn at half their covalent radius, so bonded neighbours don't touch
+        let elements = elements::all().iter().map(|element| Species::new(
+            element.symbol,
+            in_default_models.sphere(),
+            &(0.5*element.covalent_radius),
+            &element.colour,
+            &element.vdw_radius,
+        )).collect();
+        let vdw_radius = |symbol| elements::find(symbol).unwrap().vdw_radius;
 
         DefaultSpecies {
-            _carbon  : Species::new(in_default_models.sphere(), &0.1, &blue),
-            _nickel  : Species::new(in_default_models.sphere(), &0.2, &orange),
-            _sulphur : Species::new(in_default_models.sphere(), &0.4, &yellow),
-            _oxygen  : Species::new(in_default_models.sphere(), &0.2, &green),
+            _carbon  : Species::new("C",  in_default_models.sphere(), &0.1, &blue, &vdw_radius("C")),
+            _nickel  : Species::new("Ni", in_default_models.sphere(), &0.2, &orange, &vdw_radius("Ni")),
+            _sulphur : Species::new("S",  in_default_models.sphere(), &0.4, &yellow, &vdw_radius("S")),
+            _oxygen  : Species::new("O",  in_default_models.sphere(), &0.2, &green, &vdw_radius("O")),
+            _elements : elements,
+            _unknown  : Species::new("X", in_default_models.sphere(), &0.4, &grey, &2.0),
+            _unknown_labels : RefCell::new(Vec::new()),
         }
     }
 
@@ -61,4 +100,34 @@ impl<'a> DefaultSpecies<'a> {
     pub fn nickel(&self) -> &Species {&self._nickel}
     pub fn sulphur(&self) -> &Species {&self._sulphur}
     pub fn oxygen(&self) -> &Species {&self._oxygen}
+
+    /// Applies a style from the config file to every species of that element.
+    pub fn apply_style(&mut self, in_style : &ElementStyle) {
+        let species = self._elements.iter_mut().chain(vec![
+            &mut self._carbon, &mut self._nickel, &mut self._sulphur, &mut self._oxygen,
+        ]);
+        for species in species.filter(|species| species._name == in_style.symbol) {
+            if let Some(radius) = in_style.radius {species.set_size(&radius);}
+            if let Some(colour) = in_style.colour {species.set_colour(&colour);}
+            if let Some(visible) = in_style.visible {species.set_visible(&visible);}
+        }
+    }
+
+    /// The species for a label from a structure file, e.g. "Fe", "O1" or "Cl:2".
+    /// A label that is not an element gets the grey unknown species, with a warning
+    /// the first time it is seen.
+    pub fn by_label(&self, in_label : &str) -> &Species {
+        let symbol = elements::symbol_from_label(in_label);
+        match self._elements.iter().find(|species| species.name() == symbol) {
+            Some(species) => species,
+            None => {
+                let mut unknown_labels = self._unknown_labels.borrow_mut();
+                if !unknown_labels.iter().any(|label| label == in_label) {
+                    println!("Unknown element \"{}\", drawing it in grey", in_label);
+                    unknown_labels.push(in_label.to_string());
+                }
+                &self._unknown
+            },
+        }
+    }
 }
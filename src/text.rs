@@ -0,0 +1,148 @@
+extern crate glium;
+
+use glium::Surface;
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+
+use font;
+
+// ============================================================
+// Text vertex
+// ============================================================
+#[derive(Copy, Clone)]
+struct TextVertex {
+    /// Normalised device coordinates, including depth.
+    _position   : [f32;3],
+    _tex_coords : [f32;2],
+}
+
+implement_vertex!(TextVertex, _position, _tex_coords);
+
+// ============================================================
+// Text batch
+// ============================================================
+/// Strings laid out on the screen, ready to be drawn in one go.
+pub struct TextBatch {
+    _screen_size : [u32;2],
+    _vertices    : Vec<TextVertex>,
+}
+
+impl TextBatch {
+    pub fn new(in_screen_size : &[u32;2]) -> TextBatch {
+        TextBatch {
+            _screen_size : in_screen_size.to_owned(),
+            _vertices    : Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {self._vertices.is_empty()}
+
+    /// Adds text with its top-left corner at in_pixel (measured from the top-left of the window),
+    /// at in_depth in normalised device coordinates. Lines are split on '\n'.
+    pub fn add(&mut self, in_text : &str, in_pixel : &[f32;2], in_depth : &f32) {
+        let w = self._screen_size[0].max(1) as f32;
+        let h = self._screen_size[1].max(1) as f32;
+        let glyph_width = font::GLYPH_WIDTH as f32;
+        let glyph_height = font::GLYPH_HEIGHT as f32;
+        let glyph_u = 1.0/font::glyph_count() as f32;
+        // snap to whole pixels so the glyphs stay sharp
+        let left = in_pixel[0].round();
+        let mut top = in_pixel[1].round();
+        for line in in_text.lines() {
+            for (column, character) in line.chars().enumerate() {
+                let x0 = left+glyph_width*column as f32;
+                let x1 = x0+glyph_width;
+                let y0 = top;
+                let y1 = top+glyph_height;
+                let u0 = glyph_u*font::glyph_index(&character) as f32;
+                let u1 = u0+glyph_u;
+                let corner = |x : f32, y : f32, u : f32, v : f32| TextVertex {
+                    _position   : [2.0*x/w-1.0, 1.0-2.0*y/h, *in_depth],
+                    _tex_coords : [u, v],
+                };
+                // the atlas has its top row at v = 1
+                let top_left = corner(x0, y0, u0, 1.0);
+                let top_right = corner(x1, y0, u1, 1.0);
+                let bottom_left = corner(x0, y1, u0, 0.0);
+                let bottom_right = corner(x1, y1, u1, 0.0);
+                self._vertices.extend_from_slice(&[
+                    top_left, bottom_left, bottom_right,
+                    top_left, bottom_right, top_right,
+                ]);
+            }
+            top += line_height();
+        }
+    }
+
+    /// Adds text centred on in_pixel.
+    pub fn add_centred(&mut self, in_text : &str, in_pixel : &[f32;2], in_depth : &f32) {
+        let size = text_size(in_text);
+        self.add(in_text, &[in_pixel[0]-size[0]/2.0, in_pixel[1]-size[1]/2.0], in_depth);
+    }
+}
+
+/// The distance from one line of text to the next, in pixels.
+pub fn line_height() -> f32 {(font::GLYPH_HEIGHT+2) as f32}
+
+/// The width and height of a block of text, in pixels.
+pub fn text_size(in_text : &str) -> [f32;2] {
+    let columns = in_text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+    let lines = in_text.lines().count();
+    [(font::GLYPH_WIDTH as f32)*columns as f32, line_height()*lines as f32]
+}
+
+// ============================================================
+// Text renderer
+// ============================================================
+/// Draws text from a bitmap font atlas held in a texture.
+pub struct TextRenderer<'a> {
+    _atlas   : glium::texture::Texture2d,
+    _program : &'a glium::Program,
+}
+
+impl<'a> TextRenderer<'a> {
+    pub fn new(
+        in_display : &glium::backend::glutin_backend::GlutinFacade,
+        in_program : &'a glium::Program,
+    ) -> TextRenderer<'a> {
+        let (pixels, width, height) = font::atlas();
+        let image = glium::texture::RawImage2d::from_raw_rgba_reversed(pixels, (width, height));
+        TextRenderer {
+            _atlas   : glium::texture::Texture2d::new(in_display, image).unwrap(),
+            _program : in_program,
+        }
+    }
+
+    /// Draws a batch in one colour. With in_depth_test, text is hidden behind
+    /// whatever is nearer the camera, e.g. labels behind other atoms.
+    pub fn draw<S : Surface>(
+        &self,
+        in_display    : &glium::backend::glutin_backend::GlutinFacade,
+        in_target     : &mut S,
+        in_batch      : &TextBatch,
+        in_colour     : &[f32;3],
+        in_depth_test : &bool,
+    ) {
+        if in_batch.is_empty() {return;}
+        let params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: if *in_depth_test {glium::DepthTest::IfLessOrEqual} else {glium::DepthTest::Overwrite},
+                write: false,
+                .. Default::default()
+            },
+            .. Default::default()
+        };
+        let uniforms = uniform!{
+            atlas  : self._atlas.sampled()
+                .magnify_filter(MagnifySamplerFilter::Nearest)
+                .minify_filter(MinifySamplerFilter::Nearest),
+            colour : in_colour.to_owned(),
+        };
+        in_target.draw(
+            &glium::VertexBuffer::new(in_display, &in_batch._vertices).unwrap(),
+            &glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+            self._program,
+            &uniforms,
+            &params,
+        ).unwrap();
+    }
+}
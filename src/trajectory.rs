@@ -0,0 +1,331 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use lattice::Lattice;
+use compression::{self, Compression};
+
+// ============================================================
+// Frame
+// ============================================================
+/// One snapshot of a trajectory: where every atom is, and the cell if it has one,
+/// along with whatever else the file recorded about it.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// Cartesian, in the same order as the molecule's atoms.
+    _positions  : Vec<[f32;3]>,
+    _lattice    : Option<Lattice>,
+    /// In eV/Å.
+    _forces     : Option<Vec<[f32;3]>>,
+    /// In Å/ps.
+    _velocities : Option<Vec<[f32;3]>>,
+    /// The total energy, in eV.
+    _energy     : Option<f32>,
+    /// Numbers for each atom, e.g. "charge" or "spin".
+    _properties : Vec<(String, Vec<f32>)>,
+}
+
+impl Frame {
+    pub fn new(in_positions : Vec<[f32;3]>, in_lattice : Option<Lattice>) -> Frame {
+        Frame {
+            _positions  : in_positions,
+            _lattice    : in_lattice,
+            _forces     : None,
+            _velocities : None,
+            _energy     : None,
+            _properties : Vec::new(),
+        }
+    }
+
+    pub fn positions(&self) -> &Vec<[f32;3]> {&self._positions}
+    pub fn lattice(&self) -> Option<&Lattice> {self._lattice.as_ref()}
+    pub fn forces(&self) -> Option<&Vec<[f32;3]>> {self._forces.as_ref()}
+    pub fn set_forces(&mut self, in_forces : Vec<[f32;3]>) {self._forces = Some(in_forces)}
+    pub fn velocities(&self) -> Option<&Vec<[f32;3]>> {self._velocities.as_ref()}
+    pub fn set_velocities(&mut self, in_velocities : Vec<[f32;3]>) {self._velocities = Some(in_velocities)}
+    pub fn energy(&self) -> Option<f32> {self._energy}
+    pub fn set_energy(&mut self, in_energy : &f32) {self._energy = Some(*in_energy)}
+    pub fn properties(&self) -> &Vec<(String, Vec<f32>)> {&self._properties}
+
+    /// Gives every atom a value of a property, replacing any it had.
+    pub fn set_property(&mut self, in_name : &str, in_values : Vec<f32>) {
+        match self._properties.iter_mut().find(|property| property.0 == in_name) {
+            Some(property) => property.1 = in_values,
+            None => self._properties.push((in_name.to_string(), in_values)),
+        }
+    }
+
+    /// The frame with just the atoms in in_mask.
+    pub fn subset(&self, in_mask : &[bool]) -> Frame {
+        let keep = |values : &Vec<[f32;3]>| -> Vec<[f32;3]> {
+            values.iter().zip(in_mask).filter(|&(_, &keep)| keep).map(|(value, _)| *value).collect()
+        };
+        let keep_numbers = |values : &Vec<f32>| -> Vec<f32> {
+            values.iter().zip(in_mask).filter(|&(_, &keep)| keep).map(|(value, _)| *value).collect()
+        };
+        Frame {
+            _positions  : keep(&self._positions),
+            _lattice    : self._lattice,
+            _forces     : self._forces.as_ref().map(&keep),
+            _velocities : self._velocities.as_ref().map(&keep),
+            _energy     : self._energy,
+            _properties : self._properties.iter()
+                .map(|&(ref name, ref values)| (name.clone(), keep_numbers(values)))
+                .collect(),
+        }
+    }
+}
+
+// ============================================================
+// Frame index
+// ============================================================
+/// Turns the text of one frame into a Frame.
+type Parse = dyn Fn(&str) -> Result<Frame, String>;
+
+/// The frames of a trajectory file too big to read all at once. A first pass
+/// finds where each frame starts, and frames are read and parsed when wanted,
+/// keeping the few most recently used.
+pub struct FrameIndex {
+    _path         : String,
+    /// Where each frame starts in the file, then where the last one ends.
+    _offsets      : Vec<u64>,
+    /// Turns the text of one frame into a Frame, with whatever it needs from
+    /// the rest of the file, e.g. a cell given once at the top.
+    _parse        : Rc<Parse>,
+    /// The atoms to keep from each frame, if some have been deleted.
+    _mask         : Option<Vec<bool>>,
+    /// Recently read frames, least recently used first.
+    _cache        : Vec<(usize, Frame)>,
+    _capacity     : usize,
+    /// How the file is compressed, found once when it is indexed.
+    _compression  : Compression,
+    /// A compressed file, which cannot be seeked, decompressed when the first frame
+    /// is read. It is shared with any subsets, so this happens once.
+    _decompressed : Rc<RefCell<Option<Decompressed>>>,
+}
+
+impl FrameIndex {
+    pub fn new<F>(in_path : &str, in_offsets : Vec<u64>, in_parse : F) -> FrameIndex
+        where F : Fn(&str) -> Result<Frame, String> + 'static
+    {
+        FrameIndex {
+            _path         : in_path.to_string(),
+            _offsets      : in_offsets,
+            _parse        : Rc::new(in_parse),
+            _mask         : None,
+            _cache        : Vec::new(),
+            _capacity     : 16,
+            // a file that can't be read will say so when a frame is wanted
+            _compression  : compression::detect(in_path).unwrap_or(Compression::None),
+            _decompressed : Rc::new(RefCell::new(None)),
+        }
+    }
+
+    pub fn len(&self) -> usize {self._offsets.len().max(1)-1}
+
+    /// Frame in_index, from the cache or else from the file.
+    pub fn get(&mut self, in_index : &usize) -> Result<Frame, String> {
+        if let Some(position) = self._cache.iter().position(|entry| entry.0 == *in_index) {
+            let entry = self._cache.remove(position);
+            let frame = entry.1.clone();
+            self._cache.push(entry);
+            return Ok(frame);
+        }
+        let text = self.text(in_index)?;
+        let mut frame = (self._parse)(&text)
+            .map_err(|e| format!("Could not read frame {} of {}: {}", in_index+1, self._path, e))?;
+        if let Some(ref mask) = self._mask {
+            frame = frame.subset(mask);
+        }
+        if self._cache.len() == self._capacity {
+            self._cache.remove(0);
+        }
+        self._cache.push((*in_index, frame.clone()));
+        Ok(frame)
+    }
+
+    /// The text of frame in_index, straight from the file.
+    pub fn text(&mut self, in_index : &usize) -> Result<String, String> {
+        let start = self._offsets[*in_index];
+        let end = self._offsets[in_index+1];
+        let path = self._path.clone();
+        let error = |e : io::Error| format!("Could not read frame {} of {}: {}", in_index+1, path, e);
+        let file_path = if self._compression == Compression::None {
+            PathBuf::from(&self._path)
+        } else {
+            let mut decompressed = self._decompressed.borrow_mut();
+            if decompressed.is_none() {
+                *decompressed = Some(Decompressed::new(&self._path).map_err(&error)?);
+            }
+            decompressed.as_ref().unwrap()._path.clone()
+        };
+        let mut text = String::new();
+        let mut file = File::open(&file_path).map_err(&error)?;
+        file.seek(SeekFrom::Start(start)).map_err(&error)?;
+        file.take(end-start).read_to_string(&mut text).map_err(&error)?;
+        Ok(text)
+    }
+
+    /// The same frames, with just the atoms in in_mask.
+    pub fn subset(&self, in_mask : &[bool]) -> FrameIndex {
+        // in_mask covers the atoms already kept, so it is spread over the original ones
+        let mask = match self._mask {
+            Some(ref mask) => {
+                let mut kept = in_mask.iter();
+                mask.iter().map(|&x| x && *kept.next().unwrap()).collect()
+            },
+            None => in_mask.to_vec(),
+        };
+        FrameIndex {
+            _path         : self._path.clone(),
+            _offsets      : self._offsets.clone(),
+            _parse        : self._parse.clone(),
+            _mask         : Some(mask),
+            _cache        : Vec::new(),
+            _capacity     : self._capacity,
+            _compression  : self._compression,
+            _decompressed : self._decompressed.clone(),
+        }
+    }
+}
+
+/// A compressed trajectory decompressed into a temporary file, so that its frames
+/// can be read in any order. The file is removed when the last index using it goes.
+struct Decompressed {
+    _path : PathBuf,
+}
+
+impl Decompressed {
+    fn new(in_path : &str) -> io::Result<Decompressed> {
+        static COUNT : AtomicUsize = AtomicUsize::new(0);
+        let name = format!("oxide-{}-{}", process::id(), COUNT.fetch_add(1, Ordering::SeqCst));
+        let decompressed = Decompressed {_path : env::temp_dir().join(name)};
+        let mut reader = compression::open(in_path)?;
+        io::copy(&mut reader, &mut File::create(&decompressed._path)?)?;
+        Ok(decompressed)
+    }
+}
+
+impl Drop for Decompressed {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self._path);
+    }
+}
+
+// ============================================================
+// Frames
+// ============================================================
+/// A trajectory's frames, either all in memory or read from the file as needed.
+pub enum Frames {
+    InMemory(Vec<Frame>),
+    OnDisk(FrameIndex),
+}
+
+impl Frames {
+    pub fn len(&self) -> usize {
+        match *self {
+            Frames::InMemory(ref frames) => frames.len(),
+            Frames::OnDisk(ref index) => index.len(),
+        }
+    }
+
+    pub fn get(&mut self, in_index : &usize) -> Result<Frame, String> {
+        match *self {
+            Frames::InMemory(ref frames) => Ok(frames[*in_index].clone()),
+            Frames::OnDisk(ref mut index) => index.get(in_index),
+        }
+    }
+
+    /// The same frames, with just the atoms in in_mask.
+    pub fn subset(&self, in_mask : &[bool]) -> Frames {
+        match *self {
+            Frames::InMemory(ref frames) => Frames::InMemory(frames.iter().map(|frame| frame.subset(in_mask)).collect()),
+            Frames::OnDisk(ref index) => Frames::OnDisk(index.subset(in_mask)),
+        }
+    }
+}
+
+// ============================================================
+// Playback
+// ============================================================
+/// Plays a trajectory at a steady number of frames a second,
+/// stopping at the end or going round again.
+pub struct Playback {
+    _playing           : bool,
+    _frames_per_second : f32,
+    _looping           : bool,
+    /// When the last frame was stepped to, while playing.
+    _last_step         : Instant,
+}
+
+impl Playback {
+    pub fn new() -> Playback {
+        Playback {
+            _playing           : false,
+            _frames_per_second : 10.0,
+            _looping           : true,
+            _last_step         : Instant::now(),
+        }
+    }
+
+    pub fn playing(&self) -> bool {self._playing}
+    pub fn frames_per_second(&self) -> &f32 {&self._frames_per_second}
+    pub fn looping(&self) -> bool {self._looping}
+
+    pub fn set_playing(&mut self, in_playing : &bool) {
+        self._playing = *in_playing;
+        self._last_step = Instant::now();
+    }
+
+    pub fn toggle_looping(&mut self) {self._looping = !self._looping}
+
+    /// Doubles the speed, up to 120 frames a second.
+    pub fn faster(&mut self) {self._frames_per_second = (2.0*self._frames_per_second).min(120.0)}
+
+    /// Halves the speed, down to one frame every 4 seconds.
+    pub fn slower(&mut self) {self._frames_per_second = (0.5*self._frames_per_second).max(0.25)}
+
+    /// The frame after in_frame, going round to the first after the last if looping.
+    pub fn next_frame(&self, in_frame : &usize, in_frame_count : &usize) -> usize {
+        if in_frame+1 < *in_frame_count {
+            in_frame+1
+        } else if self._looping {
+            0
+        } else {
+            *in_frame
+        }
+    }
+
+    /// The frame before in_frame, going round to the last before the first if looping.
+    pub fn previous_frame(&self, in_frame : &usize, in_frame_count : &usize) -> usize {
+        if *in_frame > 0 {
+            in_frame-1
+        } else if self._looping {
+            in_frame_count-1
+        } else {
+            0
+        }
+    }
+
+    /// The frame to show now, if it is time to move on from in_frame. Call once a frame.
+    /// Playing stops at the last frame unless looping.
+    pub fn tick(&mut self, in_frame : &usize, in_frame_count : &usize) -> Option<usize> {
+        if !self._playing || *in_frame_count < 2 {return None;}
+        if self._last_step.elapsed().as_secs_f32() < 1.0/self._frames_per_second {return None;}
+        self._last_step = Instant::now();
+        let frame = self.next_frame(in_frame, in_frame_count);
+        if frame == *in_frame {
+            self._playing = false;
+            return None;
+        }
+        Some(frame)
+    }
+}
@@ -0,0 +1,30 @@
+// ============================================================
+// Vectors
+// ============================================================
+// Helpers for 3-vectors held as plain arrays.
+pub fn sub(in_a : &[f32;3], in_b : &[f32;3]) -> [f32;3] {
+    [in_a[0]-in_b[0], in_a[1]-in_b[1], in_a[2]-in_b[2]]
+}
+
+pub fn dot(in_a : &[f32;3], in_b : &[f32;3]) -> f32 {
+    in_a[0]*in_b[0]+in_a[1]*in_b[1]+in_a[2]*in_b[2]
+}
+
+pub fn cross(in_a : &[f32;3], in_b : &[f32;3]) -> [f32;3] {
+    [
+        in_a[1]*in_b[2]-in_a[2]*in_b[1],
+        in_a[2]*in_b[0]-in_a[0]*in_b[2],
+        in_a[0]*in_b[1]-in_a[1]*in_b[0],
+    ]
+}
+
+pub fn scale(in_a : &[f32;3], in_factor : &f32) -> [f32;3] {
+    [in_a[0]*in_factor, in_a[1]*in_factor, in_a[2]*in_factor]
+}
+
+pub fn length(in_a : &[f32;3]) -> f32 {dot(in_a, in_a).sqrt()}
+
+pub fn normalised(in_a : &[f32;3]) -> [f32;3] {
+    let l = length(in_a);
+    [in_a[0]/l, in_a[1]/l, in_a[2]/l]
+}
@@ -0,0 +1,153 @@
+//! Named camera bookmarks, kept in a small text file so that a view
+//! (e.g. the one used for a figure) can be reproduced exactly later.
+//!
+//! The file is a list of sections, one per view:
+//!
+//! ```text
+//! [figure3]
+//! focus = 0.0 0.0 0.0
+//! r = 15.2
+//! quaternion = 1.0 0.0 0.0 0.0
+//! field_of_view = 90.0
+//! projection = perspective
+//! view_scale = 10.1
+//! near = 1.0
+//! far = 30.0
+//! ```
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use camera::CameraState;
+use quaternion::Quaternion;
+
+pub struct ViewBookmarks {
+    _path  : PathBuf,
+    _views : Vec<(String, CameraState)>,
+}
+
+impl ViewBookmarks {
+    /// Reads the bookmarks in in_path. A missing file just means no bookmarks yet.
+    pub fn load(in_path : &Path) -> Result<ViewBookmarks, String> {
+        let mut bookmarks = ViewBookmarks {
+            _path  : in_path.to_owned(),
+            _views : Vec::new(),
+        };
+        let mut text = String::new();
+        match File::open(in_path) {
+            Ok(mut file) => {
+                if let Err(e) = file.read_to_string(&mut text) {
+                    return Err(format!("{}: {}", in_path.display(), e));
+                }
+            },
+            Err(_) => return Ok(bookmarks),
+        }
+
+        let mut current : Option<(String, Vec<(usize, String, String)>)> = None;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {continue;}
+            if line.starts_with('[') && line.ends_with(']') {
+                if let Some((name, fields)) = current.take() {
+                    bookmarks._views.push((name.clone(), parse_state(in_path, &name, &fields)?));
+                }
+                current = Some((line[1..line.len()-1].trim().to_string(), Vec::new()));
+            } else {
+                let mut parts = line.splitn(2, '=');
+                let key = parts.next().unwrap().trim().to_string();
+                let value = match parts.next() {
+                    Some(value) => value.trim().to_string(),
+                    None => return Err(format!("{}:{}: expected key = value", in_path.display(), i+1)),
+                };
+                match current {
+                    Some((_, ref mut fields)) => fields.push((i+1, key, value)),
+                    None => return Err(format!("{}:{}: \"{}\" is not inside a [view]", in_path.display(), i+1, key)),
+                }
+            }
+        }
+        if let Some((name, fields)) = current.take() {
+            bookmarks._views.push((name.clone(), parse_state(in_path, &name, &fields)?));
+        }
+        Ok(bookmarks)
+    }
+
+    pub fn path(&self) -> &Path {&self._path}
+
+    pub fn get(&self, in_name : &str) -> Option<&CameraState> {
+        self._views.iter().find(|view| view.0 == in_name).map(|view| &view.1)
+    }
+
+    /// Adds or replaces a bookmark, and writes the file straight away.
+    pub fn set(&mut self, in_name : &str, in_state : &CameraState) -> Result<(), String> {
+        match self._views.iter_mut().find(|view| view.0 == in_name) {
+            Some(view) => view.1 = *in_state,
+            None => self._views.push((in_name.to_string(), *in_state)),
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let mut text = String::from("# Oxide saved views\n");
+        for &(ref name, ref state) in &self._views {
+            // {:?} prints floats with enough digits to read them back exactly
+            let q = &state.quaternion;
+            text.push_str(&format!("\n[{}]\n", name));
+            text.push_str(&format!("focus = {:?} {:?} {:?}\n", state.focus[0], state.focus[1], state.focus[2]));
+            text.push_str(&format!("r = {:?}\n", state.r));
+            text.push_str(&format!("quaternion = {:?} {:?} {:?} {:?}\n", q.r(), q.i(), q.j(), q.k()));
+            text.push_str(&format!("field_of_view = {:?}\n", state.field_of_view_degrees));
+            text.push_str(&format!(
+                "projection = {}\n",
+                if state.orthographic {"orthographic"} else {"perspective"}
+            ));
+            text.push_str(&format!("view_scale = {:?}\n", state.view_scale));
+            text.push_str(&format!("near = {:?}\n", state.near_plane));
+            text.push_str(&format!("far = {:?}\n", state.far_plane));
+        }
+        File::create(&self._path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .map_err(|e| format!("{}: {}", self._path.display(), e))
+    }
+}
+
+fn parse_state(
+    in_path   : &Path,
+    in_name   : &str,
+    in_fields : &Vec<(usize, String, String)>,
+) -> Result<CameraState, String> {
+    let field = |key : &str| -> Result<&(usize, String, String), String> {
+        in_fields.iter().find(|field| field.1 == key).ok_or(format!(
+            "{}: view [{}] has no \"{}\"", in_path.display(), in_name, key
+        ))
+    };
+    let numbers = |key : &str, count : usize| -> Result<Vec<f32>, String> {
+        let &(line, _, ref value) = field(key)?;
+        let numbers : Vec<f32> = value.split_whitespace().filter_map(|x| x.parse().ok()).collect();
+        if numbers.len() != count || value.split_whitespace().count() != count {
+            return Err(format!("{}:{}: {} should be {} number(s)", in_path.display(), line, key, count));
+        }
+        Ok(numbers)
+    };
+
+    let focus = numbers("focus", 3)?;
+    let q = numbers("quaternion", 4)?;
+    let &(line, _, ref projection) = field("projection")?;
+    let orthographic = match projection.as_str() {
+        "orthographic" => true,
+        "perspective" => false,
+        _ => return Err(format!(
+            "{}:{}: projection should be perspective or orthographic", in_path.display(), line
+        )),
+    };
+    Ok(CameraState {
+        focus                 : [focus[0], focus[1], focus[2]],
+        r                     : numbers("r", 1)?[0],
+        quaternion            : Quaternion::new(&q[0], &q[1], &q[2], &q[3]),
+        field_of_view_degrees : numbers("field_of_view", 1)?[0],
+        orthographic          : orthographic,
+        view_scale            : numbers("view_scale", 1)?[0],
+        near_plane            : numbers("near", 1)?[0],
+        far_plane             : numbers("far", 1)?[0],
+    })
+}